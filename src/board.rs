@@ -0,0 +1,448 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Board information
+//!
+//! Decoding of the board related values reported by the mailbox property tag interface.
+//!
+
+/// The Raspberry Pi board model. The model codes are the type field of the new-style revision codes as
+/// documented at https://www.raspberrypi.org/documentation/hardware/raspberrypi/revision-codes/README.md. The
+/// value reported by the board model property tag has no documented encoding, so it is only kept as
+/// [BoardModel::Unknown] if the board revision does not provide the model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BoardModel {
+    A,
+    B,
+    APlus,
+    BPlus,
+    Pi2B,
+    Alpha,
+    Cm1,
+    Pi3B,
+    Zero,
+    Cm3,
+    ZeroW,
+    Pi3BPlus,
+    Pi3APlus,
+    Cm3Plus,
+    Pi4B,
+    Zero2W,
+    Pi400,
+    Cm4,
+    /// A model code not known to this crate
+    Unknown(u32),
+}
+
+impl BoardModel {
+    /// The human readable name of the board model
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoardModel::A => "Raspberry Pi A",
+            BoardModel::B => "Raspberry Pi B",
+            BoardModel::APlus => "Raspberry Pi A+",
+            BoardModel::BPlus => "Raspberry Pi B+",
+            BoardModel::Pi2B => "Raspberry Pi 2B",
+            BoardModel::Alpha => "Raspberry Pi Alpha",
+            BoardModel::Cm1 => "Raspberry Pi Compute Module 1",
+            BoardModel::Pi3B => "Raspberry Pi 3B",
+            BoardModel::Zero => "Raspberry Pi Zero",
+            BoardModel::Cm3 => "Raspberry Pi Compute Module 3",
+            BoardModel::ZeroW => "Raspberry Pi Zero W",
+            BoardModel::Pi3BPlus => "Raspberry Pi 3B+",
+            BoardModel::Pi3APlus => "Raspberry Pi 3A+",
+            BoardModel::Cm3Plus => "Raspberry Pi Compute Module 3+",
+            BoardModel::Pi4B => "Raspberry Pi 4B",
+            BoardModel::Zero2W => "Raspberry Pi Zero 2 W",
+            BoardModel::Pi400 => "Raspberry Pi 400",
+            BoardModel::Cm4 => "Raspberry Pi Compute Module 4",
+            BoardModel::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// Determine the board model from the board revision code. The value reported by the board model property
+    /// tag is only kept as the raw code of an unknown model if the revision code is not a new-style one.
+    pub(crate) fn from_tags(model: u32, revision: u32) -> Self {
+        BoardRevision::from(revision)
+            .model()
+            .unwrap_or(BoardModel::Unknown(model))
+    }
+
+    /// The type field of the revision code of this model or the raw code of an unknown model
+    pub fn code(&self) -> u32 {
+        match self {
+            BoardModel::A => 0x00,
+            BoardModel::B => 0x01,
+            BoardModel::APlus => 0x02,
+            BoardModel::BPlus => 0x03,
+            BoardModel::Pi2B => 0x04,
+            BoardModel::Alpha => 0x05,
+            BoardModel::Cm1 => 0x06,
+            BoardModel::Pi3B => 0x08,
+            BoardModel::Zero => 0x09,
+            BoardModel::Cm3 => 0x0a,
+            BoardModel::ZeroW => 0x0c,
+            BoardModel::Pi3BPlus => 0x0d,
+            BoardModel::Pi3APlus => 0x0e,
+            BoardModel::Cm3Plus => 0x10,
+            BoardModel::Pi4B => 0x11,
+            BoardModel::Zero2W => 0x12,
+            BoardModel::Pi400 => 0x13,
+            BoardModel::Cm4 => 0x14,
+            BoardModel::Unknown(code) => *code,
+        }
+    }
+}
+
+/// Decode the type field of a new-style revision code
+impl From<u32> for BoardModel {
+    fn from(code: u32) -> Self {
+        match code {
+            0x00 => BoardModel::A,
+            0x01 => BoardModel::B,
+            0x02 => BoardModel::APlus,
+            0x03 => BoardModel::BPlus,
+            0x04 => BoardModel::Pi2B,
+            0x05 => BoardModel::Alpha,
+            0x06 => BoardModel::Cm1,
+            0x08 => BoardModel::Pi3B,
+            0x09 => BoardModel::Zero,
+            0x0a => BoardModel::Cm3,
+            0x0c => BoardModel::ZeroW,
+            0x0d => BoardModel::Pi3BPlus,
+            0x0e => BoardModel::Pi3APlus,
+            0x10 => BoardModel::Cm3Plus,
+            0x11 => BoardModel::Pi4B,
+            0x12 => BoardModel::Zero2W,
+            0x13 => BoardModel::Pi400,
+            0x14 => BoardModel::Cm4,
+            _ => BoardModel::Unknown(code),
+        }
+    }
+}
+
+/// The Raspberry Pi board family. Boards of the same family share the same SoC and therefore the same
+/// peripheral layout.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Board {
+    /// Raspberry Pi 1 models A, B, A+, B+ and the Compute Module 1
+    Pi1,
+    /// Raspberry Pi Zero and Zero W
+    Zero,
+    /// Raspberry Pi 2B
+    Pi2,
+    /// Raspberry Pi 3 models, the Compute Module 3 variants and the Zero 2 W
+    Pi3,
+    /// Raspberry Pi 4B, 400 and the Compute Module 4
+    Pi4,
+    /// A board not known to this crate
+    Unknown,
+}
+
+impl Board {
+    /// Determine the board from the board revision code. Check
+    /// https://www.raspberrypi.org/documentation/hardware/raspberrypi/revision-codes/README.md for the
+    /// encoding of it.
+    pub fn from_revision(revision: u32) -> Self {
+        let model = match BoardRevision::from(revision).model() {
+            Some(model) => model,
+            // all old-style revision codes belong to Raspberry Pi 1 models
+            None => {
+                return match revision & 0xFF_FFFF {
+                    0x0002..=0x0015 => Board::Pi1,
+                    _ => Board::Unknown,
+                }
+            }
+        };
+        match model {
+            BoardModel::A
+            | BoardModel::B
+            | BoardModel::APlus
+            | BoardModel::BPlus
+            | BoardModel::Alpha
+            | BoardModel::Cm1 => Board::Pi1,
+            BoardModel::Zero | BoardModel::ZeroW => Board::Zero,
+            BoardModel::Pi2B => Board::Pi2,
+            BoardModel::Pi3B
+            | BoardModel::Pi3BPlus
+            | BoardModel::Pi3APlus
+            | BoardModel::Cm3
+            | BoardModel::Cm3Plus
+            | BoardModel::Zero2W => Board::Pi3,
+            BoardModel::Pi4B | BoardModel::Pi400 | BoardModel::Cm4 => Board::Pi4,
+            BoardModel::Unknown(_) => Board::Unknown,
+        }
+    }
+
+    /// The ARM physical base address of the peripherals of this board if known
+    pub fn peripheral_base(&self) -> Option<usize> {
+        match self {
+            Board::Pi1 | Board::Zero => Some(0x2000_0000),
+            Board::Pi2 | Board::Pi3 => Some(0x3F00_0000),
+            Board::Pi4 => Some(0xFE00_0000),
+            Board::Unknown => None,
+        }
+    }
+}
+
+/// The manufacturer of the board as given in the new-style revision codes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Manufacturer {
+    SonyUk,
+    Egoman,
+    Embest,
+    SonyJapan,
+    Stadium,
+    /// A manufacturer code not known to this crate
+    Unknown(u32),
+}
+
+impl Manufacturer {
+    /// The human readable name of the manufacturer
+    pub fn name(&self) -> &'static str {
+        match self {
+            Manufacturer::SonyUk => "Sony UK",
+            Manufacturer::Egoman => "Egoman",
+            Manufacturer::Embest => "Embest",
+            Manufacturer::SonyJapan => "Sony Japan",
+            Manufacturer::Stadium => "Stadium",
+            Manufacturer::Unknown(_) => "Unknown",
+        }
+    }
+}
+
+impl From<u32> for Manufacturer {
+    fn from(code: u32) -> Self {
+        match code {
+            0x0 => Manufacturer::SonyUk,
+            0x1 => Manufacturer::Egoman,
+            // Embest is given with two different codes
+            0x2 | 0x4 => Manufacturer::Embest,
+            0x3 => Manufacturer::SonyJapan,
+            0x5 => Manufacturer::Stadium,
+            _ => Manufacturer::Unknown(code),
+        }
+    }
+}
+
+/// The SoC of the board as given in the new-style revision codes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Processor {
+    Bcm2835,
+    Bcm2836,
+    Bcm2837,
+    Bcm2711,
+    /// A processor code not known to this crate
+    Unknown(u32),
+}
+
+impl Processor {
+    /// The human readable name of the processor
+    pub fn name(&self) -> &'static str {
+        match self {
+            Processor::Bcm2835 => "BCM2835",
+            Processor::Bcm2836 => "BCM2836",
+            Processor::Bcm2837 => "BCM2837",
+            Processor::Bcm2711 => "BCM2711",
+            Processor::Unknown(_) => "Unknown",
+        }
+    }
+}
+
+impl From<u32> for Processor {
+    fn from(code: u32) -> Self {
+        match code {
+            0x0 => Processor::Bcm2835,
+            0x1 => Processor::Bcm2836,
+            0x2 => Processor::Bcm2837,
+            0x3 => Processor::Bcm2711,
+            _ => Processor::Unknown(code),
+        }
+    }
+}
+
+/// The board revision code as reported by [Mailbox::get_board_revision](crate::Mailbox::get_board_revision). The
+/// fields are only available with the new-style revision codes, old-style revision codes just enumerate the boards.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoardRevision(pub u32);
+
+impl BoardRevision {
+    /// Whether this is a new-style revision code, indicated by bit 23
+    pub fn is_new_style(&self) -> bool {
+        self.0 & (1 << 23) != 0
+    }
+
+    /// The board model given in bits 4..11 of a new-style revision code
+    pub fn model(&self) -> Option<BoardModel> {
+        self.field(4, 0xFF).map(BoardModel::from)
+    }
+
+    /// The manufacturer given in bits 16..19 of a new-style revision code
+    pub fn manufacturer(&self) -> Option<Manufacturer> {
+        self.field(16, 0xF).map(Manufacturer::from)
+    }
+
+    /// The processor given in bits 12..15 of a new-style revision code
+    pub fn processor(&self) -> Option<Processor> {
+        self.field(12, 0xF).map(Processor::from)
+    }
+
+    /// The raw value of the field at the given bit position of a new-style revision code
+    fn field(&self, shift: u32, mask: u32) -> Option<u32> {
+        if self.is_new_style() {
+            Some((self.0 >> shift) & mask)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<u32> for BoardRevision {
+    fn from(revision: u32) -> Self {
+        Self(revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, Mailbox, PropertyTagId};
+
+    #[test]
+    fn decode_known_board_models() {
+        assert_eq!(BoardModel::from(0x04), BoardModel::Pi2B);
+        assert_eq!(BoardModel::from(0x08).name(), "Raspberry Pi 3B");
+        assert_eq!(BoardModel::from(0x09).name(), "Raspberry Pi Zero");
+        assert_eq!(BoardModel::from(0x11).name(), "Raspberry Pi 4B");
+        assert_eq!(BoardModel::from(0x0d).code(), 0x0d);
+    }
+
+    #[test]
+    fn decode_unknown_board_model() {
+        let model = BoardModel::from(0x42);
+        assert_eq!(model, BoardModel::Unknown(0x42));
+        assert_eq!(model.name(), "Unknown");
+        assert_eq!(model.code(), 0x42);
+    }
+
+    #[test]
+    fn board_from_revision() {
+        // old-style Raspberry Pi 1 B+
+        assert_eq!(Board::from_revision(0x0010), Board::Pi1);
+        // Raspberry Pi 2B 1GB Sony
+        assert_eq!(Board::from_revision(0xa01041), Board::Pi2);
+        // Raspberry Pi 3B+ 1GB Sony
+        assert_eq!(Board::from_revision(0xa020d3), Board::Pi3);
+        // Raspberry Pi Zero W 512MB Sony
+        assert_eq!(Board::from_revision(0x9000c1), Board::Zero);
+        // Raspberry Pi 4B 4GB Sony
+        assert_eq!(Board::from_revision(0xc03111), Board::Pi4);
+        assert_eq!(Board::from_revision(0xc03ff1), Board::Unknown);
+        assert_eq!(Board::from_revision(0x0001), Board::Unknown);
+    }
+
+    #[test]
+    fn board_peripheral_base() {
+        assert_eq!(Board::Zero.peripheral_base(), Some(0x2000_0000));
+        assert_eq!(Board::Pi3.peripheral_base(), Some(0x3F00_0000));
+        assert_eq!(Board::Pi4.peripheral_base(), Some(0xFE00_0000));
+        assert_eq!(Board::Unknown.peripheral_base(), None);
+    }
+
+    #[test]
+    fn revision_manufacturer_and_processor() {
+        // Raspberry Pi 2B 1GB Sony UK
+        let revision = BoardRevision::from(0xa01041);
+        assert_eq!(revision.manufacturer(), Some(Manufacturer::SonyUk));
+        assert_eq!(revision.processor(), Some(Processor::Bcm2836));
+        assert_eq!(revision.model(), Some(BoardModel::Pi2B));
+        // Raspberry Pi 2B 1GB Embest
+        let revision = BoardRevision::from(0xa21041);
+        assert_eq!(revision.manufacturer(), Some(Manufacturer::Embest));
+        // Raspberry Pi 3B 1GB Embest
+        let revision = BoardRevision::from(0xa22082);
+        assert_eq!(revision.manufacturer(), Some(Manufacturer::Embest));
+        assert_eq!(revision.processor(), Some(Processor::Bcm2837));
+        // Raspberry Pi 3B 1GB Sony Japan
+        assert_eq!(
+            BoardRevision::from(0xa32082)
+                .manufacturer()
+                .map(|m| m.name()),
+            Some("Sony Japan")
+        );
+        // Raspberry Pi 3B 1GB Stadium
+        assert_eq!(
+            BoardRevision::from(0xa52082).manufacturer(),
+            Some(Manufacturer::Stadium)
+        );
+        // Raspberry Pi 4B 4GB Sony UK
+        let revision = BoardRevision::from(0xc03111);
+        assert_eq!(revision.processor().map(|p| p.name()), Some("BCM2711"));
+        // Raspberry Pi Zero W 512MB Sony UK
+        assert_eq!(
+            BoardRevision::from(0x9000c1).processor(),
+            Some(Processor::Bcm2835)
+        );
+        assert_eq!(
+            BoardRevision::from(0xa07041).processor(),
+            Some(Processor::Unknown(0x7))
+        );
+    }
+
+    #[test]
+    fn old_style_revision_without_fields() {
+        // old-style Raspberry Pi 1 B+
+        let revision = BoardRevision::from(0x0010);
+        assert!(!revision.is_new_style());
+        assert_eq!(revision.manufacturer(), None);
+        assert_eq!(revision.processor(), None);
+        assert_eq!(revision.model(), None);
+    }
+
+    #[test]
+    fn board_model_is_decoded() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardModelGet, &[0])
+            .respond(PropertyTagId::BoardRevisionGet, &[0xc03111])
+            .install();
+        let mut mb = Mailbox::new();
+        let model = mb.get_board_model().unwrap();
+        assert_eq!(model, BoardModel::Pi4B);
+        assert_eq!(model.code(), 0x11);
+
+        // without a new-style revision code the reported model is kept raw
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardModelGet, &[0])
+            .respond(PropertyTagId::BoardRevisionGet, &[0x0010])
+            .install();
+        let model = mb.get_board_model().unwrap();
+        assert_eq!(model, BoardModel::Unknown(0));
+        assert_eq!(model.name(), "Unknown");
+    }
+
+    #[test]
+    fn detect_board_from_revision() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardModelGet, &[0])
+            .respond(PropertyTagId::BoardRevisionGet, &[0xa020d3])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.detect_board().unwrap(), Board::Pi3);
+    }
+
+    #[test]
+    fn detect_unknown_board_without_revision() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardModelGet, &[0])
+            .respond(PropertyTagId::BoardRevisionGet, &[0])
+            .install();
+        let mut mb = Mailbox::new();
+        let board = mb.detect_board().unwrap();
+        assert_eq!(board, Board::Unknown);
+        assert_eq!(board.peripheral_base(), None);
+    }
+}
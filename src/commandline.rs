@@ -0,0 +1,192 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Command Line
+//!
+//! The command line the firmware passes to the kernel. It is a space separated list of ``key=value`` pairs and
+//! flags without a value.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let command_line = mb.get_command_line().unwrap();
+//!     if let Some(console) = command_line.get("console") {
+//!         println!("console: {}", console);
+//!     }
+//! }
+//! ```
+//!
+
+/// The maximum length of the command line that could be retrieved from the VideoCore
+pub const COMMAND_LINE_LENGTH: usize = 1024;
+
+/// The command line as it has been retrieved from the VideoCore
+#[derive(Clone)]
+pub struct CommandLine {
+    buffer: [u8; COMMAND_LINE_LENGTH],
+    len: usize,
+}
+
+impl CommandLine {
+    /// Create the command line from the raw characters provided by the VideoCore. The characters are taken
+    /// up to the first 0 byte. Returns ``None`` if the characters are not valid UTF-8.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let len = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len())
+            .min(COMMAND_LINE_LENGTH);
+        core::str::from_utf8(&bytes[..len]).ok()?;
+        let mut buffer = [0; COMMAND_LINE_LENGTH];
+        buffer[..len].copy_from_slice(&bytes[..len]);
+        Some(Self { buffer, len })
+    }
+
+    /// The whole command line
+    pub fn as_str(&self) -> &str {
+        // the content has been validated on creation
+        core::str::from_utf8(&self.buffer[..self.len]).unwrap_or_default()
+    }
+
+    /// Iterate over the entries of the command line in the order they are given. Each entry is returned as
+    /// ``(key, value)``. The value of a flag without a ``=`` is ``None``.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.as_str()
+            .split_ascii_whitespace()
+            .map(|entry| match entry.find('=') {
+                Some(idx) => (&entry[..idx], Some(&entry[idx + 1..])),
+                None => (entry, None),
+            })
+    }
+
+    /// Get the value of the given key. If the key is given several times the last value wins, like the kernel
+    /// treats most of it's parameters. A flag without a value is returned as empty value. Returns ``None`` if
+    /// the key is not part of the command line.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter()
+            .filter(|&(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.unwrap_or(""))
+            .last()
+    }
+
+    /// Get all values of the given key in the order they are given in the command line. A flag without a value
+    /// is returned as empty value.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |&(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.unwrap_or(""))
+    }
+
+    /// Check whether the given key is part of the command line, either as flag or with a value
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl core::fmt::Debug for CommandLine {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("CommandLine").field(&self.as_str()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, Mailbox, MailboxError, PropertyTagId};
+
+    const RPI_COMMAND_LINE: &[u8] = b"coherent_pool=1M 8250.nr_uarts=1 snd_bcm2835.enable_compat_alsa=0 \
+        bcm2708_fb.fbwidth=656 bcm2708_fb.fbheight=416 vc_mem.mem_base=0x3ec00000 \
+        console=ttyS0,115200 console=tty1 root=PARTUUID=6c586e13-02 rootfstype=ext4 fsck.repair=yes \
+        rootwait quiet splash\0\0\0";
+
+    #[test]
+    fn get_values() {
+        let command_line = CommandLine::from_bytes(RPI_COMMAND_LINE).unwrap();
+        assert_eq!(command_line.get("coherent_pool"), Some("1M"));
+        assert_eq!(command_line.get("8250.nr_uarts"), Some("1"));
+        assert_eq!(command_line.get("vc_mem.mem_base"), Some("0x3ec00000"));
+        // only the first '=' separates the key from the value
+        assert_eq!(command_line.get("root"), Some("PARTUUID=6c586e13-02"));
+        assert_eq!(command_line.get("missing"), None);
+        assert!(command_line.as_str().ends_with("splash"));
+    }
+
+    #[test]
+    fn flags_without_value() {
+        let command_line = CommandLine::from_bytes(RPI_COMMAND_LINE).unwrap();
+        assert_eq!(command_line.get("rootwait"), Some(""));
+        assert!(command_line.contains("quiet"));
+        assert!(!command_line.contains("nosplash"));
+        assert_eq!(command_line.iter().last(), Some(("splash", None)));
+    }
+
+    #[test]
+    fn repeated_keys() {
+        let command_line = CommandLine::from_bytes(RPI_COMMAND_LINE).unwrap();
+        assert_eq!(command_line.get("console"), Some("tty1"));
+        assert!(command_line
+            .get_all("console")
+            .eq(["ttyS0,115200", "tty1"].iter().copied()));
+    }
+
+    #[test]
+    fn invalid_characters() {
+        assert!(CommandLine::from_bytes(b"console=\xFF").is_none());
+    }
+
+    /// firmware emulation responding with the given command line followed by the given filling
+    fn command_line_firmware(
+        command_line: &'static [u8],
+        size: u32,
+        filling: u8,
+    ) -> mock::Firmware {
+        mock::Firmware::new().on(PropertyTagId::CommandLineGet, move |values| {
+            let bytes = unsafe {
+                core::slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, values.len() * 4)
+            };
+            bytes.iter_mut().for_each(|byte| *byte = filling);
+            bytes[..command_line.len()].copy_from_slice(command_line);
+            Some(size)
+        })
+    }
+
+    #[test]
+    fn command_line_from_firmware() {
+        let command_line = b"console=tty1 root=/dev/mmcblk0p2 rootwait";
+        // anything behind the command line is not part of the response
+        command_line_firmware(command_line, command_line.len() as u32, b'x').install();
+
+        let mut mb = Mailbox::new();
+        let command_line = mb.get_command_line().unwrap();
+        assert_eq!(
+            command_line.as_str(),
+            "console=tty1 root=/dev/mmcblk0p2 rootwait"
+        );
+        assert_eq!(command_line.get("root"), Some("/dev/mmcblk0p2"));
+    }
+
+    #[test]
+    fn command_line_into_buffer() {
+        command_line_firmware(b"console=tty1\0", 13, 0).install();
+
+        let mut mb = Mailbox::new();
+        let mut buffer = [0xFF; 12];
+        assert_eq!(mb.get_command_line_into(&mut buffer), Ok(12));
+        assert_eq!(&buffer, b"console=tty1");
+
+        let mut buffer = [0xFF; 11];
+        assert_eq!(
+            mb.get_command_line_into(&mut buffer),
+            Err(MailboxError::BufferTooSmall { required: 12 })
+        );
+        assert!(buffer.iter().all(|&byte| byte == 0xFF));
+    }
+}
@@ -0,0 +1,131 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Diagnostic Report
+//!
+//! A summary of the board, firmware and the current operating state as reported by the VideoCore. This is meant
+//! to be attached to bug reports.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     println!("{}", mb.diagnostic_report().unwrap());
+//! }
+//! ```
+//!
+
+use crate::{BoardModel, ClockId, Hertz, MemoryRegion, VoltageId};
+use core::fmt;
+
+/// The values queried by [Mailbox::diagnostic_report](crate::Mailbox::diagnostic_report). The ``Display``
+/// implementation provides one labeled line for each value.
+#[derive(Copy, Clone, Debug)]
+pub struct DiagnosticReport {
+    /// The board model decoded from the board revision
+    pub board_model: BoardModel,
+    /// The raw board revision
+    pub board_revision: u32,
+    /// The firmware revision
+    pub firmware_revision: u32,
+    /// The memory split of the ARM
+    pub arm_memory: MemoryRegion,
+    /// The memory split of the VideoCore
+    pub vc_memory: MemoryRegion,
+    /// The current rate of each clock. Clocks not available on the board are reported with 0 Hz
+    pub clock_rates: [(ClockId, Hertz); 11],
+    /// The temperature in thousandths of a degree Celsius
+    pub temperature: u32,
+    /// The throttling state as reported by [Mailbox::get_throttled](crate::Mailbox::get_throttled)
+    pub throttled: u32,
+    /// The raw value of each voltage
+    pub voltages: [(VoltageId, u32); 4],
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "board model: {} ({:#x})",
+            self.board_model.name(),
+            self.board_model.code()
+        )?;
+        writeln!(f, "board revision: {:#010x}", self.board_revision)?;
+        writeln!(f, "firmware revision: {:#010x}", self.firmware_revision)?;
+        writeln!(f, "ARM memory: {}", self.arm_memory)?;
+        writeln!(f, "VideoCore memory: {}", self.vc_memory)?;
+        for (clock_id, rate) in self.clock_rates.iter() {
+            writeln!(f, "{}: {}", clock_id.name(), rate)?;
+        }
+        writeln!(
+            f,
+            "temperature: {}.{} °C",
+            self.temperature / 1000,
+            self.temperature % 1000 / 100
+        )?;
+        writeln!(f, "throttled: {:#010x}", self.throttled)?;
+        for (voltage_id, value) in self.voltages.iter() {
+            writeln!(f, "{} voltage: {}", voltage_id.name(), value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock, Mailbox, PropertyTagId};
+
+    #[test]
+    fn diagnostic_report_lines() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardModelGet, &[0])
+            .respond(PropertyTagId::BoardRevisionGet, &[0x00c0_3111])
+            .respond(PropertyTagId::FirmwareRevisionGet, &[0x60c6_f5ac])
+            .respond(PropertyTagId::ArmMemoryGet, &[0x0, 948 << 20])
+            .respond(PropertyTagId::VcMemoryGet, &[0x3B40_0000, 76 << 20])
+            .respond(PropertyTagId::TemperatureGet, &[0x0, 47_236])
+            .respond(PropertyTagId::ThrottledGet, &[0x5_0005])
+            .on(PropertyTagId::ClockrateGet, |values| {
+                values[1] = match values[0] {
+                    0x3 => 1_500_000_000,
+                    0x4 => 500_000_000,
+                    _ => 0,
+                };
+                Some(8)
+            })
+            .on(PropertyTagId::VoltageGet, |values| {
+                values[1] = 1_200_000 + values[0];
+                Some(8)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        let report = format!("{}", mb.diagnostic_report().unwrap());
+        for line in [
+            "board model: Raspberry Pi 4B (0x11)",
+            "board revision: 0x00c03111",
+            "firmware revision: 0x60c6f5ac",
+            "ARM memory: 948 MiB at 0x00000000",
+            "VideoCore memory: 76 MiB at 0x3b400000",
+            "ARM clock: 1500 MHz",
+            "Core clock: 500 MHz",
+            "EMMC2 clock: 0 Hz",
+            "temperature: 47.2 °C",
+            "throttled: 0x00050005",
+            "Core voltage: 1200001",
+            "SDRAM I/O voltage: 1200004",
+        ]
+        .iter()
+        {
+            assert!(report.lines().any(|l| l == *line), "missing line {}", line);
+        }
+        assert_eq!(report.lines().count(), 5 + 11 + 2 + 4);
+    }
+}
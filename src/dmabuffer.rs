@@ -0,0 +1,188 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # DMA Buffer
+//!
+//! Contiguous memory allocated by the VideoCore that could be accessed by the DMA controller. The memory stays
+//! locked at it's bus address until it is freed.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let buffer = mb.allocate_dma_buffer(4096, 4096).unwrap();
+//!     // set up the DMA control blocks at buffer.arm_address and pass buffer.bus_address to the DMA controller
+//!     if let Err((buffer, _)) = buffer.free(&mut mb) {
+//!         // the buffer is handed back, so freeing it could be retried
+//!         let _ = buffer.free(&mut mb);
+//!     }
+//! }
+//! ```
+//!
+
+use crate::{Mailbox, MailboxError, MemoryRelease, MemoryUnlock, PropertyTag};
+
+/// The flags the DMA memory is allocated with. The memory is accessed through the direct, uncached alias and is
+/// not allocated in the L2 cache of the VideoCore, so the ARM and the DMA controller see the same content.
+pub(crate) const DMA_MEMORY_FLAGS: u32 = 0xC;
+
+/// Memory allocated with [Mailbox::allocate_dma_buffer]. The memory is not released when the buffer is dropped,
+/// it need to be given back with [DmaBuffer::free].
+#[derive(Debug, PartialEq)]
+pub struct DmaBuffer {
+    /// The handle of the memory as provided by the VideoCore
+    pub handle: u32,
+    /// The bus address of the memory to be passed to the DMA controller
+    pub bus_address: u32,
+    /// The ARM physical address of the memory
+    pub arm_address: u32,
+    /// The size of the memory in bytes
+    pub size: u32,
+    /// Whether the memory is still locked at it's bus address
+    pub(crate) locked: bool,
+}
+
+impl DmaBuffer {
+    /// Unlock and release the memory of this buffer. The buffer must no longer be accessed by the DMA controller.
+    /// If the VideoCore fails to unlock or release the memory the buffer is handed back together with the error,
+    /// so freeing it could be retried. A buffer that has already been unlocked is only released on the retry.
+    pub fn free(mut self, mailbox: &mut Mailbox) -> Result<(), (Self, MailboxError)> {
+        if self.locked {
+            match mailbox.send_tag(MemoryUnlock::new(self.handle)) {
+                Ok(tag) if tag.response().status() == 0 => self.locked = false,
+                Ok(_) => return Err((self, MailboxError::ResponseError)),
+                Err(error) => return Err((self, error)),
+            }
+        }
+        match mailbox.send_tag(MemoryRelease::new(self.handle)) {
+            Ok(tag) if tag.response().status() == 0 => Ok(()),
+            Ok(_) => Err((self, MailboxError::ResponseError)),
+            Err(error) => Err((self, error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, PropertyTagId};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// firmware emulation of the memory property tags recording each tag with the handle passed. Locking the
+    /// memory fails if ``lockable`` is false.
+    fn memory_firmware(requests: Rc<RefCell<Vec<(u32, u32)>>>, lockable: bool) -> mock::Firmware {
+        let record =
+            move |tag: PropertyTagId, value: u32| requests.borrow_mut().push((tag as u32, value));
+        let (lock, unlock, release) = (record.clone(), record.clone(), record.clone());
+        mock::Firmware::new()
+            .on(PropertyTagId::MemoryAllocate, move |values| {
+                record(PropertyTagId::MemoryAllocate, values[2]);
+                values[0] = 7;
+                Some(4)
+            })
+            .on(PropertyTagId::MemoryLock, move |values| {
+                lock(PropertyTagId::MemoryLock, values[0]);
+                values[0] = if lockable { 0xC020_0000 } else { 0 };
+                Some(4)
+            })
+            .on(PropertyTagId::MemoryUnlock, move |values| {
+                unlock(PropertyTagId::MemoryUnlock, values[0]);
+                values[0] = 0;
+                Some(4)
+            })
+            .on(PropertyTagId::MemoryRelease, move |values| {
+                release(PropertyTagId::MemoryRelease, values[0]);
+                values[0] = 0;
+                Some(4)
+            })
+    }
+
+    #[test]
+    fn allocate_and_free() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        memory_firmware(requests.clone(), true).install();
+        let mut mb = Mailbox::new();
+
+        let buffer = mb.allocate_dma_buffer(4096, 4096).unwrap();
+        assert_eq!(
+            buffer,
+            DmaBuffer {
+                handle: 7,
+                bus_address: 0xC020_0000,
+                arm_address: 0x0020_0000,
+                size: 4096,
+                locked: true,
+            }
+        );
+        buffer.free(&mut mb).unwrap();
+
+        assert_eq!(
+            *requests.borrow(),
+            [
+                (PropertyTagId::MemoryAllocate as u32, DMA_MEMORY_FLAGS),
+                (PropertyTagId::MemoryLock as u32, 7),
+                (PropertyTagId::MemoryUnlock as u32, 7),
+                (PropertyTagId::MemoryRelease as u32, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn release_memory_that_could_not_be_locked() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        memory_firmware(requests.clone(), false).install();
+        let mut mb = Mailbox::new();
+
+        assert_eq!(
+            mb.allocate_dma_buffer(4096, 4096),
+            Err(MailboxError::ResponseError)
+        );
+        assert_eq!(
+            requests.borrow().last(),
+            Some(&(PropertyTagId::MemoryRelease as u32, 7))
+        );
+    }
+
+    #[test]
+    fn retry_free_after_failed_release() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let mut firmware = memory_firmware(requests.clone(), true);
+        let mut releases = 0;
+        mock::install(move |tag, values| {
+            let processed = firmware.handle(tag, values);
+            if tag == PropertyTagId::MemoryRelease as u32 {
+                releases += 1;
+                // the first release fails
+                if releases == 1 {
+                    values[0] = 1;
+                }
+            }
+            processed
+        });
+        let mut mb = Mailbox::new();
+
+        let buffer = mb.allocate_dma_buffer(4096, 4096).unwrap();
+        let (buffer, error) = buffer.free(&mut mb).unwrap_err();
+        assert_eq!(error, MailboxError::ResponseError);
+        assert_eq!(buffer.handle, 7);
+        assert!(!buffer.locked);
+        buffer.free(&mut mb).unwrap();
+
+        // the memory is unlocked once and released on the retry
+        assert_eq!(
+            requests.borrow()[2..],
+            [
+                (PropertyTagId::MemoryUnlock as u32, 7),
+                (PropertyTagId::MemoryRelease as u32, 7),
+                (PropertyTagId::MemoryRelease as u32, 7),
+            ]
+        );
+    }
+}
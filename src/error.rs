@@ -0,0 +1,134 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Mailbox errors
+//!
+//! The errors that could occur while communicating with the VideoCore through the mailbox. The [MailboxError]
+//! implements the ``Error`` trait of ``ruspiro-error`` so it can be converted into a ``BoxError`` with the ``?``
+//! operator.
+//!
+
+use crate::MailboxChannel;
+use core::fmt;
+
+/// The errors reported by the [Mailbox](crate::Mailbox)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MailboxError {
+    /// The VideoCore has not or only partially processed the message
+    ResponseError,
+    /// The channel could not be used to pass property tag messages
+    UnsupportedChannel(MailboxChannel),
+    /// The requested configuration or property tag is not supported by the firmware
+    Unsupported,
+    /// The size of the batch message exceeds the range of an u32
+    BatchTooLarge,
+    /// The message received from the VideoCore does not match the expected message
+    UnexpectedMessage,
+    /// The VideoCore was not able to allocate the requested memory
+    AllocationFailed,
+    /// The virtual offset would move the virtual view outside of the physical framebuffer
+    OffsetOutOfBounds,
+    /// The message buffer is placed outside of the memory the VideoCore is able to address
+    BufferUnreachable,
+    /// The expected state has not been reached in time
+    Timeout,
+    /// The message size reported back by the VideoCore differs from the size of the message sent
+    SizeMismatch {
+        /// The size in bytes of the message sent
+        expected: u32,
+        /// The size in bytes reported by the VideoCore
+        actual: u32,
+    },
+    /// The firmware reported a pixel order not known to this crate
+    UnknownPixelOrder(u32),
+    /// The firmware reported an alpha mode not known to this crate
+    UnknownAlphaMode(u32),
+    /// The firmware reported a pixel depth not known to this crate
+    UnknownDepth(u32),
+    /// The message buffer is not 16 byte aligned
+    MisalignedBuffer,
+    /// The buffer provided is too small to hold the response
+    BufferTooSmall {
+        /// The size in bytes the buffer need to have
+        required: usize,
+    },
+    /// The framebuffer property tags of a batch are in the wrong order or conflict with each other
+    InvalidFramebufferBatch,
+    /// The mailbox is currently in use by another caller
+    Busy,
+    /// The batch message does not contain any property tag
+    EmptyBatch,
+    /// The size of the property tag is not a multiple of 32 bits
+    BadTagSize,
+    /// The mailbox registers are not accessible, e.g. as they are not mapped into the address space
+    NotMapped,
+    /// The message buffer does not start with a valid header or the message size exceeds the buffer
+    InvalidHeader,
+    /// The memory reserved exceeds the memory available
+    ReservationTooLarge,
+}
+
+impl fmt::Display for MailboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailboxError::ResponseError => {
+                write!(f, "unable to send mailbox property tag message.")
+            }
+            MailboxError::UnsupportedChannel(channel) => write!(
+                f,
+                "mailbox channel {:?} not supported for property tag messages.",
+                channel
+            ),
+            MailboxError::Unsupported => write!(f, "not supported by the firmware."),
+            MailboxError::BatchTooLarge => {
+                write!(f, "mailbox property tag batch message too large.")
+            }
+            MailboxError::UnexpectedMessage => {
+                write!(f, "unexpected message received from the mailbox.")
+            }
+            MailboxError::AllocationFailed => write!(f, "unable to allocate the requested memory."),
+            MailboxError::OffsetOutOfBounds => {
+                write!(f, "virtual offset outside of the physical framebuffer.")
+            }
+            MailboxError::BufferUnreachable => {
+                write!(f, "message buffer not addressable by the VideoCore.")
+            }
+            MailboxError::Timeout => write!(f, "timeout while waiting for the expected state."),
+            MailboxError::SizeMismatch { expected, actual } => write!(
+                f,
+                "mailbox message size mismatch, expected {} bytes but got {} bytes.",
+                expected, actual
+            ),
+            MailboxError::UnknownPixelOrder(order) => {
+                write!(f, "unknown pixel order {:#x}.", order)
+            }
+            MailboxError::UnknownAlphaMode(mode) => write!(f, "unknown alpha mode {:#x}.", mode),
+            MailboxError::UnknownDepth(depth) => write!(f, "unknown pixel depth {}.", depth),
+            MailboxError::MisalignedBuffer => {
+                write!(f, "message buffer not 16 byte aligned.")
+            }
+            MailboxError::BufferTooSmall { required } => {
+                write!(f, "buffer too small, {} bytes required.", required)
+            }
+            MailboxError::InvalidFramebufferBatch => {
+                write!(f, "conflicting framebuffer property tags in batch.")
+            }
+            MailboxError::Busy => write!(f, "mailbox is busy."),
+            MailboxError::EmptyBatch => write!(f, "mailbox property tag batch message is empty."),
+            MailboxError::BadTagSize => write!(f, "property tag size not a multiple of 32 bits."),
+            MailboxError::NotMapped => write!(f, "mailbox registers not accessible."),
+            MailboxError::InvalidHeader => {
+                write!(f, "message buffer does not start with a valid header.")
+            }
+            MailboxError::ReservationTooLarge => {
+                write!(f, "reserved memory exceeds the available memory.")
+            }
+        }
+    }
+}
+
+impl ruspiro_error::Error for MailboxError {}
@@ -0,0 +1,1152 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Framebuffer
+//!
+//! The properties of a framebuffer allocated with the help of the mailbox property tag interface.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let framebuffer = mb
+//!         .framebuffer(1024, 768)
+//!         .allocate_with_fallback(&[Depth::Bpp32, Depth::Bpp16])
+//!         .unwrap();
+//! }
+//! ```
+//!
+
+use crate::{
+    AlphaModeSet, Cons, DepthGet, DepthSet, FramebufferAllocate, FramebufferRelease, Mailbox,
+    MailboxBatch, MailboxError, MailboxResult, PhysicalSizeSet, PitchGet, PixelOrderSet,
+    PropertyTag, PropertyTagList, VirtualSizeSet,
+};
+use core::convert::TryFrom;
+
+/// The property tags of the batch allocating the framebuffer, placed behind the optional ``Settings`` tags
+type AllocationTags<Settings> = Cons<
+    Cons<
+        Cons<
+            Cons<Cons<Cons<Settings, PhysicalSizeSet>, VirtualSizeSet>, DepthSet>,
+            FramebufferAllocate,
+        >,
+        PitchGet,
+    >,
+    DepthGet,
+>;
+
+/// The alignment of the framebuffer memory requested with the [FramebufferBuilder]. A larger alignment might leave
+/// a gap in front of the framebuffer that is lost for other VideoCore allocations.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FramebufferAlignment {
+    /// Align to the 64 byte cache line. The framebuffer does not share a cache line with other data, so cleaning
+    /// the data cache for the framebuffer does not affect any other memory. This wastes the least memory.
+    CacheLine = 64,
+    /// Align to the 4KB page. The framebuffer could be mapped with it's own memory attributes in the MMU, e.g.
+    /// as non-cacheable, without affecting the memory in front of it.
+    Page = 4096,
+}
+
+/// The pixel depths a framebuffer could be allocated with
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Depth {
+    /// 8 bits per pixel, each pixel is an index into the palette
+    Bpp8 = 8,
+    /// 16 bits per pixel (RGB565)
+    Bpp16 = 16,
+    /// 24 bits per pixel
+    Bpp24 = 24,
+    /// 32 bits per pixel
+    Bpp32 = 32,
+}
+
+impl TryFrom<u32> for Depth {
+    type Error = MailboxError;
+
+    /// Convert the raw bits per pixel reported by the firmware. Values not known to this crate are reported as
+    /// [MailboxError::UnknownDepth].
+    fn try_from(depth: u32) -> Result<Self, Self::Error> {
+        match depth {
+            8 => Ok(Depth::Bpp8),
+            16 => Ok(Depth::Bpp16),
+            24 => Ok(Depth::Bpp24),
+            32 => Ok(Depth::Bpp32),
+            _ => Err(MailboxError::UnknownDepth(depth)),
+        }
+    }
+}
+
+/// The order of the color components of a pixel as used with the [PixelOrderGet](crate::PixelOrderGet) and
+/// [PixelOrderSet](crate::PixelOrderSet) property tags. These are the orderings the firmware documents.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PixelOrder {
+    /// Blue in the lowest bits, red in the highest bits
+    Bgr = 0x0,
+    /// Red in the lowest bits, blue in the highest bits
+    Rgb = 0x1,
+}
+
+impl TryFrom<u32> for PixelOrder {
+    type Error = MailboxError;
+
+    /// Convert the raw pixel order reported by the firmware. Values not known to this crate are reported as
+    /// [MailboxError::UnknownPixelOrder].
+    fn try_from(order: u32) -> Result<Self, Self::Error> {
+        match order {
+            0x0 => Ok(PixelOrder::Bgr),
+            0x1 => Ok(PixelOrder::Rgb),
+            _ => Err(MailboxError::UnknownPixelOrder(order)),
+        }
+    }
+}
+
+/// The meaning of the alpha channel of a pixel as used with the [AlphaModeGet](crate::AlphaModeGet) and
+/// [AlphaModeSet](crate::AlphaModeSet) property tags.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha enabled, 0x0 in the alpha channel means fully opaque
+    Enabled = 0x0,
+    /// Alpha enabled, 0x0 in the alpha channel means fully transparent
+    Reversed = 0x1,
+    /// The alpha channel is ignored
+    Ignored = 0x2,
+}
+
+impl TryFrom<u32> for AlphaMode {
+    type Error = MailboxError;
+
+    /// Convert the raw alpha mode reported by the firmware. Values not known to this crate are reported as
+    /// [MailboxError::UnknownAlphaMode].
+    fn try_from(mode: u32) -> Result<Self, Self::Error> {
+        match mode {
+            0x0 => Ok(AlphaMode::Enabled),
+            0x1 => Ok(AlphaMode::Reversed),
+            0x2 => Ok(AlphaMode::Ignored),
+            _ => Err(MailboxError::UnknownAlphaMode(mode)),
+        }
+    }
+}
+
+/// The size of a framebuffer or display in pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Size {
+    /// The width in pixels
+    pub width: u32,
+    /// The height in pixels
+    pub height: u32,
+}
+
+impl Size {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl From<(u32, u32)> for Size {
+    /// Convert the tuple ``(width, height)``
+    fn from((width, height): (u32, u32)) -> Self {
+        Self { width, height }
+    }
+}
+
+impl From<Size> for (u32, u32) {
+    /// Convert into the tuple ``(width, height)``
+    fn from(size: Size) -> Self {
+        (size.width, size.height)
+    }
+}
+
+/// The overscan of the display in pixels, this is the border on each side of the display that is not used
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Overscan {
+    /// The border at the top
+    pub top: u32,
+    /// The border at the bottom
+    pub bottom: u32,
+    /// The border on the left
+    pub left: u32,
+    /// The border on the right
+    pub right: u32,
+}
+
+/// A framebuffer as it has been allocated by the VideoCore
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    /// The ARM physical address of the framebuffer memory
+    pub base_address: u32,
+    /// The size of the framebuffer memory in bytes
+    pub size: u32,
+    /// The physical width in pixels
+    pub width: u32,
+    /// The physical height in pixels
+    pub height: u32,
+    /// The bits per pixel as reported by the firmware after the allocation. This might differ from the
+    /// requested depth and need to be used to address the pixels.
+    pub depth: u32,
+    /// The bytes per line as reported by the firmware. The VideoCore might align the lines, so this could be
+    /// larger than the bytes required for the pixels of a line. All line addressing need to use the pitch.
+    pub pitch: u32,
+    /// The number of bytes the visible pixels of a line are occupying, this is the width times the bytes per
+    /// pixel. This might be less than the ``pitch``, so this shall not be used to calculate the start of a line.
+    pub row_bytes: u32,
+}
+
+/// Builder to allocate a [Framebuffer] with the VideoCore. The builder is created with
+/// [Mailbox::framebuffer]. If not configured otherwise the virtual size equals the physical size,
+/// 32 bits per pixel are used, the framebuffer is aligned to the cache line and the pixel order and
+/// alpha mode are left as the firmware has set them.
+pub struct FramebufferBuilder<'a> {
+    mailbox: &'a mut Mailbox,
+    width: u32,
+    height: u32,
+    virtual_width: u32,
+    virtual_height: u32,
+    depth: Depth,
+    alignment: FramebufferAlignment,
+    pixel_order: Option<PixelOrder>,
+    alpha_mode: Option<AlphaMode>,
+}
+
+impl<'a> FramebufferBuilder<'a> {
+    pub(crate) fn new(mailbox: &'a mut Mailbox, width: u32, height: u32) -> Self {
+        Self {
+            mailbox,
+            width,
+            height,
+            virtual_width: width,
+            virtual_height: height,
+            depth: Depth::Bpp32,
+            alignment: FramebufferAlignment::CacheLine,
+            pixel_order: None,
+            alpha_mode: None,
+        }
+    }
+
+    /// Set the virtual size of the framebuffer
+    pub fn virtual_size(mut self, width: u32, height: u32) -> Self {
+        self.virtual_width = width;
+        self.virtual_height = height;
+        self
+    }
+
+    /// Set the pixel depth of the framebuffer
+    pub fn depth(mut self, depth: Depth) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Set the alignment of the framebuffer memory
+    pub fn alignment(mut self, alignment: FramebufferAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Set the order of the color components of a pixel
+    pub fn pixel_order(mut self, order: PixelOrder) -> Self {
+        self.pixel_order = Some(order);
+        self
+    }
+
+    /// Set the meaning of the alpha channel of a pixel
+    pub fn alpha_mode(mut self, mode: AlphaMode) -> Self {
+        self.alpha_mode = Some(mode);
+        self
+    }
+
+    /// Allocate the framebuffer. This fails with [MailboxError::Unsupported] if the firmware does not
+    /// support the requested depth, pixel order or alpha mode. The firmware might still use a different depth
+    /// once the framebuffer is allocated, so always use the depth of the returned [Framebuffer]. A framebuffer
+    /// the firmware has allocated with a different depth or not at the requested alignment is released before
+    /// the error is returned.
+    pub fn allocate(mut self) -> MailboxResult<Framebuffer> {
+        let depth = self.depth;
+        self.allocate_depth(depth)
+    }
+
+    /// Allocate the framebuffer trying each of the given depths in order until the allocation succeeds.
+    /// The depth configured with [FramebufferBuilder::depth] is not used. The depth of the returned
+    /// [Framebuffer] is the one the allocation succeeded with. If none of the depths could be allocated
+    /// the error of the last attempt is returned.
+    pub fn allocate_with_fallback(mut self, depths: &[Depth]) -> MailboxResult<Framebuffer> {
+        let mut result = Err(MailboxError::Unsupported);
+        for &depth in depths {
+            result = self.allocate_depth(depth);
+            match result {
+                Err(MailboxError::Unsupported) | Err(MailboxError::AllocationFailed) => (),
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// Allocate the framebuffer with the given depth
+    fn allocate_depth(&mut self, depth: Depth) -> MailboxResult<Framebuffer> {
+        // the pixel order and the alpha mode need to be set in the same batch in front of the allocation
+        let settings = MailboxBatch::empty();
+        match (self.pixel_order, self.alpha_mode) {
+            (None, None) => self.allocate_with(settings, depth, |_| true),
+            (Some(order), None) => self.allocate_with(
+                settings.with_tag(PixelOrderSet::new(order as u32)),
+                depth,
+                |batch| batch.get_tag::<PixelOrderSet, _>().response().order() == order as u32,
+            ),
+            (None, Some(mode)) => self.allocate_with(
+                settings.with_tag(AlphaModeSet::new(mode as u32)),
+                depth,
+                |batch| batch.get_tag::<AlphaModeSet, _>().response().mode() == mode as u32,
+            ),
+            (Some(order), Some(mode)) => self.allocate_with(
+                settings
+                    .with_tag(PixelOrderSet::new(order as u32))
+                    .with_tag(AlphaModeSet::new(mode as u32)),
+                depth,
+                |batch| {
+                    batch.get_tag::<PixelOrderSet, _>().response().order() == order as u32
+                        && batch.get_tag::<AlphaModeSet, _>().response().mode() == mode as u32
+                },
+            ),
+        }
+    }
+
+    /// Allocate the framebuffer with the given depth behind the given settings. The settings are treated as
+    /// not supported if they are not ``accepted`` by the firmware.
+    fn allocate_with<Settings, F>(
+        &mut self,
+        settings: MailboxBatch<Settings>,
+        depth: Depth,
+        accepted: F,
+    ) -> MailboxResult<Framebuffer>
+    where
+        Settings: PropertyTagList,
+        F: FnOnce(&MailboxBatch<AllocationTags<Settings>>) -> bool,
+    {
+        let batch = settings
+            .with_tag(PhysicalSizeSet::new(self.width, self.height))
+            .with_tag(VirtualSizeSet::new(self.virtual_width, self.virtual_height))
+            .with_tag(DepthSet::new(depth as u32))
+            .with_tag(FramebufferAllocate::new(self.alignment as u32))
+            .with_tag(PitchGet::new())
+            .with_tag(DepthGet::new());
+        let batch = self.mailbox.send_batch(batch)?;
+        let physical_size = batch.get_tag::<PhysicalSizeSet, _>().response();
+        let allocation = batch.get_tag::<FramebufferAllocate, _>().response();
+        let error = if batch.get_tag::<DepthSet, _>().response().depth() != depth as u32
+            || !accepted(&batch)
+        {
+            // the firmware does not support the requested depth or settings
+            Some(MailboxError::Unsupported)
+        } else if allocation.base_address() == 0
+            || allocation.base_address() & (self.alignment as u32 - 1) != 0
+        {
+            // a framebuffer not placed at the requested alignment is treated as failed allocation
+            Some(MailboxError::AllocationFailed)
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            // a framebuffer allocated anyway is released, so it does not stay allocated without an owner
+            if allocation.base_address() != 0 {
+                self.mailbox
+                    .send_message(FramebufferRelease::new().into())?;
+            }
+            return Err(error);
+        }
+
+        // the firmware might use a different depth than requested once the framebuffer is allocated
+        let depth = batch.get_tag::<DepthGet, _>().response().depth();
+        Ok(Framebuffer {
+            // the VideoCore provides the bus address of the framebuffer
+            base_address: allocation.base_address() & 0x3FFF_FFFF,
+            size: allocation.size(),
+            width: physical_size.width(),
+            height: physical_size.height(),
+            depth,
+            pitch: batch.get_tag::<PitchGet, _>().response().pitch(),
+            row_bytes: physical_size.width() * bytes_per_pixel(depth),
+        })
+    }
+}
+
+impl Framebuffer {
+    /// The alignment in bytes the VideoCore has placed the framebuffer memory at. This is the largest power of
+    /// two the base address is a multiple of.
+    pub fn alignment(&self) -> u32 {
+        1u32.checked_shl(self.base_address.trailing_zeros())
+            .unwrap_or(0)
+    }
+
+    /// The number of bytes used to store a single pixel. This is 0 for an unsupported depth.
+    pub fn bytes_per_pixel(&self) -> u32 {
+        bytes_per_pixel(self.depth)
+    }
+
+    /// The number of padding bytes at the end of each line the VideoCore has added for alignment.
+    pub fn row_padding(&self) -> u32 {
+        self.pitch.saturating_sub(self.row_bytes)
+    }
+
+    /// The framebuffer memory of ``size`` bytes at the ``base_address`` as slice. Each line starts at a multiple of
+    /// the ``pitch`` within this slice.
+    ///
+    /// # Safety
+    /// The framebuffer memory need to be mapped at it's base address, which is the case if the MMU is disabled or
+    /// maps the memory 1:1. The slice must be the only reference to the framebuffer memory while it is in use,
+    /// so the framebuffer shall neither be accessed through another slice, e.g. of a copy of this [Framebuffer],
+    /// nor be released while the slice is alive.
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        core::slice::from_raw_parts_mut(self.memory(), self.size as usize)
+    }
+
+    /// Fill the whole visible framebuffer with the given color. The color is given in the raw pixel format of the
+    /// framebuffer and only the lower ``depth`` bits of it are used. Each line is addressed with the ``pitch`` of the
+    /// framebuffer, the padding bytes at the end of each line are not touched.
+    ///
+    /// # Safety
+    /// The framebuffer memory need to be accessible at it's base address and shall not be accessed by anything
+    /// else while filling it.
+    pub unsafe fn fill(&self, color: u32) {
+        let buffer = core::slice::from_raw_parts_mut(self.memory(), self.size as usize);
+        self.fill_buffer(buffer, color);
+    }
+
+    /// The framebuffer memory as accessed by the ARM. When running the tests the memory is provided by the
+    /// [mock](crate::mock).
+    fn memory(&self) -> *mut u8 {
+        #[cfg(not(test))]
+        let memory = self.base_address as usize as *mut u8;
+        #[cfg(test)]
+        let memory = crate::mock::memory_address(self.base_address, self.size as usize);
+        memory
+    }
+
+    /// Fill the given buffer representing the framebuffer memory with the color. Returns the number of pixels
+    /// written.
+    fn fill_buffer(&self, buffer: &mut [u8], color: u32) -> usize {
+        let bytes_per_pixel = match self.bytes_per_pixel() {
+            0 => return 0,
+            bytes => bytes as usize,
+        };
+        let pixel = color.to_le_bytes();
+        let mut count = 0;
+        for line in buffer
+            .chunks_mut(self.pitch as usize)
+            .take(self.height as usize)
+        {
+            let row_bytes = line.len().min(self.row_bytes as usize);
+            for target in line[..row_bytes].chunks_exact_mut(bytes_per_pixel) {
+                target.copy_from_slice(&pixel[..bytes_per_pixel]);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+/// The number of bytes used to store a single pixel of the given depth. This is 0 for an unsupported depth.
+pub(crate) fn bytes_per_pixel(depth: u32) -> u32 {
+    match depth {
+        1..=8 => 1,
+        9..=16 => 2,
+        17..=24 => 3,
+        25..=32 => 4,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, PropertyTagId};
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    fn framebuffer(depth: u32, pitch: u32) -> Framebuffer {
+        Framebuffer {
+            base_address: 0x0,
+            size: pitch * 3,
+            width: 4,
+            height: 3,
+            depth,
+            pitch,
+            row_bytes: 4 * bytes_per_pixel(depth),
+        }
+    }
+
+    #[test]
+    fn fill_respects_depth_and_pitch() {
+        for &(depth, pitch) in [(8, 4), (16, 12), (24, 16), (32, 16)].iter() {
+            let framebuffer = framebuffer(depth, pitch);
+            let mut buffer = vec![0xAA; framebuffer.size as usize];
+            let count = framebuffer.fill_buffer(&mut buffer, 0x1122_3344);
+            assert_eq!(count, 12);
+
+            let bytes_per_pixel = depth as usize / 8;
+            let pixel = &0x1122_3344u32.to_le_bytes()[..bytes_per_pixel];
+            for line in buffer.chunks(pitch as usize) {
+                let (pixels, padding) = line.split_at(4 * bytes_per_pixel);
+                assert!(pixels.chunks(bytes_per_pixel).all(|p| p == pixel));
+                assert!(padding.iter().all(|&byte| byte == 0xAA));
+            }
+        }
+    }
+
+    #[test]
+    fn size_from_and_into_tuple() {
+        let size = Size::from((1920, 1080));
+        assert_eq!(size.width, 1920);
+        assert_eq!(size.height, 1080);
+        assert_eq!(<(u32, u32)>::from(size), (1920, 1080));
+    }
+
+    #[test]
+    fn pixel_order_from_raw() {
+        assert_eq!(PixelOrder::try_from(0x0), Ok(PixelOrder::Bgr));
+        assert_eq!(PixelOrder::try_from(0x1), Ok(PixelOrder::Rgb));
+        assert_eq!(
+            PixelOrder::try_from(0x2),
+            Err(MailboxError::UnknownPixelOrder(0x2))
+        );
+    }
+
+    #[test]
+    fn alpha_mode_and_depth_from_raw() {
+        assert_eq!(AlphaMode::try_from(0x1), Ok(AlphaMode::Reversed));
+        assert_eq!(AlphaMode::try_from(0x2), Ok(AlphaMode::Ignored));
+        assert_eq!(
+            AlphaMode::try_from(0x3),
+            Err(MailboxError::UnknownAlphaMode(0x3))
+        );
+        assert_eq!(Depth::try_from(16), Ok(Depth::Bpp16));
+        assert_eq!(Depth::try_from(0), Err(MailboxError::UnknownDepth(0)));
+    }
+
+    #[test]
+    fn alignment_from_base_address() {
+        let mut framebuffer = framebuffer(32, 16);
+        framebuffer.base_address = 0x0010_1000;
+        assert_eq!(framebuffer.alignment(), 4096);
+        framebuffer.base_address = 0x0010_1040;
+        assert_eq!(framebuffer.alignment(), 64);
+    }
+
+    #[test]
+    fn slice_over_framebuffer_memory() {
+        let mut framebuffer = framebuffer(32, 16);
+        framebuffer.base_address = 0x0010_0000;
+        let mut memory = vec![0xAA; framebuffer.size as usize];
+        crate::mock::map_memory(0x0010_0000, &mut memory);
+        let (address, size) = (memory.as_ptr(), memory.len());
+
+        let slice = unsafe { framebuffer.as_mut_slice() };
+        assert_eq!(slice.len(), size);
+        assert_eq!(slice.as_ptr(), address);
+        slice[..4].copy_from_slice(&[0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(memory[..5], [0x11, 0x22, 0x33, 0x44, 0xAA]);
+
+        // the framebuffer is filled within the same memory
+        unsafe { framebuffer.fill(0x5566_7788) };
+        assert!(memory
+            .chunks(4)
+            .all(|pixel| pixel == 0x5566_7788u32.to_le_bytes()));
+    }
+
+    #[test]
+    fn row_padding_from_pitch() {
+        let padded = framebuffer(16, 12);
+        assert_eq!(padded.row_bytes, 8);
+        assert_eq!(padded.row_padding(), 4);
+
+        let unpadded = framebuffer(32, 16);
+        assert_eq!(unpadded.row_bytes, 16);
+        assert_eq!(unpadded.row_padding(), 0);
+    }
+
+    /// firmware emulation of the property tags required to setup a framebuffer. The given depth is
+    /// the only one supported. The palette is kept in the given entries.
+    fn framebuffer_firmware(
+        supported_depth: u32,
+        palette: Rc<RefCell<Vec<u32>>>,
+    ) -> mock::Firmware {
+        let current = palette.clone();
+        mock::Firmware::new()
+            .on(PropertyTagId::PhysicalSizeSet, |_| Some(8))
+            .on(PropertyTagId::VirtualSizeSet, |_| Some(8))
+            .on(PropertyTagId::DepthSet, move |values| {
+                if values[0] != supported_depth {
+                    values[0] = 0;
+                }
+                Some(4)
+            })
+            .respond(
+                PropertyTagId::FramebufferAllocate,
+                &[0xC010_0000, 640 * 480 * supported_depth / 8],
+            )
+            .respond(PropertyTagId::PitchGet, &[640 * supported_depth / 8])
+            .respond(PropertyTagId::DepthGet, &[supported_depth])
+            .on(PropertyTagId::PixelOrderSet, |_| Some(4))
+            .on(PropertyTagId::AlphaModeSet, |_| Some(4))
+            // the framebuffer allocated with an unsupported depth is released
+            .on(PropertyTagId::FramebufferRelease, |_| Some(0))
+            .on(PropertyTagId::PaletteGet, move |values| {
+                values[..256].copy_from_slice(&current.borrow());
+                Some(1024)
+            })
+            .on(PropertyTagId::PaletteSet, move |values| {
+                let (offset, length) = (values[0] as usize, values[1] as usize);
+                palette.borrow_mut()[offset..offset + length]
+                    .copy_from_slice(&values[2..2 + length]);
+                values[0] = 0;
+                Some(4)
+            })
+    }
+
+    #[test]
+    fn max_framebuffer_size_is_clamped() {
+        mock::Firmware::new()
+            .on(PropertyTagId::PhysicalSizeTest, |values| {
+                values[0] = values[0].min(1920);
+                values[1] = values[1].min(1080);
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.get_max_framebuffer_size().unwrap(),
+            Size::new(1920, 1080)
+        );
+    }
+
+    #[test]
+    fn set_palette_range() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        let mut firmware = framebuffer_firmware(8, palette.clone());
+        let sizes = Rc::new(RefCell::new(Vec::new()));
+        let recorded = sizes.clone();
+        mock::install_raw(move |_, buffer| {
+            recorded
+                .borrow_mut()
+                .push((buffer[0], buffer[3], buffer[5], buffer[6]));
+            mock::process(buffer, &mut |tag, values| firmware.handle(tag, values));
+            Ok(())
+        });
+
+        let mut mb = Mailbox::new();
+        mb.set_palette_range(16, &[0xFF, 0xFF00]).unwrap();
+        // only the given entries are passed and written
+        assert_eq!(sizes.borrow()[0], ((2 + 3 + 2 + 2 + 1) << 2, 16, 16, 2));
+        let palette = palette.borrow();
+        assert_eq!(&palette[16..18], &[0xFF, 0xFF00]);
+        assert!(palette[..16]
+            .iter()
+            .chain(palette[18..].iter())
+            .all(|&color| color == 0));
+
+        assert_eq!(
+            mb.set_palette_range(255, &[0x1, 0x2]),
+            Err(MailboxError::OffsetOutOfBounds)
+        );
+        assert_eq!(sizes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn setup_palettized_framebuffer() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        framebuffer_firmware(8, palette.clone()).install();
+
+        let mut colors = [0u32; 256];
+        for (idx, color) in colors.iter_mut().enumerate() {
+            *color = idx as u32 * 0x01_0101;
+        }
+        let mut mb = Mailbox::new();
+        let framebuffer = mb.setup_palettized_framebuffer(640, 480, &colors).unwrap();
+        assert_eq!(
+            framebuffer,
+            Framebuffer {
+                base_address: 0x0010_0000,
+                size: 640 * 480,
+                width: 640,
+                height: 480,
+                depth: 8,
+                pitch: 640,
+                row_bytes: 640,
+            }
+        );
+        assert_eq!(palette.borrow().as_slice(), &colors[..]);
+    }
+
+    #[test]
+    fn setup_palettized_framebuffer_rejected() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        framebuffer_firmware(32, palette.clone()).install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.setup_palettized_framebuffer(640, 480, &[0xFF; 256]),
+            Err(MailboxError::Unsupported)
+        );
+        // the palette shall not be touched if the palette mode is not supported
+        assert!(palette.borrow().iter().all(|&color| color == 0));
+    }
+
+    #[test]
+    fn framebuffer_depth_from_firmware() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        framebuffer_firmware(24, palette)
+            // the requested depth is accepted but substituted on allocation
+            .respond(PropertyTagId::DepthGet, &[32])
+            .install();
+
+        let mut mb = Mailbox::new();
+        let framebuffer = mb
+            .framebuffer(640, 480)
+            .depth(Depth::Bpp24)
+            .allocate()
+            .unwrap();
+        assert_eq!(framebuffer.depth, 32);
+        assert_eq!(framebuffer.bytes_per_pixel(), 4);
+        assert_eq!(framebuffer.row_bytes, 640 * 4);
+    }
+
+    #[test]
+    fn overscan_set_and_get() {
+        let current = Rc::new(Cell::new([0u32; 4]));
+        let applied = current.clone();
+        let reported = current.clone();
+        mock::Firmware::new()
+            .on(PropertyTagId::OverscanSet, move |values| {
+                applied.set([values[0], values[1], values[2], values[3]]);
+                Some(16)
+            })
+            .on(PropertyTagId::OverscanGet, move |values| {
+                values[..4].copy_from_slice(&reported.get());
+                Some(16)
+            })
+            .install();
+        let overscan = Overscan {
+            top: 1,
+            bottom: 2,
+            left: 3,
+            right: 4,
+        };
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.set_overscan(overscan), Ok(overscan));
+        assert_eq!(mb.get_overscan(), Ok(overscan));
+        assert_eq!(current.get(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn num_displays() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::NumDisplaysGet, &[2])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.get_num_displays(), Ok(2));
+
+        // firmware without support of the tag
+        mock::Firmware::new().install();
+        assert_eq!(mb.get_num_displays(), Ok(1));
+    }
+
+    #[test]
+    fn pixel_order_from_firmware() {
+        let order = Rc::new(Cell::new(0x0));
+        let current = order.clone();
+        let applied = order.clone();
+        mock::Firmware::new()
+            .on(PropertyTagId::PixelOrderGet, move |values| {
+                values[0] = current.get();
+                Some(4)
+            })
+            .on(PropertyTagId::PixelOrderSet, move |values| {
+                applied.set(values[0]);
+                Some(4)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.get_pixel_order(), Ok(PixelOrder::Bgr));
+        assert_eq!(mb.set_pixel_order(PixelOrder::Rgb), Ok(PixelOrder::Rgb));
+        assert_eq!(mb.get_pixel_order(), Ok(PixelOrder::Rgb));
+        order.set(0x7);
+        assert_eq!(
+            mb.get_pixel_order(),
+            Err(MailboxError::UnknownPixelOrder(0x7))
+        );
+    }
+
+    #[test]
+    fn allocate_framebuffer_with_pixel_format() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        let mut firmware = framebuffer_firmware(32, palette);
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let recorded = requests.clone();
+        mock::install(move |tag, values| {
+            recorded.borrow_mut().push((tag, values[0]));
+            firmware.handle(tag, values)
+        });
+
+        let mut mb = Mailbox::new();
+        let framebuffer = mb
+            .framebuffer(640, 480)
+            .pixel_order(PixelOrder::Rgb)
+            .alpha_mode(AlphaMode::Reversed)
+            .allocate()
+            .unwrap();
+        assert_eq!(framebuffer.depth, 32);
+        assert_eq!(framebuffer.pitch, 640 * 4);
+        // the pixel format is set in front of the allocation
+        assert_eq!(
+            requests.borrow().as_slice(),
+            &[
+                (PropertyTagId::PixelOrderSet as u32, 0x1),
+                (PropertyTagId::AlphaModeSet as u32, 0x1),
+                (PropertyTagId::PhysicalSizeSet as u32, 640),
+                (PropertyTagId::VirtualSizeSet as u32, 640),
+                (PropertyTagId::DepthSet as u32, 32),
+                (PropertyTagId::FramebufferAllocate as u32, 64),
+                (PropertyTagId::PitchGet as u32, 0),
+                (PropertyTagId::DepthGet as u32, 0),
+            ]
+        );
+
+        // without a pixel format configured the firmware settings are kept
+        requests.borrow_mut().clear();
+        mb.framebuffer(640, 480).allocate().unwrap();
+        assert_eq!(
+            requests.borrow()[0].0,
+            PropertyTagId::PhysicalSizeSet as u32
+        );
+    }
+
+    #[test]
+    fn setup_rgba_framebuffer() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        let mut firmware = framebuffer_firmware(32, palette);
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let recorded = requests.clone();
+        mock::install(move |tag, values| {
+            recorded.borrow_mut().push((tag, values[0]));
+            firmware.handle(tag, values)
+        });
+
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.setup_rgba_framebuffer(640, 480),
+            Ok(Framebuffer {
+                base_address: 0x0010_0000,
+                size: 640 * 480 * 4,
+                width: 640,
+                height: 480,
+                depth: 32,
+                pitch: 640 * 4,
+                row_bytes: 640 * 4,
+            })
+        );
+        assert_eq!(
+            requests.borrow().as_slice(),
+            &[
+                (PropertyTagId::PixelOrderSet as u32, PixelOrder::Rgb as u32),
+                (
+                    PropertyTagId::AlphaModeSet as u32,
+                    AlphaMode::Reversed as u32
+                ),
+                (PropertyTagId::PhysicalSizeSet as u32, 640),
+                (PropertyTagId::VirtualSizeSet as u32, 640),
+                (PropertyTagId::DepthSet as u32, 32),
+                (PropertyTagId::FramebufferAllocate as u32, 64),
+                (PropertyTagId::PitchGet as u32, 0),
+                (PropertyTagId::DepthGet as u32, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn allocate_framebuffer_with_rejected_pixel_format() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        framebuffer_firmware(32, palette)
+            // the firmware ignores the alpha channel instead
+            .respond(PropertyTagId::AlphaModeSet, &[AlphaMode::Ignored as u32])
+            .install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.framebuffer(640, 480)
+                .alpha_mode(AlphaMode::Reversed)
+                .allocate(),
+            Err(MailboxError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn allocate_framebuffer_with_fallback() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        framebuffer_firmware(16, palette).install();
+
+        let mut mb = Mailbox::new();
+        let framebuffer = mb
+            .framebuffer(640, 480)
+            .allocate_with_fallback(&[Depth::Bpp32, Depth::Bpp16, Depth::Bpp8])
+            .unwrap();
+        assert_eq!(framebuffer.depth, 16);
+        assert_eq!(framebuffer.pitch, 640 * 2);
+
+        assert_eq!(
+            mb.framebuffer(640, 480)
+                .allocate_with_fallback(&[Depth::Bpp32, Depth::Bpp24]),
+            Err(MailboxError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn allocate_framebuffer_with_alignment() {
+        let palette = Rc::new(RefCell::new(vec![0; 256]));
+        let alignments = Rc::new(RefCell::new(Vec::new()));
+        let recorded = alignments.clone();
+        let releases = Rc::new(Cell::new(0));
+        let released = releases.clone();
+        framebuffer_firmware(32, palette)
+            .on(PropertyTagId::FramebufferRelease, move |_| {
+                released.set(released.get() + 1);
+                Some(0)
+            })
+            .on(PropertyTagId::FramebufferAllocate, move |values| {
+                recorded.borrow_mut().push(values[0]);
+                // place the framebuffer at a cache line but not at a page boundary
+                values[..2].copy_from_slice(&[0xC010_0040, 640 * 480 * 4]);
+                Some(8)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        let framebuffer = mb.framebuffer(640, 480).allocate().unwrap();
+        assert_eq!(framebuffer.alignment(), 64);
+        assert_eq!(
+            mb.framebuffer(640, 480)
+                .alignment(FramebufferAlignment::Page)
+                .allocate(),
+            Err(MailboxError::AllocationFailed)
+        );
+        assert_eq!(*alignments.borrow(), [64, 4096]);
+        // only the framebuffer not placed at the requested alignment is released
+        assert_eq!(releases.get(), 1);
+    }
+
+    #[test]
+    fn existing_framebuffer() {
+        for &(active, expected) in [
+            (
+                true,
+                Some(Framebuffer {
+                    base_address: 0,
+                    size: 2560 * 480,
+                    width: 640,
+                    height: 480,
+                    depth: 32,
+                    pitch: 2560,
+                    row_bytes: 2560,
+                }),
+            ),
+            (false, None),
+        ]
+        .iter()
+        {
+            // without an active framebuffer the VideoCore reports everything as 0
+            let value = |value: u32| if active { value } else { 0 };
+            mock::Firmware::new()
+                .respond(PropertyTagId::PhysicalSizeGet, &[value(640), value(480)])
+                .respond(PropertyTagId::DepthGet, &[value(32)])
+                .respond(PropertyTagId::PitchGet, &[value(2560)])
+                .install();
+
+            let mut mb = Mailbox::new();
+            assert_eq!(mb.get_existing_framebuffer(), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn set_virtual_offset_checked() {
+        let offset = Rc::new(Cell::new(None));
+        let applied = offset.clone();
+        mock::Firmware::new()
+            .respond(PropertyTagId::PhysicalSizeGet, &[640, 480])
+            .respond(PropertyTagId::VirtualSizeGet, &[640, 960])
+            .on(PropertyTagId::VirtualOffsetSet, move |values| {
+                applied.set(Some((values[0], values[1])));
+                Some(8)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        // the first and the second page of the double buffered framebuffer
+        assert_eq!(mb.set_virtual_offset_checked(0, 0), Ok((0, 0)));
+        assert_eq!(offset.take(), Some((0, 0)));
+        assert_eq!(mb.set_virtual_offset_checked(0, 480), Ok((0, 480)));
+        assert_eq!(offset.take(), Some((0, 480)));
+
+        assert_eq!(
+            mb.set_virtual_offset_checked(0, 481),
+            Err(MailboxError::OffsetOutOfBounds)
+        );
+        assert_eq!(
+            mb.set_virtual_offset_checked(1, 0),
+            Err(MailboxError::OffsetOutOfBounds)
+        );
+        // an out of bounds offset is never passed to the VideoCore
+        assert_eq!(offset.take(), None);
+    }
+
+    #[test]
+    fn flip_framebuffer_with_vsync() {
+        let mut firmware = mock::Firmware::new()
+            .respond(PropertyTagId::PhysicalSizeGet, &[640, 480])
+            .respond(PropertyTagId::VirtualSizeGet, &[640, 960])
+            .on(PropertyTagId::VirtualOffsetSet, |_| Some(8))
+            .on(PropertyTagId::WaitForVsync, |_| Some(4));
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let recorded = messages.clone();
+        mock::install_raw(move |_, buffer| {
+            let mut tags = Vec::new();
+            mock::process(buffer, &mut |tag, values: &mut [u32]| {
+                tags.push((tag, values[0], values.get(1).copied().unwrap_or(0)));
+                firmware.handle(tag, values)
+            });
+            recorded.borrow_mut().push(tags);
+            Ok(())
+        });
+
+        let mut mb = Mailbox::new();
+        mb.flip_framebuffer_vsync(1).unwrap();
+        let flip = messages.borrow_mut().pop().unwrap();
+        // the offset is set and the vertical sync awaited within the same message
+        assert_eq!(flip[0], (PropertyTagId::VirtualOffsetSet as u32, 0, 480));
+        assert_eq!(flip[1].0, PropertyTagId::WaitForVsync as u32);
+        assert_eq!(flip.len(), 2);
+
+        // the page 1 ends exactly at the virtual height, page 2 would start beyond it
+        assert_eq!(
+            mb.flip_framebuffer_vsync(2),
+            Err(MailboxError::OffsetOutOfBounds)
+        );
+        assert_eq!(messages.borrow().len(), 2);
+    }
+
+    #[test]
+    fn read_color_format() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::PixelOrderGet, &[PixelOrder::Rgb as u32])
+            .respond(PropertyTagId::AlphaModeGet, &[AlphaMode::Ignored as u32])
+            .respond(PropertyTagId::DepthGet, &[24])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.get_color_format(),
+            Ok((PixelOrder::Rgb, AlphaMode::Ignored, Depth::Bpp24))
+        );
+    }
+
+    #[test]
+    fn probe_supported_depths() {
+        mock::Firmware::new()
+            .on(PropertyTagId::DepthTest, |values| {
+                if values[0] != 16 && values[0] != 32 {
+                    values[0] = 0;
+                }
+                Some(4)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.supported_depths(), Ok(vec![Depth::Bpp16, Depth::Bpp32]));
+    }
+
+    #[test]
+    fn get_palette_into_buffers() {
+        let colors: Vec<u32> = (0..256).map(|color| color * 3).collect();
+        let palette = Rc::new(RefCell::new(colors.clone()));
+        framebuffer_firmware(8, palette).install();
+
+        let mut mb = Mailbox::new();
+        for &len in [0, 1, 16, 256, 300].iter() {
+            let mut buffer = vec![0xFFFF_FFFF; len];
+            let count = mb.get_palette_into(&mut buffer).unwrap();
+            assert_eq!(count, len.min(256));
+            assert_eq!(&buffer[..count], &colors[..count]);
+            // entries beyond the palette are not touched
+            assert!(buffer[count..].iter().all(|&color| color == 0xFFFF_FFFF));
+        }
+    }
+
+    #[test]
+    fn modify_palette_rotates_entries() {
+        let colors: Vec<u32> = (0..256).collect();
+        let palette = Rc::new(RefCell::new(colors.clone()));
+        framebuffer_firmware(8, palette.clone()).install();
+
+        let mut mb = Mailbox::new();
+        mb.modify_palette(|palette| palette.rotate_left(1)).unwrap();
+
+        let mut expected = colors;
+        expected.rotate_left(1);
+        assert_eq!(palette.borrow().as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn set_virtual_size_returns_granted_size() {
+        mock::Firmware::new()
+            .on(PropertyTagId::VirtualSizeSet, |values| {
+                values[0] = values[0].min(1280);
+                values[1] = values[1].min(720);
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.set_virtual_size_checked(640, 480).unwrap(),
+            Size::new(640, 480)
+        );
+        assert_eq!(
+            mb.set_virtual_size_checked(1920, 1080).unwrap(),
+            Size::new(1280, 720)
+        );
+    }
+
+    #[test]
+    fn set_virtual_size_unsupported() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::VirtualSizeSet, &[0, 0])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.set_virtual_size_checked(12345, 1),
+            Err(MailboxError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn clear_screen_blanks() {
+        mock::Firmware::new()
+            .on(PropertyTagId::BlankScreen, |values| {
+                if values[0] == 1 {
+                    Some(4)
+                } else {
+                    None
+                }
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert!(mb.clear_screen().is_ok());
+    }
+}
@@ -10,43 +10,73 @@
 //! This module provide the low level implementation of the mailbox property tag interface dealing with the actual
 //! peripherals.
 //!
+// the mailbox peripheral is not accessed when running the tests
+#![cfg_attr(test, allow(dead_code))]
+
+#[cfg(any(test, feature = "bounce_buffer"))]
+use crate::MailboxScratch;
 use crate::{
-    MailboxBatch, MailboxChannel, MailboxMessage, MailboxResult, MessageState, PropertyTag,
+    MailboxBatch, MailboxChannel, MailboxConfig, MailboxError, MailboxMessage, MailboxResult,
+    MailboxTagArray, MessageState, PropertyTag, TimingEvent,
 };
+use core::ops::Range;
+#[cfg(target_arch = "aarch64")]
 use ruspiro_cache as cache;
-use ruspiro_error::{BoxError, GenericError};
-use ruspiro_mmio_register::define_mmio_register;
+#[cfg(any(test, feature = "bounce_buffer"))]
+use ruspiro_lock::Mutex;
 
 // MMIO base address for peripherals
 #[cfg(feature = "ruspiro_pi3")]
-const PERIPHERAL_BASE: usize = 0x3F00_0000;
+pub(crate) const PERIPHERAL_BASE: usize = 0x3F00_0000;
+
+// Offset of the mailbox registers from the peripheral base address
+const MAILBOX_OFFSET: usize = 0x0000_B880;
+
+// The end of the memory range the VideoCore is able to address
+const VC_ADDRESSABLE_LIMIT: usize = 0x4000_0000;
 
-// Mailbox MMIO base address
-const MAILBOX_BASE: usize = PERIPHERAL_BASE + 0x0000_B880;
+// The number of words of the bounce buffer for messages the VideoCore is not able to address
+#[cfg(any(test, feature = "bounce_buffer"))]
+const BOUNCE_BUFFER_WORDS: usize = 1024;
+
+/// The bounce buffer is placed with the binary, that is loaded into the memory the VideoCore is able to address
+#[cfg(any(test, feature = "bounce_buffer"))]
+static BOUNCE_BUFFER: Mutex<MailboxScratch<BOUNCE_BUFFER_WORDS>> =
+    Mutex::new(MailboxScratch::new());
+
+/// The memory the message buffer passed to the VideoCore is placed in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum BufferMemory {
+    /// The buffer is placed in cached memory. The caches are maintained around the exchange and the VideoCore
+    /// accesses the buffer through it's uncached alias
+    Cached,
+    /// The buffer is placed in uncached memory. The caches are not maintained, the VideoCore still accesses the
+    /// buffer through it's uncached alias
+    Uncached,
+}
 
 /// Function to send a specific message to the mailbox channel given
 /// The mailbox interface does update the memory location of the message send. Therefor the function
 /// returns ``Ok(_)`` with the updated message in case of a success
 #[inline(never)]
 pub(crate) fn send_message<T: PropertyTag>(
+    config: &MailboxConfig,
     channel: MailboxChannel,
     mut message: MailboxMessage<T>,
 ) -> MailboxResult<MailboxMessage<T>> {
+    check_request_channel(channel)?;
     let msg_ptr: *mut MailboxMessage<T> = &mut message;
-    let msg_ptr_uncached: u32 = (msg_ptr as u32) | 0xC000_0000;
-
-    #[cfg(target_arch = "aarch64")]
-    unsafe {
-        cache::flush_dcache_range(msg_ptr as usize, core::mem::size_of::<MailboxMessage<T>>());
-    }
-    mb_write(channel, msg_ptr_uncached)?;
-    mb_read(channel)?;
-    //cache::cleaninvalidate();
+    exchange(
+        config,
+        channel,
+        msg_ptr as *mut u32,
+        core::mem::size_of::<MailboxMessage<T>>(),
+        BufferMemory::Cached,
+    )?;
     // at this point the property tag message memory has been changed under the hood
     // that Rust is not aware of, so optimizations might do things that will loose this fact
     // so read this memory location back into the corresponding message type
-    let result_ptr = (msg_ptr_uncached ^ 0xC000_0000) as *mut MailboxMessage<T>;
-    let result = unsafe { core::ptr::read_volatile(result_ptr) };
+    let result = unsafe { core::ptr::read_volatile(msg_ptr) };
     // now that we have reconstructed the MailboxMessage from the exact memory location
     // as the prevoius one, ensure the previous one will not being dropped as this might release
     // resources now used be the reconstructed version
@@ -54,68 +84,877 @@ pub(crate) fn send_message<T: PropertyTag>(
 
     match result.state() {
         MessageState::ResponseOk => Ok(result),
-        _ => Err(GenericError::with_message("unable to send mailbox property tag message.").into()),
+        _ => Err(MailboxError::ResponseError),
+    }
+}
+
+/// Send a message containing several property tags of the same type and return the processed message
+#[inline(never)]
+pub(crate) fn send_tag_array<T: PropertyTag + Copy, const N: usize>(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    mut message: MailboxTagArray<T, N>,
+) -> MailboxResult<MailboxTagArray<T, N>> {
+    check_request_channel(channel)?;
+    let words = message.as_mut_words();
+    exchange(
+        config,
+        channel,
+        words.as_mut_ptr(),
+        words.len() << 2,
+        BufferMemory::Cached,
+    )?;
+    // the message memory has been changed by the VideoCore, so read it back like any other message
+    let msg_ptr: *mut MailboxTagArray<T, N> = &mut message;
+    let result = unsafe { core::ptr::read_volatile(msg_ptr) };
+    match result.state() {
+        MessageState::ResponseOk => Ok(result),
+        _ => Err(MailboxError::ResponseError),
     }
 }
 
 #[inline(never)]
 pub(crate) fn send_batch<T>(
+    config: &MailboxConfig,
     channel: MailboxChannel,
     mut batch: MailboxBatch<T>,
 ) -> MailboxResult<MailboxBatch<T>> {
+    check_request_channel(channel)?;
+    if batch.is_empty() {
+        return Err(MailboxError::EmptyBatch);
+    }
+    if batch.is_oversized() {
+        return Err(MailboxError::BatchTooLarge);
+    }
+    let expected = batch.msg_size();
     // get the binary data from the batch and pass the address to it to the mailbox for processing
-    let batch_ptr = &mut batch as *mut MailboxBatch<T>; // as *mut u32;
-    let batch_ptr_uncached: u32 = (batch_ptr as u32) | 0xC000_0000;
+    let batch_ptr = &mut batch as *mut MailboxBatch<T>;
     // send this mailbox message and wait for the GPU to respond
-    #[cfg(target_arch = "aarch64")]
-    unsafe {
-        cache::flush_dcache_range(batch_ptr as usize, core::mem::size_of::<MailboxBatch<T>>());
-    }
-    mb_write(channel, batch_ptr_uncached)?;
-    mb_read(channel)?;
-    //cache::cleaninvalidate();
+    exchange(
+        config,
+        channel,
+        batch_ptr as *mut u32,
+        core::mem::size_of::<MailboxBatch<T>>(),
+        BufferMemory::Cached,
+    )?;
 
     // at this point the property tag message batch memory has been changed under the hood
     // that Rust is not aware of, so optimizations might do things that will loose this fact
     // so read this memory location back into the corresponding buffer type
-    let result_ptr = (batch_ptr_uncached ^ 0xC000_0000) as *mut MailboxBatch<T>;
-    let result = unsafe { core::ptr::read_volatile(result_ptr) };
+    let result = unsafe { core::ptr::read_volatile(batch_ptr) };
     // as we have reconstructed the MailboxBatch at the exact location of the previous one
     // we need to ensure the previous one does not get dropped as this might release
     // resources now used be the reconstructed version
     core::mem::forget(batch);
     if let MessageState::ResponseOk = result.get_state() {
+        // the VideoCore reports a different size if it has not processed the batch as it was sent
+        let actual = result.msg_size();
+        if actual != expected {
+            return Err(MailboxError::SizeMismatch { expected, actual });
+        }
         Ok(result)
     } else {
-        Err(GenericError::with_message("unable to send mailbox property tag batch message.").into())
+        Err(MailboxError::ResponseError)
+    }
+}
+
+#[inline(never)]
+pub(crate) fn send_raw(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: &mut [u32],
+    memory: BufferMemory,
+) -> MailboxResult<()> {
+    check_request_channel(channel)?;
+    // the lower 4 bits of the address passed to the mailbox are used for the channel
+    if buffer.as_ptr() as usize & 0xF != 0 {
+        return Err(MailboxError::MisalignedBuffer);
+    }
+    // the VideoCore would access memory beyond the buffer if the message size exceeds it
+    if buffer.len() < 3
+        || buffer[0] & 0x3 != 0
+        || buffer[0] as usize > buffer.len() << 2
+        || buffer[1] != MessageState::Request as u32
+    {
+        return Err(MailboxError::InvalidHeader);
+    }
+    let size = buffer.len() << 2;
+    exchange(config, channel, buffer.as_mut_ptr(), size, memory)?;
+    // the buffer memory has been changed under the hood by the VideoCore, so read it back to
+    // ensure Rust is aware of the changes
+    for word in buffer.iter_mut() {
+        *word = unsafe { core::ptr::read_volatile(word) };
+    }
+
+    if buffer[1] == MessageState::ResponseOk as u32 {
+        Ok(())
+    } else {
+        Err(MailboxError::ResponseError)
+    }
+}
+
+/// Receive a message the VideoCore has initiated on the given channel. The message is expected to contain exactly
+/// one property tag of the requested type. Once the message has been read the buffer is handed back to the
+/// VideoCore to acknowledge the reception.
+#[inline(never)]
+pub(crate) fn receive_message<T: PropertyTag>(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+) -> MailboxResult<T> {
+    check_receive_channel(channel)?;
+    let size = core::mem::size_of::<MailboxMessage<T>>();
+    let buffer = receive(config, channel, size)?;
+    // verify the raw message header and tag id before the buffer is treated as the requested message type
+    let (msg_size, tag_id) = unsafe {
+        (
+            core::ptr::read_volatile(buffer),
+            core::ptr::read_volatile(buffer.add(2)),
+        )
+    };
+    let result =
+        if msg_size as usize != 12 + core::mem::size_of::<T>() || tag_id != T::TAG_ID as u32 {
+            Err(MailboxError::UnexpectedMessage)
+        } else {
+            let message = unsafe { core::ptr::read_volatile(buffer as *const MailboxMessage<T>) };
+            Ok(message.into_tag())
+        };
+    // hand the buffer back to the VideoCore regardless of it's content
+    acknowledge(config, channel, buffer)?;
+
+    result
+}
+
+/// Ensure the requests the ARM initiates are only passed on the channel dedicated to them
+fn check_request_channel(channel: MailboxChannel) -> MailboxResult<()> {
+    if channel.is_valid_for_requests() {
+        Ok(())
+    } else {
+        Err(MailboxError::UnsupportedChannel(channel))
+    }
+}
+
+/// Ensure the messages the VideoCore initiates are only received on the channel dedicated to them
+fn check_receive_channel(channel: MailboxChannel) -> MailboxResult<()> {
+    if channel == MailboxChannel::PropertyTagsArm {
+        Ok(())
+    } else {
+        Err(MailboxError::UnsupportedChannel(channel))
+    }
+}
+
+/// Pass the message buffer of the given size to the mailbox and wait for the VideoCore to process it. Once this
+/// function returns successfully the buffer contains the response written by the VideoCore.
+/// With the ``bounce_buffer`` feature a buffer the VideoCore is not able to address is passed through the bounce
+/// buffer. The bounce buffer is placed in cached memory regardless of the memory of the buffer.
+#[cfg(not(test))]
+fn exchange(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: *mut u32,
+    size: usize,
+    memory: BufferMemory,
+) -> MailboxResult<()> {
+    #[cfg(feature = "bounce_buffer")]
+    {
+        if bus_address(buffer, size) == Err(MailboxError::BufferUnreachable) {
+            return bounce(buffer, size, |bounce_buffer| {
+                exchange_addressable(config, channel, bounce_buffer, size, BufferMemory::Cached)
+            });
+        }
+    }
+    exchange_addressable(config, channel, buffer, size, memory)
+}
+
+/// Pass the message buffer that is placed in the memory the VideoCore is able to address to the mailbox
+#[cfg(not(test))]
+fn exchange_addressable(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: *mut u32,
+    size: usize,
+    memory: BufferMemory,
+) -> MailboxResult<()> {
+    let address = bus_address(buffer, size)?;
+    // the ranges are determined upfront as the VideoCore might change the size in the message header
+    let request = request_range(buffer, size);
+    let response = response_range(buffer, size);
+    if memory == BufferMemory::Cached {
+        flush_request(request);
+    }
+    notify_timing(config, TimingEvent::BeforeWrite);
+    mb_write(config, channel, address)?;
+    mb_read_response(config, channel);
+    notify_timing(config, TimingEvent::AfterRead);
+    if memory == BufferMemory::Cached {
+        invalidate_response(response);
+    }
+    Ok(())
+}
+
+/// Notify the timing hook of the configuration, if any, about the event
+#[inline]
+fn notify_timing(config: &MailboxConfig, event: TimingEvent) {
+    if let Some(hook) = config.timing_hook {
+        hook(event);
+    }
+}
+
+/// The memory range the VideoCore reads the request from. This is the message as stated by the size in it's
+/// header, including the end tag. A buffer larger than the message, like a [MailboxScratch], is not covered
+/// beyond the message. The range never exceeds the buffer of the given size.
+fn request_range(buffer: *const u32, size: usize) -> Range<usize> {
+    let msg_size = unsafe { core::ptr::read_volatile(buffer) } as usize;
+    let start = buffer as usize;
+    let range = start..start + core::cmp::min(msg_size, size);
+    debug_assert!(
+        range.len() >= 12,
+        "the request range need to cover the message header and the end tag"
+    );
+    range
+}
+
+/// The memory range the VideoCore writes the response to. This is the whole request range. The end tag is only
+/// read by the VideoCore, but it shares the cache line with the last property tag, so excluding it would not save
+/// any cache maintenance.
+fn response_range(buffer: *const u32, size: usize) -> Range<usize> {
+    request_range(buffer, size)
+}
+
+/// Write the request back to the memory before it is passed to the VideoCore
+fn flush_request(range: Range<usize>) {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        cache::flush_dcache_range(range.start, range.len());
+    }
+    #[cfg(test)]
+    crate::mock::flush_dcache_range(range.start, range.len());
+    #[cfg(not(any(test, target_arch = "aarch64")))]
+    let _ = range;
+}
+
+/// Discard any cached content of the response once the VideoCore has written it to the memory
+fn invalidate_response(range: Range<usize>) {
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        cache::invalidate_dcache_range(range.start, range.len());
+    }
+    #[cfg(test)]
+    crate::mock::invalidate_dcache_range(range.start, range.len());
+    #[cfg(not(any(test, target_arch = "aarch64")))]
+    let _ = range;
+}
+
+/// Get the bus address of the uncached alias of the buffer the VideoCore shall access. The VideoCore is only able
+/// to address the first 1GB of the memory, so the whole buffer need to be placed below. Otherwise the address would
+/// be silently truncated and the VideoCore would access a different memory location.
+fn bus_address(buffer: *const u32, size: usize) -> MailboxResult<u32> {
+    let address = buffer as usize;
+    match address.checked_add(size) {
+        Some(end) if end <= VC_ADDRESSABLE_LIMIT => Ok(address as u32 | 0xC000_0000),
+        _ => Err(MailboxError::BufferUnreachable),
+    }
+}
+
+/// Pass the message buffer through the bounce buffer to the VideoCore. The message is copied into the bounce buffer,
+/// exchanged with the given function and the response is copied back into the message buffer. The bounce buffer
+/// is locked for the whole exchange. Messages larger than the bounce buffer are rejected.
+#[cfg(any(test, feature = "bounce_buffer"))]
+fn bounce<F>(buffer: *mut u32, size: usize, exchange: F) -> MailboxResult<()>
+where
+    F: FnOnce(*mut u32) -> MailboxResult<()>,
+{
+    let words = (size + 3) >> 2;
+    if words > BOUNCE_BUFFER_WORDS {
+        return Err(MailboxError::BufferUnreachable);
+    }
+    let mut bounce_buffer = BOUNCE_BUFFER.lock();
+    let bounce_buffer = &mut bounce_buffer.as_mut_slice()[..words];
+    let message = unsafe { core::slice::from_raw_parts_mut(buffer, words) };
+    bounce_buffer.copy_from_slice(message);
+    // the cached content of the response written to the bounce buffer has been invalidated by the exchange
+    exchange(bounce_buffer.as_mut_ptr())?;
+    for (word, response) in message.iter_mut().zip(bounce_buffer.iter()) {
+        *word = unsafe { core::ptr::read_volatile(response) };
+    }
+
+    Ok(())
+}
+
+/// Wait for the VideoCore to pass a message on the given channel and return the ARM address of the message buffer
+#[cfg(not(test))]
+fn receive(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    size: usize,
+) -> MailboxResult<*mut u32> {
+    // the VideoCore passes the bus address of the message buffer
+    let buffer = (mb_read(config, channel)? & 0x3FFF_FFFF) as *mut u32;
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        cache::invalidate_dcache_range(buffer as usize, size);
     }
+    #[cfg(not(target_arch = "aarch64"))]
+    let _ = size;
+    Ok(buffer)
 }
 
-define_mmio_register! [
-    MAILBOX0_READ<ReadOnly<u32>@(MAILBOX_BASE)>,
-    MAILBOX0_STATUS<ReadOnly<u32>@(MAILBOX_BASE + 0x18)>,
-    MAILBOX1_WRITE<WriteOnly<u32>@(MAILBOX_BASE + 0x20)>,
-    MAILBOX1_STATUS<ReadOnly<u32>@(MAILBOX_BASE + 0x38)>
-];
+/// Hand the buffer of a received message back to the VideoCore
+#[cfg(not(test))]
+fn acknowledge(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: *mut u32,
+) -> MailboxResult<()> {
+    mb_write(config, channel, (buffer as u32) | 0xC000_0000)
+}
+
+/// When running the tests the message buffer is handed over to the firmware emulation of the [mock](crate::mock)
+/// instead of the mailbox peripheral. The cache maintenance is recorded by the mock.
+#[cfg(test)]
+fn exchange(
+    config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: *mut u32,
+    size: usize,
+    memory: BufferMemory,
+) -> MailboxResult<()> {
+    // the first word of each message contains it's size in bytes
+    let words = unsafe { *buffer } as usize >> 2;
+    let request = request_range(buffer, size);
+    let response = response_range(buffer, size);
+    if memory == BufferMemory::Cached {
+        flush_request(request);
+    }
+    notify_timing(config, TimingEvent::BeforeWrite);
+    crate::mock::exchange(channel, unsafe {
+        core::slice::from_raw_parts_mut(buffer, words)
+    })?;
+    notify_timing(config, TimingEvent::AfterRead);
+    if memory == BufferMemory::Cached {
+        invalidate_response(response);
+    }
+    Ok(())
+}
+
+/// When running the tests the received messages are provided by the [mock](crate::mock)
+#[cfg(test)]
+fn receive(
+    _config: &MailboxConfig,
+    channel: MailboxChannel,
+    _size: usize,
+) -> MailboxResult<*mut u32> {
+    crate::mock::receive(channel)
+}
+
+#[cfg(test)]
+fn acknowledge(
+    _config: &MailboxConfig,
+    channel: MailboxChannel,
+    buffer: *mut u32,
+) -> MailboxResult<()> {
+    crate::mock::acknowledge(channel, buffer);
+    Ok(())
+}
+
+// Offsets of the mailbox registers from the mailbox base
+const MAILBOX0_READ: usize = 0x00;
+const MAILBOX0_STATUS: usize = 0x18;
+const MAILBOX1_WRITE: usize = 0x20;
+const MAILBOX1_STATUS: usize = 0x38;
 
 const MAILBOX_FULL: u32 = 0x8000_0000; // status register value if the mailbox is already full
 const MAILBOX_EMPTY: u32 = 0x4000_0000; // status register value if the mailbox is empty
 
+// The number of times the mailbox status is polled before giving up with a timeout
+pub(crate) const MAILBOX_TIMEOUT_ITERATIONS: u32 = 0x0100_0000;
+
+/// Read the status registers of the mailbox the ARM reads from and the mailbox the ARM writes to
+pub(crate) fn mb_status(config: &MailboxConfig) -> (u32, u32) {
+    (
+        read_register(config, MAILBOX0_STATUS),
+        read_register(config, MAILBOX1_STATUS),
+    )
+}
+
+/// Check that the mailbox registers are accessible. An unmapped region typically reads as all ones, which is not a
+/// valid status as the mailbox could not be empty and full at the same time.
+pub(crate) fn mb_probe(config: &MailboxConfig) -> MailboxResult<()> {
+    if read_register(config, MAILBOX0_STATUS) == 0xFFFF_FFFF {
+        Err(MailboxError::NotMapped)
+    } else {
+        Ok(())
+    }
+}
+
+/// Wait for data on the given channel for at most the configured number of iterations
+#[inline]
+fn mb_read(config: &MailboxConfig, channel: MailboxChannel) -> MailboxResult<u32> {
+    for _ in 0..config.timeout_iterations {
+        if let Some(data) = mb_poll(config, channel) {
+            return Ok(data);
+        }
+    }
+    Err(MailboxError::Timeout)
+}
+
+/// Wait for the response to a message buffer that has been written to the mailbox. Once written the buffer is
+/// owned by the VideoCore until it responds, so this wait is not bounded by the timeout iterations. Giving up
+/// early would hand the buffer back to the caller while the VideoCore could still write the response into it,
+/// and the late response would be read as the response to the next message.
 #[inline]
-fn mb_read(channel: MailboxChannel) -> MailboxResult<u32> {
+fn mb_read_response(config: &MailboxConfig, channel: MailboxChannel) -> u32 {
     loop {
-        while (MAILBOX0_STATUS::Register.get() & MAILBOX_EMPTY) != 0x0 {}
-        let data = MAILBOX0_READ::Register.get();
-        if (data & 0xF) == channel as u32 {
-            return Ok(data & 0xFFFF_FFF0);
+        if let Some(data) = mb_poll(config, channel) {
+            return data;
+        }
+    }
+}
+
+/// Discard the data pending in the mailbox, e.g. the late response to a message that has been given up on, so it
+/// is not read as the response to the next message. Data on any other channel is passed to the foreign channel
+/// hook, if any. The mailbox is polled for at most the configured number of iterations.
+pub(crate) fn mb_drain(config: &MailboxConfig, channel: MailboxChannel) {
+    for _ in 0..config.timeout_iterations {
+        if (read_register(config, MAILBOX0_STATUS) & MAILBOX_EMPTY) != 0x0 {
+            return;
         }
+        let _ = mb_poll(config, channel);
     }
 }
 
+/// Check the mailbox once for data on the given channel. Data on any other channel is passed to the foreign
+/// channel hook, if any.
 #[inline]
-fn mb_write(channel: MailboxChannel, data: u32) -> MailboxResult<()> {
-    while (MAILBOX1_STATUS::Register.get() & MAILBOX_FULL) != 0x0 {}
-    let value = (data & 0xFFFF_FFF0) | ((channel as u8) & 0xF) as u32;
-    MAILBOX1_WRITE::Register.set(value);
-    Ok(())
+fn mb_poll(config: &MailboxConfig, channel: MailboxChannel) -> Option<u32> {
+    if (read_register(config, MAILBOX0_STATUS) & MAILBOX_EMPTY) != 0x0 {
+        return None;
+    }
+    let data = read_register(config, MAILBOX0_READ);
+    if (data & 0xF) == channel as u32 {
+        return Some(data & 0xFFFF_FFF0);
+    }
+    // the data is meant for someone else
+    if let Some(hook) = config.foreign_channel_hook {
+        hook(data & 0xF, data & 0xFFFF_FFF0);
+    }
+    None
+}
+
+/// Write the data to the mailbox on the given channel. The lower 4 bits of the value written are used for the
+/// channel, so the lower 4 bits of the data need to be 0. For the address of a message buffer this requires the
+/// buffer to be 16 byte aligned.
+#[inline]
+fn mb_write(config: &MailboxConfig, channel: MailboxChannel, data: u32) -> MailboxResult<()> {
+    debug_assert!(
+        data & 0xF == 0,
+        "the lower 4 bits of the mailbox data are reserved for the channel"
+    );
+    for _ in 0..config.timeout_iterations {
+        if (read_register(config, MAILBOX1_STATUS) & MAILBOX_FULL) == 0x0 {
+            let value = (data & 0xFFFF_FFF0) | ((channel as u8) & 0xF) as u32;
+            write_register(config, MAILBOX1_WRITE, value);
+            return Ok(());
+        }
+    }
+    Err(MailboxError::Timeout)
+}
+
+/// The address of the mailbox register at the given offset
+fn register_address(config: &MailboxConfig, offset: usize) -> usize {
+    config.peripheral_base + MAILBOX_OFFSET + offset
+}
+
+/// Read the mailbox register at the given offset
+#[cfg(not(test))]
+fn read_register(config: &MailboxConfig, offset: usize) -> u32 {
+    unsafe { core::ptr::read_volatile(register_address(config, offset) as *const u32) }
+}
+
+/// Write the mailbox register at the given offset
+#[cfg(not(test))]
+fn write_register(config: &MailboxConfig, offset: usize, value: u32) {
+    unsafe { core::ptr::write_volatile(register_address(config, offset) as *mut u32, value) }
+}
+
+/// When running the tests the mailbox registers are provided by the [mock](crate::mock)
+#[cfg(test)]
+fn read_register(config: &MailboxConfig, offset: usize) -> u32 {
+    crate::mock::read_register(register_address(config, offset))
+}
+
+#[cfg(test)]
+fn write_register(config: &MailboxConfig, offset: usize, value: u32) {
+    crate::mock::write_register(register_address(config, offset), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, ClockId, ClockrateGet, Mailbox, MailboxBatch, MessageState, PropertyTagId};
+
+    #[test]
+    fn bus_address_of_low_memory_buffer() {
+        assert_eq!(bus_address(0x0010_0000 as *const u32, 32), Ok(0xC010_0000));
+        assert_eq!(
+            bus_address((VC_ADDRESSABLE_LIMIT - 32) as *const u32, 32),
+            Ok(0xFFFF_FFE0)
+        );
+    }
+
+    #[test]
+    fn bounce_high_memory_buffer() {
+        let mut message =
+            crate::MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        let exchanged = std::rc::Rc::new(std::cell::Cell::new(core::ptr::null()));
+        let exchanged_buffer = exchanged.clone();
+        mock::install_raw(move |_, buffer| {
+            exchanged_buffer.set(buffer.as_ptr());
+            mock::process(buffer, &mut |_, values| {
+                values[1] = 250_000_000;
+                Some(8)
+            });
+            Ok(())
+        });
+
+        let buffer = message.as_mut_slice().as_mut_ptr();
+        bounce(buffer, 32, |bounce_buffer| {
+            exchange(
+                &MailboxConfig::new(),
+                MailboxChannel::PropertyTagsVc,
+                bounce_buffer,
+                32,
+                BufferMemory::Cached,
+            )
+        })
+        .unwrap();
+        // the VideoCore has only seen the bounce buffer
+        assert_eq!(exchanged.get(), BOUNCE_BUFFER.lock().as_slice().as_ptr());
+        assert_eq!(
+            message.as_slice(),
+            &[
+                32,
+                0x8000_0000,
+                0x3_0002,
+                8,
+                0x8000_0008,
+                0x4,
+                250_000_000,
+                0x0
+            ]
+        );
+    }
+
+    #[test]
+    fn bounce_rejects_large_message() {
+        let mut message = [0u32; BOUNCE_BUFFER_WORDS + 1];
+        assert_eq!(
+            bounce(
+                message.as_mut_ptr(),
+                core::mem::size_of_val(&message),
+                |_| Ok(())
+            ),
+            Err(MailboxError::BufferUnreachable)
+        );
+    }
+
+    #[test]
+    fn cache_ranges_cover_message() {
+        // the message of 8 words is placed in a larger buffer
+        let message = crate::MailboxScratch::with_words([
+            32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0, 0xAA, 0xAA,
+        ]);
+        let buffer = message.as_slice().as_ptr();
+        let start = buffer as usize;
+        assert_eq!(request_range(buffer, 40), start..start + 32);
+        assert_eq!(response_range(buffer, 40), start..start + 32);
+    }
+
+    #[test]
+    fn cache_ranges_bounded_by_buffer() {
+        // the header states a message larger than the buffer
+        let message = crate::MailboxScratch::with_words([64, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        let buffer = message.as_slice().as_ptr();
+        let start = buffer as usize;
+        assert_eq!(request_range(buffer, 32), start..start + 32);
+        assert_eq!(response_range(buffer, 32), start..start + 32);
+    }
+
+    // the check is a debug assertion, so it is only verified in builds with debug assertions enabled
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "the request range need to cover the message header and the end tag")]
+    fn cache_ranges_require_message_header() {
+        let message = crate::MailboxScratch::with_words([8, 0x0, 0x0, 0x0]);
+        request_range(message.as_slice().as_ptr(), 16);
+    }
+
+    #[test]
+    fn mailbox_registers_at_configured_base() {
+        let mailbox = Mailbox::builder().peripheral_base(0xFE00_0000).build();
+        // a response of the VideoCore is available on the property tag channel
+        mock::set_register(0xFE00_B880, 0x0010_0008);
+        assert_eq!(
+            mb_read(&mailbox.config, MailboxChannel::PropertyTagsVc),
+            Ok(0x0010_0000)
+        );
+        mb_write(&mailbox.config, MailboxChannel::PropertyTagsVc, 0xC010_0000).unwrap();
+        assert_eq!(mock::read_register(0xFE00_B8A0), 0xC010_0008);
+    }
+
+    #[test]
+    fn foreign_channel_data_passed_to_hook() {
+        std::thread_local! {
+            static FOREIGN: core::cell::RefCell<Vec<(u32, u32)>> = core::cell::RefCell::new(Vec::new());
+        }
+        let mailbox = Mailbox::builder()
+            .peripheral_base(0xFE00_0000)
+            .foreign_channel_hook(|channel, data| {
+                FOREIGN.with(|foreign| foreign.borrow_mut().push((channel, data)))
+            })
+            .build();
+        // a VCHIQ doorbell arrives before the response on the property tag channel
+        mock::queue_register(0xFE00_B880, &[0x0020_0003]);
+        mock::set_register(0xFE00_B880, 0x0010_0008);
+        assert_eq!(
+            mb_read(&mailbox.config, MailboxChannel::PropertyTagsVc),
+            Ok(0x0010_0000)
+        );
+        FOREIGN.with(|foreign| assert_eq!(*foreign.borrow(), [(0x3, 0x0020_0000)]));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "the lower 4 bits of the mailbox data are reserved for the channel")]
+    fn mailbox_data_need_to_keep_channel_bits() {
+        let mailbox = Mailbox::builder().peripheral_base(0xFE00_0000).build();
+        let _ = mb_write(&mailbox.config, MailboxChannel::PropertyTagsVc, 0xC010_0004);
+    }
+
+    #[test]
+    fn send_raw_rejects_misaligned_buffer() {
+        let mut message =
+            crate::MailboxScratch::with_words([0x0, 28, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        // the message starts 4 bytes behind the aligned start of the buffer
+        assert_eq!(
+            send_raw(
+                &MailboxConfig::new(),
+                MailboxChannel::PropertyTagsVc,
+                &mut message.as_mut_slice()[1..],
+                BufferMemory::Cached
+            ),
+            Err(MailboxError::MisalignedBuffer)
+        );
+    }
+
+    #[test]
+    fn mailbox_timeout_after_configured_iterations() {
+        let mailbox = Mailbox::builder()
+            .peripheral_base(0xFE00_0000)
+            .timeout_iterations(10)
+            .build();
+        // the mailbox stays empty and full
+        mock::set_register(0xFE00_B898, MAILBOX_EMPTY);
+        mock::set_register(0xFE00_B8B8, MAILBOX_FULL);
+        assert_eq!(
+            mb_read(&mailbox.config, MailboxChannel::PropertyTagsVc),
+            Err(MailboxError::Timeout)
+        );
+        assert_eq!(mock::register_reads(0xFE00_B898), 10);
+        assert_eq!(
+            mb_write(&mailbox.config, MailboxChannel::PropertyTagsVc, 0xC010_0000),
+            Err(MailboxError::Timeout)
+        );
+        assert_eq!(mock::register_reads(0xFE00_B8B8), 10);
+    }
+
+    #[test]
+    fn response_wait_not_bounded_by_timeout() {
+        let mailbox = Mailbox::builder()
+            .peripheral_base(0xFE00_0000)
+            .timeout_iterations(10)
+            .build();
+        // the VideoCore takes longer than the timeout iterations to respond
+        mock::queue_register(0xFE00_B898, &[MAILBOX_EMPTY; 20]);
+        mock::set_register(0xFE00_B898, 0x0);
+        mock::set_register(0xFE00_B880, 0x0010_0008);
+        assert_eq!(
+            mb_read_response(&mailbox.config, MailboxChannel::PropertyTagsVc),
+            0x0010_0000
+        );
+        assert_eq!(mock::register_reads(0xFE00_B898), 21);
+    }
+
+    #[test]
+    fn status_registers_at_configured_base() {
+        let mailbox = Mailbox::builder().peripheral_base(0xFE00_0000).build();
+        mock::set_register(0xFE00_B898, MAILBOX_EMPTY);
+        mock::set_register(0xFE00_B8B8, MAILBOX_FULL);
+        assert_eq!(mailbox.status(), (MAILBOX_EMPTY, MAILBOX_FULL));
+    }
+
+    #[test]
+    fn probe_unmapped_registers() {
+        let mailbox = Mailbox::builder().peripheral_base(0xFE00_0000).build();
+        mock::set_register(0xFE00_B898, MAILBOX_EMPTY);
+        assert_eq!(mailbox.probe_mmio(), Ok(()));
+        mock::set_register(0xFE00_B898, 0xFFFF_FFFF);
+        assert_eq!(mailbox.probe_mmio(), Err(MailboxError::NotMapped));
+    }
+
+    #[test]
+    fn timing_hook_around_exchange() {
+        std::thread_local! {
+            static EVENTS: core::cell::RefCell<Vec<&'static str>> = core::cell::RefCell::new(Vec::new());
+        }
+        fn record(event: &'static str) {
+            EVENTS.with(|events| events.borrow_mut().push(event));
+        }
+
+        mock::install(|_, values| {
+            record("exchange");
+            values[1] = 42_000;
+            Some(8)
+        });
+        let mut mailbox = Mailbox::builder()
+            .timing_hook(|event| match event {
+                TimingEvent::BeforeWrite => record("before write"),
+                TimingEvent::AfterRead => record("after read"),
+            })
+            .build();
+        assert_eq!(mailbox.get_temperature(), Ok(42_000));
+        EVENTS.with(|events| {
+            assert_eq!(*events.borrow(), ["before write", "exchange", "after read"]);
+        });
+    }
+
+    #[test]
+    fn high_memory_buffer_is_unreachable() {
+        // the buffer would cross the addressable range
+        assert_eq!(
+            bus_address((VC_ADDRESSABLE_LIMIT - 16) as *const u32, 32),
+            Err(MailboxError::BufferUnreachable)
+        );
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(
+            bus_address(0x1_0010_0000usize as *const u32, 32),
+            Err(MailboxError::BufferUnreachable)
+        );
+    }
+
+    #[test]
+    fn send_raw_clockrate_message() {
+        mock::Firmware::new()
+            .on(PropertyTagId::ClockrateGet, |values| {
+                if values[0] == ClockId::Core as u32 {
+                    values[1] = 250_000_000;
+                    Some(8)
+                } else {
+                    None
+                }
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        let mut message = MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice())
+            .unwrap();
+        let response = message.as_slice();
+        assert_eq!(response[1], MessageState::ResponseOk as u32);
+        assert_eq!(response[4], 0x8000_0008);
+        assert_eq!(response[6], 250_000_000);
+    }
+
+    #[test]
+    fn send_raw_rejects_invalid_header() {
+        mock::install_raw(|_, _| panic!("message with an invalid header passed to the VideoCore"));
+        let mut mb = Mailbox::new();
+        // the message size exceeds the buffer
+        let mut message = MailboxScratch::with_words([64, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        assert_eq!(
+            mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice()),
+            Err(MailboxError::InvalidHeader)
+        );
+        // the message is not a request
+        let mut message =
+            MailboxScratch::with_words([32, 0x8000_0000, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        assert_eq!(
+            mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice()),
+            Err(MailboxError::InvalidHeader)
+        );
+        // the buffer is too small to hold the message header and the end tag
+        let mut message = MailboxScratch::with_words([8, 0x0]);
+        assert_eq!(
+            mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice()),
+            Err(MailboxError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn send_raw_uncached_skips_cache_maintenance() {
+        mock::install(|_, values| {
+            values[1] = 250_000_000;
+            Some(8)
+        });
+        let mut mb = Mailbox::new();
+        let mut message = MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        mb.send_raw_uncached(MailboxChannel::PropertyTagsVc, message.as_mut_slice())
+            .unwrap();
+        assert_eq!(message.as_slice()[6], 250_000_000);
+        assert!(mock::cache_maintenance().is_empty());
+
+        // the cached path flushes the request and invalidates the response
+        message.as_mut_slice()[1] = MessageState::Request as u32;
+        mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice())
+            .unwrap();
+        let start = message.as_slice().as_ptr() as usize;
+        assert_eq!(
+            mock::cache_maintenance(),
+            [
+                mock::CacheMaintenance::Flush(start, 32),
+                mock::CacheMaintenance::Invalidate(start, 32)
+            ]
+        );
+    }
+
+    #[test]
+    fn request_on_receive_channel_rejected() {
+        mock::install(|_, _| Some(0));
+        let mut mb = Mailbox::new();
+        let batch = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
+        assert_eq!(
+            mb.send_batch_on(MailboxChannel::PropertyTagsArm, batch)
+                .err(),
+            Some(MailboxError::UnsupportedChannel(
+                MailboxChannel::PropertyTagsArm
+            ))
+        );
+        let mut message = MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        assert_eq!(
+            mb.send_raw(MailboxChannel::PropertyTagsArm, message.as_mut_slice()),
+            Err(MailboxError::UnsupportedChannel(
+                MailboxChannel::PropertyTagsArm
+            ))
+        );
+        assert_eq!(message.as_slice()[1], 0x0);
+    }
+
+    #[test]
+    fn send_on_framebuffer_channel_rejected() {
+        mock::install(|_, _| Some(0));
+        let mut mb = Mailbox::new();
+        let mut message = MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+        assert_eq!(
+            mb.send_raw(MailboxChannel::FrameBuffer, message.as_mut_slice()),
+            Err(MailboxError::UnsupportedChannel(
+                MailboxChannel::FrameBuffer
+            ))
+        );
+        assert_eq!(
+            mb.send_raw(MailboxChannel::VirtualUart, message.as_mut_slice()),
+            Err(MailboxError::UnsupportedChannel(
+                MailboxChannel::VirtualUart
+            ))
+        );
+        // the message shall not have been touched
+        assert_eq!(message.as_slice()[1], 0x0);
+    }
 }
@@ -1,6 +1,6 @@
 /***********************************************************************************************************************
  * Copyright (c) 2020 by the authors
- * 
+ *
  * Author: André Borrmann <pspwizard@gmx.de>
  * License: Apache License 2.0 / MIT
  **********************************************************************************************************************/
@@ -19,14 +19,18 @@
 //! - ArmMemoryGet
 //! - BoardMACAddressGet
 //! - VcMemoryGet
+//! - ClocksGet
 //! - DmaChannelsGet
 //! - PowerStateGet
 //! - PowerStateSet
 //! - ClockStateGet
 //! - ClockStateSet
+//! - DomainStateGet
+//! - DomainStateSet
 //! - ClockrateGet
 //! - ClockrateSet
 //! - MaxClockrateGet
+//! - MeasuredClockrateGet
 //! - MinClockrateGet
 //! - VoltageGet
 //! - VoltageSet
@@ -34,15 +38,21 @@
 //! - MinVoltageGet
 //! - TemperatureGet
 //! - MaxTemperatureGet
+//! - MemoryAllocate
+//! - MemoryLock
+//! - MemoryUnlock
+//! - MemoryRelease
 //! - FramebufferAllocate
 //! - FramebufferRelease
 //! - BlankScreen
 //! - PhysicalSizeGet
 //! - PhysicalSizeSet
+//! - PhysicalSizeTest
 //! - VirtualSizeGet
 //! - VirtualSizeSet
 //! - DepthGet
 //! - DepthSet
+//! - DepthTest
 //! - PixelOrderGet
 //! - PixelOrderSet
 //! - AlphaModeGet
@@ -54,6 +64,14 @@
 //! - OverscanSet
 //! - PaletteGet
 //! - PaletteSet
+//! - NumDisplaysGet
+//! - WaitForVsync
+//! - ThrottledGet
+//! - QpuEnable
+//! - DispmanxResourceMemHandleGet
+//! - EdidBlockGet
+//! - CustomerOtpGet
+//! - CommandLineGet
 //!
 //! Check the [official documentation](https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface)
 //! of those property tags and their purpose.
@@ -112,22 +130,58 @@
 //! # Hint
 //!
 //! The `Mailbox` represents a kind of a peripheral device of the Raspberry Pi. Therefore it should only be instantiated
-//! once. As the `Mailbox` is not capable to handle multiple parallel requests the `Mailbox` methods always require a 
+//! once. As the `Mailbox` is not capable to handle multiple parallel requests the `Mailbox` methods always require a
 //! mutable reference of it when called. This ensures exclusive access to the mailbox interface for message calls.
+//! To share the `Mailbox` between several cores the [SharedMailbox] guards the access with a mutex.
+//!
+//! # Byte Order
+//!
+//! The VideoCore reads and writes the messages in little-endian byte order and the property tags are accessed
+//! with the native byte order of the ARM core. Building for a big-endian target would silently swap the bytes of
+//! every value passed to and from the VideoCore. Therefore this crate refuses to compile for a big-endian target.
 //!
 //! # Features
 //!
 //! - `ruspiro_pi3` When active it ensures the proper MMIO base address is compiled for Raspberry Pi 3
+//! - `bounce_buffer` Message buffers the VideoCore is not able to address are passed through a bounce buffer
+//!   placed with the binary instead of being rejected
 //!
 
-use ruspiro_error::BoxError;
-
+mod board;
+pub use board::*;
+mod commandline;
+pub use commandline::*;
+mod diagnostic;
+pub use diagnostic::*;
+mod dmabuffer;
+pub use dmabuffer::*;
+mod error;
+pub use error::*;
+mod framebuffer;
+pub use framebuffer::*;
 mod interface;
 use interface::*;
+mod mailboxbuilder;
+pub use mailboxbuilder::*;
 mod propertytags;
 pub use propertytags::*;
+mod retrypolicy;
+pub use retrypolicy::*;
+mod sharedmailbox;
+pub use sharedmailbox::*;
 mod message;
 pub use message::*;
+mod units;
+pub use units::*;
+#[cfg(test)]
+mod mock;
+
+#[cfg(target_endian = "big")]
+compile_error!("the mailbox property tag interface requires a little-endian target");
+
+extern crate alloc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
 
 /// Definition of the different clock id's used in the mailbox interface
 #[repr(u32)]
@@ -146,6 +200,111 @@ pub enum ClockId {
     Emmc2 = 0xc,
 }
 
+impl ClockId {
+    /// The human readable name of the clock
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClockId::Emmc => "EMMC clock",
+            ClockId::Uart => "UART clock",
+            ClockId::Arm => "ARM clock",
+            ClockId::Core => "Core clock",
+            ClockId::V3D => "V3D clock",
+            ClockId::H264 => "H264 clock",
+            ClockId::Isp => "ISP clock",
+            ClockId::SdRam => "SDRAM clock",
+            ClockId::Pixel => "Pixel clock",
+            ClockId::Pwm => "PWM clock",
+            ClockId::Emmc2 => "EMMC2 clock",
+        }
+    }
+
+    /// All available [ClockId]s
+    pub const fn all() -> [ClockId; 11] {
+        [
+            ClockId::Emmc,
+            ClockId::Uart,
+            ClockId::Arm,
+            ClockId::Core,
+            ClockId::V3D,
+            ClockId::H264,
+            ClockId::Isp,
+            ClockId::SdRam,
+            ClockId::Pixel,
+            ClockId::Pwm,
+            ClockId::Emmc2,
+        ]
+    }
+}
+
+impl TryFrom<u32> for ClockId {
+    type Error = u32;
+
+    /// Convert the raw clock id into the corresponding [ClockId]. The raw id is returned if there is no
+    /// corresponding [ClockId].
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        match id {
+            0x1 => Ok(ClockId::Emmc),
+            0x2 => Ok(ClockId::Uart),
+            0x3 => Ok(ClockId::Arm),
+            0x4 => Ok(ClockId::Core),
+            0x5 => Ok(ClockId::V3D),
+            0x6 => Ok(ClockId::H264),
+            0x7 => Ok(ClockId::Isp),
+            0x8 => Ok(ClockId::SdRam),
+            0x9 => Ok(ClockId::Pixel),
+            0xa => Ok(ClockId::Pwm),
+            0xc => Ok(ClockId::Emmc2),
+            _ => Err(id),
+        }
+    }
+}
+
+/// The parent id reported by [Mailbox::get_clock_parents] for clocks without a parent clock
+pub const CLOCK_PARENT_NONE: u32 = 0x0;
+/// The parent id reported by [Mailbox::get_clock_parents] if the parent clock is not known to this crate
+pub const CLOCK_PARENT_UNKNOWN: u32 = u32::MAX;
+
+/// The state of the ARM clock as reported by [Mailbox::arm_clock_status]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ArmClockStatus {
+    /// The ARM frequency is currently capped by the firmware
+    pub capped: bool,
+    /// The rate the ARM clock is actually running at
+    pub measured_hz: Hertz,
+    /// The maximum rate of the ARM clock
+    pub max_hz: Hertz,
+}
+
+/// The throttling state as reported by [Mailbox::poll_throttle_events]. Both values use the bits 0..3 of the
+/// state reported by [Mailbox::get_throttled]: under-voltage, ARM frequency capped, throttled and soft
+/// temperature limit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThrottleStatus {
+    /// The conditions currently active
+    pub current: u32,
+    /// The conditions that have newly occurred since the previous poll
+    pub events: u32,
+}
+
+/// The result of the VCHIQ initialization as reported by the [VchiqInit] property tag
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VchiqStatus {
+    /// The VideoCore has accepted the slots and VCHIQ could be used
+    Initialized,
+    /// The VideoCore has rejected the slots with the given raw status
+    Failed(u32),
+}
+
+impl From<u32> for VchiqStatus {
+    /// Convert the raw status of the [VchiqInit] response, 0 signals success
+    fn from(status: u32) -> Self {
+        match status {
+            0 => VchiqStatus::Initialized,
+            _ => VchiqStatus::Failed(status),
+        }
+    }
+}
+
 /// Definition of the different Unique Device Id's on Raspberry Pi
 #[repr(u32)]
 #[derive(Copy, Clone, Debug)]
@@ -159,6 +318,49 @@ pub enum DeviceId {
     I2C2 = 0x0000_0006,
     Spi = 0x0000_0007,
     Ccp2Tx = 0x0000_0008,
+    /// The 3D graphics core. This id is not listed in the firmware documentation but used by the Linux power
+    /// domain driver on firmware without power domain support
+    V3d = 0x0000_000A,
+}
+
+impl DeviceId {
+    /// The human readable name of the device
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceId::SdCard => "SD Card",
+            DeviceId::Uart0 => "UART0",
+            DeviceId::Uart1 => "UART1",
+            DeviceId::UsbHcd => "USB HCD",
+            DeviceId::I2C0 => "I2C0",
+            DeviceId::I2C1 => "I2C1",
+            DeviceId::I2C2 => "I2C2",
+            DeviceId::Spi => "SPI",
+            DeviceId::Ccp2Tx => "CCP2TX",
+            DeviceId::V3d => "V3D",
+        }
+    }
+}
+
+impl TryFrom<u32> for DeviceId {
+    type Error = u32;
+
+    /// Convert the raw device id into the corresponding [DeviceId]. The raw id is returned if there is no
+    /// corresponding [DeviceId].
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        match id {
+            0x0 => Ok(DeviceId::SdCard),
+            0x1 => Ok(DeviceId::Uart0),
+            0x2 => Ok(DeviceId::Uart1),
+            0x3 => Ok(DeviceId::UsbHcd),
+            0x4 => Ok(DeviceId::I2C0),
+            0x5 => Ok(DeviceId::I2C1),
+            0x6 => Ok(DeviceId::I2C2),
+            0x7 => Ok(DeviceId::Spi),
+            0x8 => Ok(DeviceId::Ccp2Tx),
+            0xA => Ok(DeviceId::V3d),
+            _ => Err(id),
+        }
+    }
 }
 
 /// Definition of the different Voltage Id's on Raspberry Pi
@@ -171,9 +373,149 @@ pub enum VoltageId {
     SdRamI = 0x04,
 }
 
+impl VoltageId {
+    /// The human readable name of the voltage
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoltageId::Core => "Core",
+            VoltageId::SdRamC => "SDRAM Controller",
+            VoltageId::SdRamP => "SDRAM PHY",
+            VoltageId::SdRamI => "SDRAM I/O",
+        }
+    }
+
+    /// All available [VoltageId]s
+    pub const fn all() -> [VoltageId; 4] {
+        [
+            VoltageId::Core,
+            VoltageId::SdRamC,
+            VoltageId::SdRamP,
+            VoltageId::SdRamI,
+        ]
+    }
+}
+
+impl TryFrom<u32> for VoltageId {
+    type Error = u32;
+
+    /// Convert the raw voltage id into the corresponding [VoltageId]. The raw id is returned if there is no
+    /// corresponding [VoltageId].
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        VoltageId::all()
+            .iter()
+            .copied()
+            .find(|&voltage_id| voltage_id as u32 == id)
+            .ok_or(id)
+    }
+}
+
+/// Definition of the different power domain Id's on Raspberry Pi. The firmware numbers the power domains
+/// starting with 1, so each id is the index of the domain in the Raspberry Pi power device tree binding plus one.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DomainId {
+    I2C0 = 1,
+    I2C1 = 2,
+    I2C2 = 3,
+    VideoScaler = 4,
+    Vpu1 = 5,
+    Hdmi = 6,
+    Usb = 7,
+    Vec = 8,
+    Jpeg = 9,
+    H264 = 10,
+    V3D = 11,
+    Isp = 12,
+    Unicam0 = 13,
+    Unicam1 = 14,
+    Ccp2Rx = 15,
+    Csi2 = 16,
+    Cpi = 17,
+    Dsi0 = 18,
+    Dsi1 = 19,
+    Transposer = 20,
+    Ccp2Tx = 21,
+    Cdp = 22,
+    Arm = 23,
+}
+
+impl DomainId {
+    /// The human readable name of the power domain
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainId::I2C0 => "I2C0",
+            DomainId::I2C1 => "I2C1",
+            DomainId::I2C2 => "I2C2",
+            DomainId::VideoScaler => "Video Scaler",
+            DomainId::Vpu1 => "VPU1",
+            DomainId::Hdmi => "HDMI",
+            DomainId::Usb => "USB",
+            DomainId::Vec => "VEC",
+            DomainId::Jpeg => "JPEG",
+            DomainId::H264 => "H264",
+            DomainId::V3D => "V3D",
+            DomainId::Isp => "ISP",
+            DomainId::Unicam0 => "UNICAM0",
+            DomainId::Unicam1 => "UNICAM1",
+            DomainId::Ccp2Rx => "CCP2RX",
+            DomainId::Csi2 => "CSI2",
+            DomainId::Cpi => "CPI",
+            DomainId::Dsi0 => "DSI0",
+            DomainId::Dsi1 => "DSI1",
+            DomainId::Transposer => "Transposer",
+            DomainId::Ccp2Tx => "CCP2TX",
+            DomainId::Cdp => "CDP",
+            DomainId::Arm => "ARM",
+        }
+    }
+
+    /// All available [DomainId]s
+    pub const fn all() -> [DomainId; 23] {
+        [
+            DomainId::I2C0,
+            DomainId::I2C1,
+            DomainId::I2C2,
+            DomainId::VideoScaler,
+            DomainId::Vpu1,
+            DomainId::Hdmi,
+            DomainId::Usb,
+            DomainId::Vec,
+            DomainId::Jpeg,
+            DomainId::H264,
+            DomainId::V3D,
+            DomainId::Isp,
+            DomainId::Unicam0,
+            DomainId::Unicam1,
+            DomainId::Ccp2Rx,
+            DomainId::Csi2,
+            DomainId::Cpi,
+            DomainId::Dsi0,
+            DomainId::Dsi1,
+            DomainId::Transposer,
+            DomainId::Ccp2Tx,
+            DomainId::Cdp,
+            DomainId::Arm,
+        ]
+    }
+}
+
+impl TryFrom<u32> for DomainId {
+    type Error = u32;
+
+    /// Convert the raw power domain id into the corresponding [DomainId]. The raw id is returned if there is
+    /// no corresponding [DomainId].
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        DomainId::all()
+            .iter()
+            .copied()
+            .find(|&domain_id| domain_id as u32 == id)
+            .ok_or(id)
+    }
+}
+
 /// Definition of the different mailbox channels to be used for communication
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[allow(dead_code)]
 pub enum MailboxChannel {
     /// Power management channel
@@ -182,12 +524,31 @@ pub enum MailboxChannel {
     FrameBuffer = 0x1,
     /// Virtual UART channel
     VirtualUart = 0x2,
-    /// Property tag channel to send from ARM to VideoCore
+    /// Property tag channel to send from ARM to VideoCore. All requests the ARM initiates are passed on this
+    /// channel and the VideoCore responds on the same channel.
     PropertyTagsVc = 0x8,
-    /// Property tag channel to send from VideoCore to ARM
+    /// Property tag channel to send from VideoCore to ARM. This channel is reserved for the messages the VideoCore
+    /// initiates, see [Mailbox::receive_from_vc]. Requests of the ARM are rejected on this channel.
     PropertyTagsArm = 0x9,
 }
 
+impl MailboxChannel {
+    /// Check whether property tag messages could be passed on this channel. This is only the case for the
+    /// dedicated property tag channels.
+    pub fn is_valid_for_property_tags(&self) -> bool {
+        matches!(
+            self,
+            MailboxChannel::PropertyTagsVc | MailboxChannel::PropertyTagsArm
+        )
+    }
+
+    /// Check whether the ARM could initiate requests on this channel. This is only the case for the
+    /// [MailboxChannel::PropertyTagsVc] channel.
+    pub fn is_valid_for_requests(&self) -> bool {
+        *self == MailboxChannel::PropertyTagsVc
+    }
+}
+
 /// Definition of the different message stats/types used in the mailbox interface
 #[repr(u32)]
 #[derive(Copy, Clone, Debug)]
@@ -202,14 +563,48 @@ pub enum MessageState {
 }
 
 /// Type alias for Results of the functions in this module
-pub type MailboxResult<T> = Result<T, BoxError>;
+pub type MailboxResult<T> = Result<T, MailboxError>;
 
-/// MAILBOX peripheral representation
-pub struct Mailbox;
+/// MAILBOX peripheral representation. The [Mailbox] is ``Send`` and ``Sync`` as it does not contain any shared
+/// state. Passing a message requires a mutable reference, so the borrow rules ensure exclusive access. Use a
+/// [SharedMailbox] to access the single [Mailbox] from several cores.
+pub struct Mailbox {
+    /// The configuration used for each exchange with the mailbox peripheral
+    config: MailboxConfig,
+    /// The throttling conditions that have occurred as of the previous [Mailbox::poll_throttle_events]
+    throttle_occurred: u32,
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Mailbox {
     pub const fn new() -> Self {
-        Mailbox
+        Self::with_config(MailboxConfig::new())
+    }
+
+    /// Create a [MailboxBuilder] to configure the [Mailbox] to create
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::builder().timeout_iterations(0x1000).build();
+    /// # }
+    /// ```
+    pub const fn builder() -> MailboxBuilder {
+        MailboxBuilder::new()
+    }
+
+    pub(crate) const fn with_config(config: MailboxConfig) -> Self {
+        Mailbox {
+            config,
+            throttle_occurred: 0,
+        }
     }
 
     /// Send a mailbox batch message
@@ -226,10 +621,12 @@ impl Mailbox {
     /// # }
     /// ```
     pub fn send_batch<T>(&mut self, batch: MailboxBatch<T>) -> MailboxResult<MailboxBatch<T>> {
-        send_batch(MailboxChannel::PropertyTagsVc, batch)
+        send_batch(&self.config, MailboxChannel::PropertyTagsVc, batch)
     }
 
-    /// Get the firmware revision of this Raspberry Pi
+    /// Send a mailbox batch message on the given channel. Requests are only accepted on the
+    /// [MailboxChannel::PropertyTagsVc] channel [Mailbox::send_batch] is using, any other channel is rejected
+    /// with [MailboxError::UnsupportedChannel].
     ///
     /// # Example
     ///
@@ -237,18 +634,30 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let revision = mb.get_firmware_revision().unwrap();
+    /// let batch = MailboxBatch::empty()
+    ///                 .with_tag(ClockrateGet::new(ClockId::Core));
+    /// let _ = mb.send_batch_on(MailboxChannel::PropertyTagsVc, batch);
     /// # }
     /// ```
-    pub fn get_firmware_revision(&mut self) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            FirmwareRevisionGet::new().into(),
-        )
-        .map(|message| message.response().firmware_revision())
+    pub fn send_batch_on<T>(
+        &mut self,
+        channel: MailboxChannel,
+        batch: MailboxBatch<T>,
+    ) -> MailboxResult<MailboxBatch<T>> {
+        send_batch(&self.config, channel, batch)
     }
 
-    /// Get the board model of this Raspberry Pi
+    /// Send a hand crafted message buffer to the mailbox. This is meant for property tags that could not
+    /// be expressed with the [PropertyTag]s provided by this crate. The buffer need to be 16 byte aligned
+    /// and need to start with a valid message header: the message size in bytes followed by the
+    /// request code 0x0. The tags contained and the final end tag are up to the caller. Once this function
+    /// returns successfully the buffer contains the response of the VideoCore. A buffer without a valid
+    /// header, or with a message size exceeding the buffer, is rejected with [MailboxError::InvalidHeader]
+    /// as the VideoCore would access the memory behind the buffer.
+    ///
+    /// A [MailboxScratch] buffer ensures the required alignment. The lower 4 bits of the buffer address are
+    /// used to pass the channel to the mailbox, so a buffer that is not aligned is rejected with
+    /// [MailboxError::MisalignedBuffer].
     ///
     /// # Example
     ///
@@ -256,75 +665,88 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let model = mb.get_board_model().unwrap();
+    /// // get the core clock rate
+    /// let mut message = MailboxScratch::with_words([32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+    /// mb.send_raw(MailboxChannel::PropertyTagsVc, message.as_mut_slice()).unwrap();
+    /// let clock_rate = message.as_slice()[6];
     /// # }
     /// ```
-    pub fn get_board_model(&mut self) -> MailboxResult<u32> {
-        send_message(MailboxChannel::PropertyTagsVc, BoardModelGet::new().into())
-            .map(|message| message.response().board_model())
+    pub fn send_raw(&mut self, channel: MailboxChannel, buffer: &mut [u32]) -> MailboxResult<()> {
+        send_raw(&self.config, channel, buffer, BufferMemory::Cached)
     }
 
-    /// Get the board revision of this Raspberry Pi.
-    /// Check out https://www.raspberrypi.org/documentation/hardware/raspberrypi/revision-codes/README.md
-    /// for the encoding of the returned value
+    /// Send a hand crafted message buffer placed in uncached memory to the mailbox, e.g. a buffer allocated from a
+    /// device memory pool. The buffer is passed like with [Mailbox::send_raw], including the uncached alias of
+    /// the bus address, but the ARM data cache is neither flushed nor invalidated.
+    ///
+    /// # Caller Responsibility
+    ///
+    /// The buffer need to be placed in memory that is not cached by the ARM, otherwise the VideoCore reads a stale
+    /// request and the response might be overwritten by the cache afterwards. Buffers on the stack or in the
+    /// regular heap are typically cached and need to be passed with [Mailbox::send_raw].
     ///
     /// # Example
     ///
     /// ```no_run
     /// # use ruspiro_mailbox::*;
-    /// # fn doc() {
+    /// # fn doc(uncached: &mut [u32]) {
     /// let mut mb = Mailbox::new();
-    /// let revision = mb.get_board_revision().unwrap();
+    /// // the buffer has been allocated from uncached memory
+    /// uncached[..8].copy_from_slice(&[32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+    /// mb.send_raw_uncached(MailboxChannel::PropertyTagsVc, uncached).unwrap();
+    /// let clock_rate = uncached[6];
     /// # }
     /// ```
-    pub fn get_board_revision(&mut self) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            BoardRevisionGet::new().into(),
-        )
-        .map(|message| message.response().board_revision())
+    pub fn send_raw_uncached(
+        &mut self,
+        channel: MailboxChannel,
+        buffer: &mut [u32],
+    ) -> MailboxResult<()> {
+        send_raw(&self.config, channel, buffer, BufferMemory::Uncached)
     }
 
-    /// Get the MAC address of this Raspberry Pi
+    /// Read the raw status registers of the mailbox peripheral as ``(read_status, write_status)``. The read
+    /// status belongs to the mailbox the responses are read from and has bit 30 set if it is empty. The write
+    /// status belongs to the mailbox the messages are written to and has bit 31 set if it is full. This is meant
+    /// to diagnose a mailbox that does not respond.
     ///
     /// # Example
     ///
     /// ```no_run
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
-    /// let mut mb = Mailbox::new();
-    /// let mac_addr = mb.get_board_mac_address().unwrap();
+    /// let mb = Mailbox::new();
+    /// let (read_status, write_status) = mb.status();
+    /// println!("read empty: {}, write full: {}", read_status & (1 << 30) != 0, write_status & (1 << 31) != 0);
     /// # }
     /// ```
-    pub fn get_board_mac_address(&mut self) -> MailboxResult<[u8; 6]> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            BoardMACAddressGet::new().into(),
-        )
-        .map(|message| message.response().mac_address())
+    pub fn status(&self) -> (u32, u32) {
+        mb_status(&self.config)
     }
 
-    /// Get the ARM memory base address and size as configured in the boot config file.
-    /// Returns a tuple Ok((address:u32, size:u32)) on success or an Err(msg: &str) on failure
+    /// Check that the mailbox registers are accessible before the mailbox is used. With the MMU enabled the
+    /// mailbox registers need to be mapped as device memory. An unmapped region typically reads as all ones, which
+    /// is reported as [MailboxError::NotMapped]. A region that faults on access can not be detected this way.
     ///
     /// # Example
     ///
     /// ```no_run
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
-    /// let mut mb = Mailbox::new();
-    /// let (address, size) = mb.get_arm_memory().unwrap();
+    /// let mb = Mailbox::new();
+    /// if mb.probe_mmio().is_err() {
+    ///     println!("mailbox registers not mapped");
+    /// }
     /// # }
     /// ```
-    pub fn get_arm_memory(&mut self) -> MailboxResult<(u32, u32)> {
-        send_message(MailboxChannel::PropertyTagsVc, ArmMemoryGet::new().into()).map(|message| {
-            let response = message.response();
-            (response.base_address(), response.size())
-        })
+    pub fn probe_mmio(&self) -> MailboxResult<()> {
+        mb_probe(&self.config)
     }
 
-    /// Get the VideoCore memory base address and size as configured in the boot config file.
-    /// Returns a tuple Ok((address:u32, size:u32)) on success or an Err(msg: &str) on failure
+    /// Send a single property tag and retry it as given by the [RetryPolicy] if the mailbox times out or the
+    /// VideoCore responds with an error. Returns the processed tag of the first successful attempt or the error
+    /// of the last attempt. Before an attempt following a timeout any data pending in the mailbox is discarded,
+    /// so a late response to the previous attempt is not read as the response to the retry.
     ///
     /// # Example
     ///
@@ -332,19 +754,33 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let (address, size) = mb.get_vc_memory().unwrap();
+    /// // retry up to 5 timeouts but give up on the first error response
+    /// let temperature = mb
+    ///     .send_with_policy(TemperatureGet::new(), RetryPolicy::new(5, 1))
+    ///     .unwrap()
+    ///     .response()
+    ///     .value();
     /// # }
     /// ```
-    pub fn get_vc_memory(&mut self) -> MailboxResult<(u32, u32)> {
-        send_message(MailboxChannel::PropertyTagsVc, VcMemoryGet::new().into()).map(|message| {
-            let response = message.response();
-            (response.base_address(), response.size())
+    pub fn send_with_policy<T: PropertyTag + Copy>(
+        &mut self,
+        tag: T,
+        policy: RetryPolicy,
+    ) -> MailboxResult<T> {
+        let mut timed_out = false;
+        policy.run(|| {
+            if timed_out {
+                mb_drain(&self.config, MailboxChannel::PropertyTagsVc);
+            }
+            let result = self.send_tag(tag);
+            timed_out = matches!(result, Err(MailboxError::Timeout));
+            result
         })
     }
 
-    /// Get the active DMA channels.<br>
-    /// Bits 0-15  of the response represents the DMA channels 0-15. If the corresponding bit is set for a
-    /// channel it is usable. Bits 16-31 are reserved
+    /// Send a single property tag and pass it's response to the given closure to extract the values of interest.
+    /// This allows to query custom property tags in the same way the functions of the [Mailbox] are
+    /// querying the property tags provided by this crate.
     ///
     /// # Example
     ///
@@ -352,18 +788,55 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let dma_channel = mb.get_dma_channels().unwrap();
+    /// let (clock_id, rate) = mb
+    ///     .query(ClockrateGet::new(ClockId::Core), |response| {
+    ///         (response.clock_id(), response.clock_rate())
+    ///     })
+    ///     .unwrap();
     /// # }
     /// ```
-    pub fn get_dma_channels(&mut self) -> MailboxResult<u32> {
-        send_message(MailboxChannel::PropertyTagsVc, DmaChannelsGet::new().into())
-            .map(|message| message.response().channel_mask())
+    pub fn query<T, R, F>(&mut self, tag: T, extract: F) -> MailboxResult<R>
+    where
+        T: PropertyTag,
+        F: FnOnce(&T::Response) -> R,
+    {
+        self.send_tag(tag).map(|tag| extract(tag.response()))
     }
 
-    /// Get the power state of the given device id.
-    /// The returned state could have the following values:
-    /// Bit 0: 0 = off, 1 = on
-    /// Bit 1: 0 = device exists, 1 = device unknown
+    /// Send a single property tag in a [MailboxMessage] and return the processed tag. A property tag that is not a
+    /// multiple of 32 bits in size would be truncated and is rejected with [MailboxError::BadTagSize].
+    fn send_tag<T: PropertyTag>(&self, tag: T) -> MailboxResult<T> {
+        if T::WIRE_SIZE & 0x3 != 0 {
+            return Err(MailboxError::BadTagSize);
+        }
+        self.send_message(tag.into())
+            .map(|message| message.into_tag())
+    }
+
+    /// Send the message on the [MailboxChannel::PropertyTagsVc] channel
+    fn send_message<T: PropertyTag>(
+        &self,
+        message: MailboxMessage<T>,
+    ) -> MailboxResult<MailboxMessage<T>> {
+        send_message(&self.config, MailboxChannel::PropertyTagsVc, message)
+    }
+
+    /// Send the message containing several property tags of the same type on the
+    /// [MailboxChannel::PropertyTagsVc] channel
+    fn send_tag_array<T: PropertyTag + Copy, const N: usize>(
+        &self,
+        message: MailboxTagArray<T, N>,
+    ) -> MailboxResult<MailboxTagArray<T, N>> {
+        send_tag_array(&self.config, MailboxChannel::PropertyTagsVc, message)
+    }
+
+    /// Check whether the firmware supports the given property tag. The tag is send with a request where
+    /// all values are 0. It is treated as supported if the VideoCore has marked the tag as processed in the
+    /// response. This is only a heuristic with the following limitations:
+    /// - The tag is really processed, so setting tags will apply the zeroed request. Only use it to probe
+    ///   tags that just retrieve values.
+    /// - Tags that reject the zeroed request are reported as not supported even if the firmware knows them.
+    /// - Only the first 10 value words of the response are provided, larger responses are truncated.
     ///
     /// # Example
     ///
@@ -371,24 +844,44 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let state = mb.get_powerstate(DeviceId::Uart0).unwrap();
+    /// if mb.tag_supported(PropertyTagId::ThrottledGet).unwrap() {
+    ///     let throttled = mb.get_throttled().unwrap();
+    /// }
     /// # }
     /// ```
-    pub fn get_powerstate(&mut self, device_id: DeviceId) -> MailboxResult<u32> {
-        send_message(
+    pub fn tag_supported(&mut self, id: PropertyTagId) -> MailboxResult<bool> {
+        // message header, tag header, value buffer and end tag
+        const WORDS: usize = 16;
+        const VALUE_WORDS: usize = WORDS - 6;
+        let mut message = MailboxScratch::<WORDS>::new();
+        let buffer = message.as_mut_slice();
+        buffer[..4].copy_from_slice(&[
+            (WORDS << 2) as u32,
+            MessageState::Request as u32,
+            id as u32,
+            (VALUE_WORDS << 2) as u32,
+        ]);
+        match send_raw(
+            &self.config,
             MailboxChannel::PropertyTagsVc,
-            PowerStateGet::new(device_id).into(),
-        )
-        .map(|message| message.response().state())
+            buffer,
+            BufferMemory::Cached,
+        ) {
+            // bit 31 of the tag state is set once the VideoCore has processed the tag
+            Ok(()) => Ok(buffer[4] & 0x8000_0000 != 0),
+            Err(MailboxError::ResponseError) => Ok(false),
+            Err(error) => Err(error),
+        }
     }
 
-    /// Set the power state of the given device id.
-    /// The state to be set should contain those values:
-    /// Bit 0: 0 = off, 1 = on
-    /// Bit 1: 0 = don't wait for device state change, 1 = wait for device state change
-    /// The returned state contains the following values:
-    /// Bit 0: 0 = off, 1 = on
-    /// Bit 1: 0 = device exists, 1 = device unknown
+    /// Receive a message the VideoCore has initiated on the [MailboxChannel::PropertyTagsArm] channel. This is
+    /// the inverse direction of all other mailbox requests.
+    ///
+    /// The handshake works as follows: The VideoCore writes the bus address of a property tag message buffer
+    /// into the ARM's mailbox using the channel 9. This call waits for such a message, verifies the message
+    /// contains exactly one property tag of the requested type and hands the buffer back to the VideoCore
+    /// by writing the address to the VideoCore's mailbox on the same channel. The buffer is handed back even if it
+    /// does not contain the requested property tag.
     ///
     /// # Example
     ///
@@ -396,21 +889,21 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let new_state = mb.set_powerstate(DeviceId::Uart0, 0b11).unwrap();
+    /// let tag = mb.receive_from_vc::<BlankScreen>().unwrap();
+    /// let state = tag.response().state();
     /// # }
     /// ```
-    pub fn set_powerstate(&mut self, device_id: DeviceId, state: u32) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            PowerStateSet::new(device_id, state).into(),
-        )
-        .map(|message| message.response().state())
+    pub fn receive_from_vc<T: PropertyTag>(&mut self) -> MailboxResult<T> {
+        receive_message(&self.config, MailboxChannel::PropertyTagsArm)
     }
 
-    /// Get the clock state of the given clock id.
-    /// The returned state could have the following values:
-    /// Bit 0: 0 = off, 1 = on
-    /// Bit 1: 0 = clock exists, 1 = clock unknown
+    /// Guess whether the mailbox is emulated, e.g. by QEMU, instead of being served by the firmware of a real
+    /// Raspberry Pi. This allows tests running on both to skip the checks that need real hardware. The mailbox
+    /// is treated as emulated if the firmware revision is 0 or the fixed revision reported by QEMU, or if the
+    /// board serial number is 0.
+    ///
+    /// This is only a heuristic. Emulators reporting a plausible firmware revision and serial number, or a
+    /// future QEMU version reporting a different revision, are not detected.
     ///
     /// # Example
     ///
@@ -418,23 +911,31 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let state = mb.get_clockstate(ClockId::Core).unwrap();
+    /// if !mb.is_emulated().unwrap() {
+    ///     assert!(mb.get_temperature().unwrap() > 0);
+    /// }
     /// # }
     /// ```
-    pub fn get_clockstate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            ClockStateGet::new(clock_id).into(),
-        )
-        .map(|message| message.response().state())
+    pub fn is_emulated(&mut self) -> MailboxResult<bool> {
+        // the firmware revision QEMU reports for all emulated Raspberry Pi boards
+        const QEMU_FIRMWARE_REVISION: u32 = 346_337;
+        let batch = MailboxBatch::empty()
+            .with_tag(FirmwareRevisionGet::new())
+            .with_tag(BoardSerialGet::new());
+        let batch = self.send_batch(batch)?;
+        let revision = batch
+            .get_tag::<FirmwareRevisionGet, _>()
+            .response()
+            .firmware_revision();
+        let serial = batch.get_tag::<BoardSerialGet, _>().response().serial();
+
+        Ok(revision == 0 || revision == QEMU_FIRMWARE_REVISION || serial == 0)
     }
 
-    /// Set the clock state of the given clock id.
-    /// The state to be set should contain those values:
-    /// Bit 0: 0 = off, 1 = on
-    /// The returned state contains the following values:
-    /// Bit 0: 0 = off, 1 = on
-    /// Bit 1: 0 = clock exists, 1 = clock unknown
+    /// Verify the VideoCore firmware is up and responds to mailbox messages. This sends the cheapest query
+    /// available, retrieving the firmware revision, and does not change any state of the VideoCore. So it
+    /// could be called as often as required. If the mailbox does not accept the message in time
+    /// [MailboxError::Timeout] is returned.
     ///
     /// # Example
     ///
@@ -442,19 +943,16 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let new_state = mb.set_clockstate(ClockId::Core, 0b1).unwrap();
+    /// if mb.ping().is_ok() {
+    ///     let revision = mb.get_board_revision().unwrap();
+    /// }
     /// # }
     /// ```
-    pub fn set_clockstate(&mut self, clock_id: ClockId, state: u32) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            ClockStateSet::new(clock_id, state).into(),
-        )
-        .map(|message| message.response().state())
+    pub fn ping(&mut self) -> MailboxResult<()> {
+        self.get_firmware_revision().map(|_| ())
     }
 
-    /// Get the clock rate via mailbox interface for the clockId given.
-    /// Returns Ok(rate:u32) on success or Err(msg: &str) on failure
+    /// Get the firmware revision of this Raspberry Pi
     ///
     /// # Example
     ///
@@ -462,19 +960,17 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let clock_rate = mb.get_clockrate(ClockId::Core).unwrap();
+    /// let revision = mb.get_firmware_revision().unwrap();
     /// # }
     /// ```
-    pub fn get_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            ClockrateGet::new(clock_id).into(),
-        )
-        .map(|message| message.response().clock_rate())
+    pub fn get_firmware_revision(&mut self) -> MailboxResult<u32> {
+        self.send_message(FirmwareRevisionGet::new().into())
+            .map(|message| message.response().firmware_revision())
     }
 
-    /// Set the clock rate via the mailbox interface for the clockId given. The rate will be set to the closest valid
-    /// value. Returns Ok(rate:u32) with the new clock rate set on success ore Err(msg: &str) on failure
+    /// Get the board model of this Raspberry Pi. The model is decoded from the board revision code, the value
+    /// reported for the board model is kept as [BoardModel::Unknown] if the revision code does not provide the
+    /// model. The raw model code is still available with [BoardModel::code].
     ///
     /// # Example
     ///
@@ -482,18 +978,29 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let new_clock_rate = mb.set_clockrate(ClockId::Core, 250_000_000).unwrap();
+    /// let model = mb.get_board_model().unwrap();
+    /// println!("Running on {}", model.name());
     /// # }
     /// ```
-    pub fn set_clockrate(&mut self, clock_id: ClockId, rate: u32) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            ClockrateSet::new(clock_id, rate, 0x0).into(),
-        )
-        .map(|message| message.response().clock_rate())
+    pub fn get_board_model(&mut self) -> MailboxResult<BoardModel> {
+        let batch = MailboxBatch::empty()
+            .with_tag(BoardModelGet::new())
+            .with_tag(BoardRevisionGet::new());
+        let batch = self.send_batch(batch)?;
+        Ok(BoardModel::from_tags(
+            batch.get_tag::<BoardModelGet, _>().response().board_model(),
+            batch
+                .get_tag::<BoardRevisionGet, _>()
+                .response()
+                .board_revision(),
+        ))
     }
 
-    /// Get the maximum available clock rate for the given clock id
+    /// Detect the board family of this Raspberry Pi based on the board revision code. The returned
+    /// [Board] provides the peripheral base address that applies to the detected board. This allows to
+    /// verify the board at runtime the crate has been compiled for with the corresponding feature. If the
+    /// firmware does not report a board revision the board is [Board::Unknown], as the value reported for
+    /// the board model has no documented encoding that would allow to tell the boards apart.
     ///
     /// # Example
     ///
@@ -501,18 +1008,17 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let max_rate = mb.get_max_clockrate(ClockId::Core).unwrap();
+    /// let board = mb.detect_board().unwrap();
+    /// let peripheral_base = board.peripheral_base();
     /// # }
     /// ```
-    pub fn get_max_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            MaxClockrateGet::new(clock_id).into(),
-        )
-        .map(|message| message.response().clock_rate())
+    pub fn detect_board(&mut self) -> MailboxResult<Board> {
+        self.get_board_revision().map(Board::from_revision)
     }
 
-    /// Get the minimum available clock rate for the given clock id
+    /// Get the board revision of this Raspberry Pi.
+    /// Check out https://www.raspberrypi.org/documentation/hardware/raspberrypi/revision-codes/README.md
+    /// for the encoding of the returned value
     ///
     /// # Example
     ///
@@ -520,19 +1026,18 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let min_rate = mb.get_min_clockrate(ClockId::Core).unwrap();
+    /// let revision = mb.get_board_revision().unwrap();
     /// # }
     /// ```
-    pub fn get_min_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            MinClockrateGet::new(clock_id).into(),
-        )
-        .map(|message| message.response().clock_rate())
+    pub fn get_board_revision(&mut self) -> MailboxResult<u32> {
+        self.send_message(BoardRevisionGet::new().into())
+            .map(|message| message.response().board_revision())
     }
 
-    /// Get the current voltage of the given [VoltageId]. The value represents an offset from
-    /// 1.2V in units of 0.025V.
+    /// Get the full 64 bit serial number of this Raspberry Pi. The firmware reports the serial in two
+    /// words that are combined little-endian, the first word containing the lower 32 bits. The firmware
+    /// always returns both words, but the upper word may be 0, in which case the result equals the 32 bit
+    /// serial number.
     ///
     /// # Example
     ///
@@ -540,19 +1045,16 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let voltage = mb.get_voltage(VoltageId::Core).unwrap();
+    /// let serial = mb.get_board_serial_64().unwrap();
+    /// println!("Serial: {:016x}", serial);
     /// # }
     /// ```
-    pub fn get_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            VoltageGet::new(voltage_id).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn get_board_serial_64(&mut self) -> MailboxResult<u64> {
+        self.send_message(BoardSerialGet::new().into())
+            .map(|message| message.response().serial())
     }
 
-    /// Set the current voltage for the given [VoltageId]. The value represents an offset from
-    /// 1.2V in units of 0.025V.
+    /// Get the MAC address of this Raspberry Pi
     ///
     /// # Example
     ///
@@ -560,19 +1062,18 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let new_voltage = mb.set_voltage(VoltageId::Core, 10).unwrap();
+    /// let mac_addr = mb.get_board_mac_address().unwrap();
     /// # }
     /// ```
-    pub fn set_voltage(&mut self, voltage_id: VoltageId, value: u32) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            VoltageSet::new(voltage_id, value).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn get_board_mac_address(&mut self) -> MailboxResult<[u8; 6]> {
+        self.send_message(BoardMACAddressGet::new().into())
+            .map(|message| message.response().mac_address())
     }
 
-    /// Get the maximum voltage of the given [VoltageId]. The value represents an offset from
-    /// 1.2V in units of 0.025V.
+    /// Get the clock hierarchy as reported by the firmware. Each entry contains a clock and the raw id of it's
+    /// parent clock. Clocks without a parent are reported with [CLOCK_PARENT_NONE] and parents that do not
+    /// correspond to a [ClockId] with [CLOCK_PARENT_UNKNOWN]. Clocks that do not correspond to a [ClockId]
+    /// are skipped.
     ///
     /// # Example
     ///
@@ -580,19 +1081,34 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let max_voltage = mb.get_max_voltage(VoltageId::Core).unwrap();
+    /// for (clock, parent) in mb.get_clock_parents().unwrap() {
+    ///     println!("{} -> {}", clock.name(), parent);
+    /// }
     /// # }
     /// ```
-    pub fn get_max_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            MaxVoltageGet::new(voltage_id).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn get_clock_parents(&mut self) -> MailboxResult<Vec<(ClockId, u32)>> {
+        let message = self.send_message(ClocksGet::new().into())?;
+        let tag = message.into_tag();
+        // only the pairs of parent and clock id covered by the response are valid
+        let clocks = tag
+            .response_words()
+            .chunks_exact(2)
+            .filter_map(|pair| {
+                let clock = ClockId::try_from(pair[1]).ok()?;
+                let parent = match pair[0] {
+                    CLOCK_PARENT_NONE => CLOCK_PARENT_NONE,
+                    parent => ClockId::try_from(parent).map_or(CLOCK_PARENT_UNKNOWN, |_| parent),
+                };
+                Some((clock, parent))
+            })
+            .collect();
+
+        Ok(clocks)
     }
 
-    /// Get the minimum voltage of the given [VoltageId]. The value represents an offset from
-    /// 1.2V in units of 0.025V.
+    /// Get the command line the firmware passes to the kernel. The [CommandLine] provides the values of the
+    /// individual keys. This fails with [MailboxError::UnexpectedMessage] if the command line is not valid
+    /// UTF-8.
     ///
     /// # Example
     ///
@@ -600,18 +1116,19 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let min_voltage = mb.get_min_voltage(VoltageId::Core).unwrap();
+    /// let command_line = mb.get_command_line().unwrap();
+    /// let console = command_line.get("console");
     /// # }
     /// ```
-    pub fn get_min_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            MinVoltageGet::new(voltage_id).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn get_command_line(&mut self) -> MailboxResult<CommandLine> {
+        let mut buffer = [0; COMMAND_LINE_LENGTH];
+        let len = self.get_command_line_into(&mut buffer)?;
+        CommandLine::from_bytes(&buffer[..len]).ok_or(MailboxError::UnexpectedMessage)
     }
 
-    /// Get the current temperature in thousandths of a degree Celsius.
+    /// Copy the command line the firmware passes to the kernel into the given buffer and return the number of
+    /// bytes written. The command line is not terminated with a 0 byte. This fails with
+    /// [MailboxError::BufferTooSmall] if the command line does not fit into the buffer.
     ///
     /// # Example
     ///
@@ -619,19 +1136,35 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let temperature = mb.get_temperature().unwrap();
+    /// let mut buffer = [0; 256];
+    /// let len = mb.get_command_line_into(&mut buffer).unwrap();
+    /// let command_line = &buffer[..len];
     /// # }
     /// ```
-    pub fn get_temperature(&mut self) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            TemperatureGet::new(0x0).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn get_command_line_into(&mut self, buffer: &mut [u8]) -> MailboxResult<usize> {
+        let message = self.send_message(CommandLineGet::new().into())?;
+        let tag = message.into_tag();
+        // the size of the response state is the length of the command line
+        let len = (tag.state() & 0x7FFF_FFFF) as usize;
+        let command_line = tag.response().command_line_ref();
+        let command_line = &command_line[..len.min(command_line.len())];
+        // the command line might be terminated by the VideoCore
+        let len = command_line
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(command_line.len());
+        if len > buffer.len() {
+            return Err(MailboxError::BufferTooSmall { required: len });
+        }
+        buffer[..len].copy_from_slice(&command_line[..len]);
+
+        Ok(len)
     }
 
-    /// Get the maximum safe temperature in thousandths of a degree Celsius. Above this temperature
-    /// overclocking/turbo might get deactivated
+    /// Copy the EDID of the attached display into the given buffer and return the number of bytes written. The
+    /// EDID consists of the base block and the number of extension blocks given in the base block, each of
+    /// 128 bytes. This fails with [MailboxError::BufferTooSmall] if not all blocks fit into the buffer. Nothing
+    /// is copied in this case.
     ///
     /// # Example
     ///
@@ -639,18 +1172,39 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let max_temp = mb.get_max_temperature().unwrap();
+    /// let mut buffer = [0; 512];
+    /// let len = mb.read_edid_into(&mut buffer).unwrap();
+    /// let edid = &buffer[..len];
     /// # }
     /// ```
-    pub fn get_max_temperature(&self) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            MaxTemperatureGet::new(0x0).into(),
-        )
-        .map(|message| message.response().value())
+    pub fn read_edid_into(&mut self, buffer: &mut [u8]) -> MailboxResult<usize> {
+        const BLOCK_SIZE: usize = 128;
+        let mut blocks = 1;
+        let mut block = 0;
+        while block < blocks {
+            let message = self.send_message(EdidBlockGet::new(block as u32).into())?;
+            let response = message.response();
+            if response.status() != 0 {
+                return Err(MailboxError::ResponseError);
+            }
+            let edid = response.edid_ref();
+            if block == 0 {
+                // the last but one byte of the base block contains the number of extension blocks
+                blocks += edid[126] as usize;
+                let required = blocks * BLOCK_SIZE;
+                if required > buffer.len() {
+                    return Err(MailboxError::BufferTooSmall { required });
+                }
+            }
+            buffer[block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE].copy_from_slice(edid);
+            block += 1;
+        }
+
+        Ok(blocks * BLOCK_SIZE)
     }
 
-    /// Send the VCHIQ slots base address to the VideoCore
+    /// Read the value of a single row of the customer OTP. The customer OTP consists of the rows 0 to 7,
+    /// reading any other row is rejected by the firmware.
     ///
     /// # Example
     ///
@@ -658,14 +1212,2183 @@ impl Mailbox {
     /// # use ruspiro_mailbox::*;
     /// # fn doc() {
     /// let mut mb = Mailbox::new();
-    /// let _ = mb.set_vchiq_slot_base(0xBEEF_0000).unwrap();
+    /// let value = mb.read_otp_row(0).unwrap();
     /// # }
     /// ```
-    pub fn set_vchiq_slot_base(&mut self, slot_base: u32) -> MailboxResult<u32> {
-        send_message(
-            MailboxChannel::PropertyTagsVc,
-            VchiqInit::new(slot_base).into(),
-        )
-        .map(|message| message.response().status())
+    pub fn read_otp_row(&mut self, row: u32) -> MailboxResult<u32> {
+        self.send_tag(CustomerOtpGet::new(row, 1))
+            .map(|tag| tag.response().values_ref()[0])
+    }
+
+    /// Get the ARM memory base address and size as configured in the boot config file.
+    /// Returns a tuple Ok((address:u32, size:u32)) on success or an Err(msg: &str) on failure
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (address, size) = mb.get_arm_memory().unwrap();
+    /// # }
+    /// ```
+    pub fn get_arm_memory(&mut self) -> MailboxResult<(u32, u32)> {
+        self.send_message(ArmMemoryGet::new().into())
+            .map(|message| {
+                let response = message.response();
+                (response.base_address(), response.size())
+            })
+    }
+
+    /// Get the ARM memory as configured in the boot config file as typed [MemoryRegion]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let region = mb.get_arm_memory_region().unwrap();
+    /// println!("ARM memory: {}", region);
+    /// # }
+    /// ```
+    pub fn get_arm_memory_region(&mut self) -> MailboxResult<MemoryRegion> {
+        self.get_arm_memory()
+            .map(|(base_address, size)| MemoryRegion { base_address, size })
+    }
+
+    /// Suggest the memory window an allocator could use as heap. The window is the ARM memory without the
+    /// ``reserve_low`` bytes at it's start (e.g. the kernel image) and the ``reserve_high`` bytes at it's end
+    /// (e.g. the stacks or DMA buffers). Returns a tuple Ok((address:u32, size:u32)) on success or
+    /// [MailboxError::ReservationTooLarge] if the reservations do not leave any memory for the heap.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (heap_start, heap_size) = mb.suggest_heap_region(0x10_0000, 0x8_0000).unwrap();
+    /// # }
+    /// ```
+    pub fn suggest_heap_region(
+        &mut self,
+        reserve_low: u32,
+        reserve_high: u32,
+    ) -> MailboxResult<(u32, u32)> {
+        let (base_address, size) = self.get_arm_memory()?;
+        let heap_size = size
+            .checked_sub(reserve_low)
+            .and_then(|size| size.checked_sub(reserve_high))
+            .filter(|&heap_size| heap_size > 0)
+            .ok_or(MailboxError::ReservationTooLarge)?;
+        let heap_start = base_address
+            .checked_add(reserve_low)
+            .ok_or(MailboxError::ReservationTooLarge)?;
+        Ok((heap_start, heap_size))
+    }
+
+    /// Get the VideoCore memory base address and size as configured in the boot config file.
+    /// Returns a tuple Ok((address:u32, size:u32)) on success or an Err(msg: &str) on failure
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (address, size) = mb.get_vc_memory().unwrap();
+    /// # }
+    /// ```
+    pub fn get_vc_memory(&mut self) -> MailboxResult<(u32, u32)> {
+        self.send_message(VcMemoryGet::new().into()).map(|message| {
+            let response = message.response();
+            (response.base_address(), response.size())
+        })
+    }
+
+    /// Get the VideoCore memory as configured in the boot config file as typed [MemoryRegion]
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let region = mb.get_vc_memory_region().unwrap();
+    /// println!("VideoCore memory: {} MiB", region.size_mib());
+    /// # }
+    /// ```
+    pub fn get_vc_memory_region(&mut self) -> MailboxResult<MemoryRegion> {
+        self.get_vc_memory()
+            .map(|(base_address, size)| MemoryRegion { base_address, size })
+    }
+
+    /// Get the active DMA channels.<br>
+    /// Bits 0-15  of the response represents the DMA channels 0-15. If the corresponding bit is set for a
+    /// channel it is usable. Bits 16-31 are reserved
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let dma_channel = mb.get_dma_channels().unwrap();
+    /// # }
+    /// ```
+    pub fn get_dma_channels(&mut self) -> MailboxResult<u32> {
+        self.send_message(DmaChannelsGet::new().into())
+            .map(|message| message.response().channel_mask())
+    }
+
+    /// Get the power state of the given device id.
+    /// The returned state could have the following values:
+    /// Bit 0: 0 = off, 1 = on
+    /// Bit 1: 0 = device exists, 1 = device unknown
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let state = mb.get_powerstate(DeviceId::Uart0).unwrap();
+    /// # }
+    /// ```
+    pub fn get_powerstate(&mut self, device_id: DeviceId) -> MailboxResult<u32> {
+        self.send_message(PowerStateGet::new(device_id).into())
+            .map(|message| message.response().state())
+    }
+
+    /// Set the power state of the given device id.
+    /// The state to be set should contain those values:
+    /// Bit 0: 0 = off, 1 = on
+    /// Bit 1: 0 = don't wait for device state change, 1 = wait for device state change
+    /// The returned state contains the following values:
+    /// Bit 0: 0 = off, 1 = on
+    /// Bit 1: 0 = device exists, 1 = device unknown
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let new_state = mb.set_powerstate(DeviceId::Uart0, 0b11).unwrap();
+    /// # }
+    /// ```
+    pub fn set_powerstate(&mut self, device_id: DeviceId, state: u32) -> MailboxResult<u32> {
+        self.send_message(PowerStateSet::new(device_id, state).into())
+            .map(|message| message.response().state())
+    }
+
+    /// Switch the given device on or off and wait until the device reports the new state. The state is set
+    /// with the "wait for device state change" bit. Afterwards the power state is polled at most ``max_polls``
+    /// times, calling the given ``delay`` function before each poll, until the device reports the requested
+    /// state. So the timeout is given by the number of polls and the delay between them.
+    /// Returns [MailboxError::Timeout] if the device has not reached the state in time and
+    /// [MailboxError::Unsupported] if the device is unknown to the firmware.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// // wait up to 100 polls with some delay between them for the SD card to be powered
+    /// mb.set_power_state_wait(DeviceId::SdCard, true, 100, || {
+    ///     for _ in 0..1000 { core::hint::spin_loop() }
+    /// }).unwrap();
+    /// # }
+    /// ```
+    pub fn set_power_state_wait<F: FnMut()>(
+        &mut self,
+        device_id: DeviceId,
+        on: bool,
+        max_polls: u32,
+        mut delay: F,
+    ) -> MailboxResult<()> {
+        let requested = on as u32;
+        let state = self.set_powerstate(device_id, 0b10 | requested)?;
+        if state & 0b10 != 0 {
+            return Err(MailboxError::Unsupported);
+        }
+        if state & 0b1 == requested {
+            return Ok(());
+        }
+        for _ in 0..max_polls {
+            delay();
+            if self.get_powerstate(device_id)? & 0b1 == requested {
+                return Ok(());
+            }
+        }
+
+        Err(MailboxError::Timeout)
+    }
+
+    /// Get the clock state of the given clock id.
+    /// The returned state could have the following values:
+    /// Bit 0: 0 = off, 1 = on
+    /// Bit 1: 0 = clock exists, 1 = clock unknown
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let state = mb.get_clockstate(ClockId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_clockstate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
+        self.send_message(ClockStateGet::new(clock_id).into())
+            .map(|message| message.response().state())
+    }
+
+    /// Set the clock state of the given clock id.
+    /// The state to be set should contain those values:
+    /// Bit 0: 0 = off, 1 = on
+    /// The returned state contains the following values:
+    /// Bit 0: 0 = off, 1 = on
+    /// Bit 1: 0 = clock exists, 1 = clock unknown
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let new_state = mb.set_clockstate(ClockId::Core, 0b1).unwrap();
+    /// # }
+    /// ```
+    pub fn set_clockstate(&mut self, clock_id: ClockId, state: u32) -> MailboxResult<u32> {
+        self.send_message(ClockStateSet::new(clock_id, state).into())
+            .map(|message| message.response().state())
+    }
+
+    /// Enable the V3D block to use the QPUs for computations. This powers the V3D block first and enables
+    /// the [ClockId::V3D] clock afterwards. If powering the V3D block fails the clock is not touched.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// mb.enable_v3d().unwrap();
+    /// // now the QPUs could be used
+    /// mb.disable_v3d().unwrap();
+    /// # }
+    /// ```
+    pub fn enable_v3d(&mut self) -> MailboxResult<()> {
+        self.set_qpu_enable(1)?;
+        // bit 0 of the clock state confirms the clock is running
+        if self.set_clockstate(ClockId::V3D, 0b1)? & 0b11 != 0b1 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(())
+    }
+
+    /// Disable the V3D block in the reverse order of [Mailbox::enable_v3d]. The [ClockId::V3D] clock is
+    /// stopped first and the V3D block is powered down afterwards.
+    pub fn disable_v3d(&mut self) -> MailboxResult<()> {
+        if self.set_clockstate(ClockId::V3D, 0b0)? & 0b11 != 0b0 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        self.set_qpu_enable(0)
+    }
+
+    /// Enable or disable the QPUs and with them the power of the V3D block
+    fn set_qpu_enable(&mut self, enable: u32) -> MailboxResult<()> {
+        let status = self
+            .send_message(QpuEnable::new(enable).into())?
+            .response()
+            .status();
+        if status != 0 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(())
+    }
+
+    /// Get the clock rate via mailbox interface for the clockId given.
+    /// Returns Ok(rate:u32) on success or Err(msg: &str) on failure
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let clock_rate = mb.get_clockrate(ClockId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
+        self.send_tag(ClockrateGet::new(clock_id))
+            .map(|tag| tag.response().clock_rate())
+    }
+
+    /// Get the clock rate for the given clock id like [Mailbox::get_clockrate] does. The firmware reports a rate
+    /// of 0 for a clock that does not exist on the board, in this case ``Ok(None)`` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// if let Some(rate) = mb.get_clockrate_opt(ClockId::Emmc2).unwrap() {
+    ///     println!("EMMC2 clock: {} Hz", rate);
+    /// }
+    /// # }
+    /// ```
+    pub fn get_clockrate_opt(&mut self, clock_id: ClockId) -> MailboxResult<Option<u32>> {
+        self.get_clockrate(clock_id)
+            .map(|rate| if rate == 0 { None } else { Some(rate) })
+    }
+
+    /// Get the clock rate for the given clock id like [Mailbox::get_clockrate] does. If the clock does not
+    /// exist on the board, reported with a rate of 0 by the firmware, the given default rate is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let rate = mb.get_clockrate_or(ClockId::Emmc2, 100_000_000).unwrap();
+    /// # }
+    /// ```
+    pub fn get_clockrate_or(&mut self, clock_id: ClockId, default: u32) -> MailboxResult<u32> {
+        self.get_clockrate_opt(clock_id)
+            .map(|rate| rate.unwrap_or(default))
+    }
+
+    /// Set the clock rate via the mailbox interface for the clockId given. The rate will be set to the closest valid
+    /// value. Returns Ok(rate:u32) with the new clock rate set on success ore Err(msg: &str) on failure
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let new_clock_rate = mb.set_clockrate(ClockId::Core, 250_000_000).unwrap();
+    /// # }
+    /// ```
+    pub fn set_clockrate(&mut self, clock_id: ClockId, rate: u32) -> MailboxResult<u32> {
+        self.send_message(ClockrateSet::new(clock_id, rate, 0x0).into())
+            .map(|message| message.response().clock_rate())
+    }
+
+    /// Set the clock rate for the given clock id like [Mailbox::set_clockrate] does. Returns the requested and
+    /// the granted clock rate as ``(requested, granted)`` on success, e.g. to log both while tuning the clocks.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (requested, granted) = mb.set_clockrate_verbose(ClockId::Arm, 1_234_000_000).unwrap();
+    /// println!("ARM clock rate requested {} got {}", requested, granted);
+    /// # }
+    /// ```
+    pub fn set_clockrate_verbose(
+        &mut self,
+        clock_id: ClockId,
+        rate: u32,
+    ) -> MailboxResult<(u32, u32)> {
+        self.set_clockrate(clock_id, rate)
+            .map(|granted| (rate, granted))
+    }
+
+    /// Set the rates of several clocks with a single message, so the VideoCore applies all of them at once.
+    /// The rates are given as ``(clock_id, rate)`` and each rate will be set to the closest valid value.
+    /// Returns the granted rates in the order the clocks are given.
+    ///
+    /// The number of clocks is only known at runtime, so the message is not build as a [MailboxBatch] but
+    /// contains one [ClockrateSet] property tag for each clock. Each clock should be given only once, as
+    /// there are at most as many [ClockrateSet] tags as there are [ClockId]s. Passing more rates fails with
+    /// [MailboxError::BatchTooLarge].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let granted = mb
+    ///     .set_clockrates(&[(ClockId::Arm, 1_200_000_000), (ClockId::Core, 400_000_000)])
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn set_clockrates(
+        &mut self,
+        rates: &[(ClockId, u32)],
+    ) -> MailboxResult<Vec<(ClockId, u32)>> {
+        let mut message = MailboxTagArray::<ClockrateSet, { ClockId::all().len() }>::new();
+        for &(clock_id, rate) in rates {
+            message.push(ClockrateSet::new(clock_id, rate, 0))?;
+        }
+        self.send_tag_array(message)?
+            .tags()
+            .map(|tag| {
+                let response = tag.response();
+                response
+                    .clock_id()
+                    .map(|clock_id| (clock_id, response.clock_rate()))
+                    .map_err(|_| MailboxError::UnexpectedMessage)
+            })
+            .collect()
+    }
+
+    /// Get the clock rate for the given clock id as typed [Hertz] value
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let clock_rate = mb.get_clockrate_hz(ClockId::Core).unwrap();
+    /// println!("Core clock rate: {}", clock_rate);
+    /// # }
+    /// ```
+    pub fn get_clockrate_hz(&mut self, clock_id: ClockId) -> MailboxResult<Hertz> {
+        self.get_clockrate(clock_id).map(Hertz)
+    }
+
+    /// Set the clock rate for the given clock id from a typed [Hertz] value. The rate will be set to the
+    /// closest valid value which is returned on success.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let new_clock_rate = mb.set_clockrate_hz(ClockId::Core, Hertz::from_mhz(250)).unwrap();
+    /// # }
+    /// ```
+    pub fn set_clockrate_hz(&mut self, clock_id: ClockId, rate: Hertz) -> MailboxResult<Hertz> {
+        self.set_clockrate(clock_id, rate.0).map(Hertz)
+    }
+
+    /// Get the maximum available clock rate for the given clock id
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let max_rate = mb.get_max_clockrate(ClockId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_max_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
+        self.send_message(MaxClockrateGet::new(clock_id).into())
+            .map(|message| message.response().clock_rate())
+    }
+
+    /// Get the state of the ARM clock to diagnose a slow running system. The returned [ArmClockStatus] tells
+    /// whether the ARM frequency is currently capped and the actual measured and maximum ARM clock rate.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let status = mb.arm_clock_status().unwrap();
+    /// if status.capped {
+    ///     println!("ARM capped at {} of max {}", status.measured_hz, status.max_hz);
+    /// }
+    /// # }
+    /// ```
+    pub fn arm_clock_status(&mut self) -> MailboxResult<ArmClockStatus> {
+        let batch = MailboxBatch::empty()
+            .with_tag(ThrottledGet::new(0x0))
+            .with_tag(MeasuredClockrateGet::new(ClockId::Arm))
+            .with_tag(MaxClockrateGet::new(ClockId::Arm));
+        let batch = self.send_batch(batch)?;
+        // bit 1 of the throttling state reports the ARM frequency being capped currently
+        let throttled = batch.get_tag::<ThrottledGet, _>().response().state();
+
+        Ok(ArmClockStatus {
+            capped: throttled & 0b10 != 0,
+            measured_hz: Hertz(
+                batch
+                    .get_tag::<MeasuredClockrateGet, _>()
+                    .response()
+                    .clock_rate(),
+            ),
+            max_hz: Hertz(
+                batch
+                    .get_tag::<MaxClockrateGet, _>()
+                    .response()
+                    .clock_rate(),
+            ),
+        })
+    }
+
+    /// Compare the clock rate set for the given clock id with the rate it is actually running at. Returns
+    /// ``(set, measured, measured - set)``. A clock running below the rate it has been set to indicates the
+    /// firmware throttling it, e.g. due to high temperature or under-voltage.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (set, measured, diff) = mb.clock_discrepancy(ClockId::Arm).unwrap();
+    /// if diff < 0 {
+    ///     println!("ARM set to {} but running at {}", set, measured);
+    /// }
+    /// # }
+    /// ```
+    pub fn clock_discrepancy(&mut self, clock_id: ClockId) -> MailboxResult<(u32, u32, i32)> {
+        let batch = MailboxBatch::empty()
+            .with_tag(ClockrateGet::new(clock_id))
+            .with_tag(MeasuredClockrateGet::new(clock_id));
+        let batch = self.send_batch(batch)?;
+        let set = batch.get_tag::<ClockrateGet, _>().response().clock_rate();
+        let measured = batch
+            .get_tag::<MeasuredClockrateGet, _>()
+            .response()
+            .clock_rate();
+        let diff = (measured as i64 - set as i64).clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+
+        Ok((set, measured, diff))
+    }
+
+    /// Get the minimum available clock rate for the given clock id
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let min_rate = mb.get_min_clockrate(ClockId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_min_clockrate(&mut self, clock_id: ClockId) -> MailboxResult<u32> {
+        self.send_message(MinClockrateGet::new(clock_id).into())
+            .map(|message| message.response().clock_rate())
+    }
+
+    /// Get the current voltage of the given [VoltageId]. The value represents an offset from
+    /// 1.2V in units of 0.025V.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let voltage = mb.get_voltage(VoltageId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
+        self.send_message(VoltageGet::new(voltage_id).into())
+            .map(|message| message.response().value())
+    }
+
+    /// Set the current voltage for the given [VoltageId]. The value represents an offset from
+    /// 1.2V in units of 0.025V.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let new_voltage = mb.set_voltage(VoltageId::Core, 10).unwrap();
+    /// # }
+    /// ```
+    pub fn set_voltage(&mut self, voltage_id: VoltageId, value: u32) -> MailboxResult<u32> {
+        self.send_message(VoltageSet::new(voltage_id, value).into())
+            .map(|message| message.response().value())
+    }
+
+    /// Get the maximum voltage of the given [VoltageId]. The value represents an offset from
+    /// 1.2V in units of 0.025V.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let max_voltage = mb.get_max_voltage(VoltageId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_max_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
+        self.send_message(MaxVoltageGet::new(voltage_id).into())
+            .map(|message| message.response().value())
+    }
+
+    /// Get the minimum voltage of the given [VoltageId]. The value represents an offset from
+    /// 1.2V in units of 0.025V.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let min_voltage = mb.get_min_voltage(VoltageId::Core).unwrap();
+    /// # }
+    /// ```
+    pub fn get_min_voltage(&mut self, voltage_id: VoltageId) -> MailboxResult<u32> {
+        self.send_message(MinVoltageGet::new(voltage_id).into())
+            .map(|message| message.response().value())
+    }
+
+    /// Get the current voltage of all [VoltageId]s with a single batch message. The values represent an
+    /// offset from 1.2V in units of 0.025V. They are returned in the order of [VoltageId::all].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// for (voltage_id, value) in mb.get_all_voltages().unwrap().iter() {
+    ///     println!("{:?}: {}", voltage_id, value);
+    /// }
+    /// # }
+    /// ```
+    pub fn get_all_voltages(&mut self) -> MailboxResult<[(VoltageId, u32); 4]> {
+        let [core, sdram_c, sdram_p, sdram_i] = VoltageId::all();
+        let batch = MailboxBatch::empty()
+            .with_tag(VoltageGet::new(core))
+            .with_tag(VoltageGet::new(sdram_c))
+            .with_tag(VoltageGet::new(sdram_p))
+            .with_tag(VoltageGet::new(sdram_i));
+        let batch = self.send_batch(batch)?;
+        // as the same tag type is contained multiple times in the batch the position of the tag
+        // need to be given explicitly. The last tag added is found [Here]
+        Ok([
+            (
+                core,
+                batch
+                    .get_tag::<VoltageGet, Next<Next<Next<Here>>>>()
+                    .response()
+                    .value(),
+            ),
+            (
+                sdram_c,
+                batch
+                    .get_tag::<VoltageGet, Next<Next<Here>>>()
+                    .response()
+                    .value(),
+            ),
+            (
+                sdram_p,
+                batch.get_tag::<VoltageGet, Next<Here>>().response().value(),
+            ),
+            (
+                sdram_i,
+                batch.get_tag::<VoltageGet, Here>().response().value(),
+            ),
+        ])
+    }
+
+    /// Get the state of all [DomainId]s with a single message. Bit 0 of the state is set if the power
+    /// domain is powered on. The states are returned in the order of [DomainId::all].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// for (domain_id, state) in mb.get_all_domain_states().unwrap() {
+    ///     println!("{}: {}", domain_id.name(), state & 0x1 != 0);
+    /// }
+    /// # }
+    /// ```
+    pub fn get_all_domain_states(&mut self) -> MailboxResult<Vec<(DomainId, u32)>> {
+        let message = MailboxTagArray::from_tags(DomainId::all().map(DomainStateGet::new));
+        self.send_tag_array(message)?
+            .tags()
+            .map(|tag| {
+                let response = tag.response();
+                response
+                    .domain_id()
+                    .map(|domain_id| (domain_id, response.state()))
+                    .map_err(|_| MailboxError::UnexpectedMessage)
+            })
+            .collect()
+    }
+
+    /// Get the current temperature in thousandths of a degree Celsius.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let temperature = mb.get_temperature().unwrap();
+    /// # }
+    /// ```
+    pub fn get_temperature(&mut self) -> MailboxResult<u32> {
+        self.send_tag(TemperatureGet::new())
+            .map(|tag| tag.response().value())
+    }
+
+    /// Get the throttling state of the Raspberry Pi.
+    /// The returned state contains the following values:
+    /// Bit 0: under-voltage detected
+    /// Bit 1: ARM frequency capped
+    /// Bit 2: currently throttled
+    /// Bit 3: soft temperature limit active
+    /// Bits 16..19: the corresponding state has occurred since the last reset or clearing of these flags
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let throttled = mb.get_throttled().unwrap();
+    /// # }
+    /// ```
+    pub fn get_throttled(&mut self) -> MailboxResult<u32> {
+        self.send_tag(ThrottledGet::new(0x0))
+            .map(|tag| tag.response().state())
+    }
+
+    /// Clear the sticky "occurred" flags (bits 16..19) of the throttling state. Returns the throttling state
+    /// as it was before clearing the flags. This allows to detect new under-voltage or throttling events
+    /// after a baseline has been taken. The firmware clears the sticky flags corresponding to the bits of the
+    /// lower half word passed with the [ThrottledGet] request while reading the state. Like the Linux
+    /// ``raspberrypi-hwmon`` driver the mask 0xFFFF is passed to clear all of them. Firmware versions not
+    /// supporting this ignore the flags to clear and the sticky flags remain set until the next reset.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let _ = mb.clear_throttled_flags().unwrap();
+    /// // any under-voltage flagged from now on is a new event
+    /// let under_voltage = mb.get_throttled().unwrap() & (1 << 16) != 0;
+    /// # }
+    /// ```
+    pub fn clear_throttled_flags(&mut self) -> MailboxResult<u32> {
+        self.send_tag(ThrottledGet::new(0xFFFF))
+            .map(|tag| tag.response().state())
+    }
+
+    /// Poll the throttling conditions that have occurred since the previous call. The sticky "occurred" flags
+    /// are read and cleared at once, so with each call the firmware reports the conditions that occurred
+    /// in between. A condition is reported as event if it has occurred since the previous call but not
+    /// before. As firmware versions not supporting to clear the flags keep them set until the next reset,
+    /// comparing with the flags of the previous call detects the new conditions with those versions as well.
+    /// However, each condition is reported only once with them.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// loop {
+    ///     let status = mb.poll_throttle_events().unwrap();
+    ///     if status.events & 0b1 != 0 {
+    ///         println!("under-voltage detected");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn poll_throttle_events(&mut self) -> MailboxResult<ThrottleStatus> {
+        let state = self.clear_throttled_flags()?;
+        let occurred = (state >> 16) & 0xF;
+        let events = occurred & !self.throttle_occurred;
+        self.throttle_occurred = occurred;
+
+        Ok(ThrottleStatus {
+            current: state & 0xF,
+            events,
+        })
+    }
+
+    /// Query the board, the firmware and the current operating state for a [DiagnosticReport]. The queries
+    /// are combined into three messages: one for the board, firmware, memory, temperature and throttling state,
+    /// one for the rates of all clocks and one for all voltages.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// println!("{}", mb.diagnostic_report().unwrap());
+    /// # }
+    /// ```
+    pub fn diagnostic_report(&mut self) -> MailboxResult<DiagnosticReport> {
+        let batch = MailboxBatch::empty()
+            .with_tag(BoardModelGet::new())
+            .with_tag(BoardRevisionGet::new())
+            .with_tag(FirmwareRevisionGet::new())
+            .with_tag(ArmMemoryGet::new())
+            .with_tag(VcMemoryGet::new())
+            .with_tag(TemperatureGet::new())
+            .with_tag(ThrottledGet::new(0x0));
+        let batch = self.send_batch(batch)?;
+        let arm_memory = batch.get_tag::<ArmMemoryGet, _>().response();
+        let vc_memory = batch.get_tag::<VcMemoryGet, _>().response();
+        let board_revision = batch
+            .get_tag::<BoardRevisionGet, _>()
+            .response()
+            .board_revision();
+
+        let message = MailboxTagArray::from_tags(ClockId::all().map(ClockrateGet::new));
+        let message = self.send_tag_array(message)?;
+        let mut clock_rates = [(ClockId::Emmc, Hertz(0)); ClockId::all().len()];
+        for (clock_rate, tag) in clock_rates.iter_mut().zip(message.tags()) {
+            let response = tag.response();
+            *clock_rate = (
+                response
+                    .clock_id()
+                    .map_err(|_| MailboxError::UnexpectedMessage)?,
+                Hertz(response.clock_rate()),
+            );
+        }
+
+        Ok(DiagnosticReport {
+            board_model: BoardModel::from_tags(
+                batch.get_tag::<BoardModelGet, _>().response().board_model(),
+                board_revision,
+            ),
+            board_revision,
+            firmware_revision: batch
+                .get_tag::<FirmwareRevisionGet, _>()
+                .response()
+                .firmware_revision(),
+            arm_memory: MemoryRegion {
+                base_address: arm_memory.base_address(),
+                size: arm_memory.size(),
+            },
+            vc_memory: MemoryRegion {
+                base_address: vc_memory.base_address(),
+                size: vc_memory.size(),
+            },
+            clock_rates,
+            temperature: batch.get_tag::<TemperatureGet, _>().response().value(),
+            throttled: batch.get_tag::<ThrottledGet, _>().response().state(),
+            voltages: self.get_all_voltages()?,
+        })
+    }
+
+    /// Get the maximum safe temperature in thousandths of a degree Celsius. Above this temperature
+    /// overclocking/turbo might get deactivated
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let max_temp = mb.get_max_temperature().unwrap();
+    /// # }
+    /// ```
+    ///
+    /// Like any other mailbox exchange this requires exclusive access to the [Mailbox], so it could not
+    /// be called through shared references:
+    ///
+    /// ```compile_fail
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mb = Mailbox::new();
+    /// let (first, second) = (&mb, &mb);
+    /// let _ = first.get_max_temperature();
+    /// let _ = second.get_max_temperature();
+    /// # }
+    /// ```
+    pub fn get_max_temperature(&mut self) -> MailboxResult<u32> {
+        self.send_message(MaxTemperatureGet::new().into())
+            .map(|message| message.response().value())
+    }
+
+    /// Get the maximum framebuffer size the firmware is able to provide.
+    ///
+    /// The firmware clamps any requested physical size to the maximum it supports and reports the clamped
+    /// size back. So requesting a huge size with the [PhysicalSizeTest] property tag and reading the
+    /// response reveals the maximum size. As the test tag does not apply anything, this does not change
+    /// the current framebuffer configuration.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let max_size = mb.get_max_framebuffer_size().unwrap();
+    /// # }
+    /// ```
+    pub fn get_max_framebuffer_size(&mut self) -> MailboxResult<Size> {
+        self.send_message(PhysicalSizeTest::new(u32::MAX, u32::MAX).into())
+            .map(|message| message.response().size())
+    }
+
+    /// Blank the screen. This does not clear the content of the framebuffer, which need to be done by the
+    /// caller writing to the framebuffer memory, for example with [Framebuffer::fill].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// mb.clear_screen().unwrap();
+    /// # }
+    /// ```
+    pub fn clear_screen(&mut self) -> MailboxResult<()> {
+        self.send_message(BlankScreen::new(1).into()).map(|_| ())
+    }
+
+    /// Set the virtual framebuffer size and return the size actually granted by the firmware. The granted
+    /// size might differ from the requested one as the firmware chooses the closest supported
+    /// configuration. If there is no supported configuration at all an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let size = mb.set_virtual_size_checked(1024, 768).unwrap();
+    /// # }
+    /// ```
+    pub fn set_virtual_size_checked(&mut self, width: u32, height: u32) -> MailboxResult<Size> {
+        let message = self.send_message(VirtualSizeSet::new(width, height).into())?;
+        match message.response().size() {
+            Size {
+                width: 0,
+                height: 0,
+            } => Err(MailboxError::Unsupported),
+            size => Ok(size),
+        }
+    }
+
+    /// Set the offset of the visible area into the virtual framebuffer. The physical size is the visible area
+    /// and the virtual size the whole framebuffer it is placed in. The current physical and virtual sizes are
+    /// queried first and an offset that would move the visible area outside of the virtual framebuffer is
+    /// rejected with [MailboxError::OffsetOutOfBounds] without changing the offset. Returns the offset the
+    /// VideoCore has applied.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// // scroll the view by one line
+    /// let (x, y) = mb.set_virtual_offset_checked(0, 1).unwrap();
+    /// # }
+    /// ```
+    pub fn set_virtual_offset_checked(&mut self, x: u32, y: u32) -> MailboxResult<(u32, u32)> {
+        let batch = MailboxBatch::empty()
+            .with_tag(PhysicalSizeGet::new())
+            .with_tag(VirtualSizeGet::new());
+        let batch = self.send_batch(batch)?;
+        let physical_size = batch.get_tag::<PhysicalSizeGet, _>().response();
+        let virtual_size = batch.get_tag::<VirtualSizeGet, _>().response();
+        let exceeds = |offset: u32, size: u32, limit: u32| {
+            offset
+                .checked_add(size)
+                .filter(|&end| end <= limit)
+                .is_none()
+        };
+        if exceeds(x, physical_size.width(), virtual_size.width())
+            || exceeds(y, physical_size.height(), virtual_size.height())
+        {
+            return Err(MailboxError::OffsetOutOfBounds);
+        }
+
+        let message = self.send_message(VirtualOffsetSet::new(x, y).into())?;
+        let response = message.response();
+        Ok((response.offset_x(), response.offset_y()))
+    }
+
+    /// Show the given page of the framebuffer and wait until it is displayed. The framebuffer is treated as
+    /// pages of the current physical size, the visible area, stacked on top of each other within the virtual
+    /// size, so page ``n`` starts at the line ``n * physical height``. A page that does not fit completely into
+    /// the virtual size, i.e. an offset beyond ``virtual height - physical height``, is rejected with
+    /// [MailboxError::OffsetOutOfBounds] without changing the offset.
+    ///
+    /// The virtual offset of the page is set together with the [WaitForVsync] property tag in one batch. As
+    /// the VideoCore processes the tags in order the response is received after the next vertical sync, once
+    /// the new offset is displayed. The [WaitForVsync] tag is not documented and only known to recent firmware.
+    /// A firmware that does not know it responds with [MailboxError::ResponseError], but might have applied
+    /// the offset anyway, so the page is shown without waiting for the vertical sync in this case. Boards
+    /// without a display attached might not provide a vertical sync at all.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// // double buffering: draw into page 1 while page 0 is displayed, then flip
+    /// mb.flip_framebuffer_vsync(1).unwrap();
+    /// # }
+    /// ```
+    pub fn flip_framebuffer_vsync(&mut self, page: u32) -> MailboxResult<()> {
+        let batch = MailboxBatch::empty()
+            .with_tag(PhysicalSizeGet::new())
+            .with_tag(VirtualSizeGet::new());
+        let batch = self.send_batch(batch)?;
+        let physical_size = batch.get_tag::<PhysicalSizeGet, _>().response().size();
+        let virtual_size = batch.get_tag::<VirtualSizeGet, _>().response().size();
+        let max_offset_y = virtual_size
+            .height
+            .checked_sub(physical_size.height)
+            .ok_or(MailboxError::OffsetOutOfBounds)?;
+        let offset_y = page
+            .checked_mul(physical_size.height)
+            .filter(|&offset_y| offset_y <= max_offset_y)
+            .ok_or(MailboxError::OffsetOutOfBounds)?;
+
+        let batch = MailboxBatch::empty()
+            .with_tag(VirtualOffsetSet::new(0, offset_y))
+            .with_tag(WaitForVsync::new());
+        self.send_batch(batch).map(|_| ())
+    }
+
+    /// Create a [FramebufferBuilder] to allocate a framebuffer with the given physical size
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let framebuffer = mb
+    ///     .framebuffer(1024, 768)
+    ///     .virtual_size(1024, 1536)
+    ///     .depth(Depth::Bpp16)
+    ///     .allocate()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn framebuffer(&mut self, width: u32, height: u32) -> FramebufferBuilder<'_> {
+        FramebufferBuilder::new(self, width, height)
+    }
+
+    /// Get the current overscan of the display
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let overscan = mb.get_overscan().unwrap();
+    /// # }
+    /// ```
+    pub fn get_overscan(&mut self) -> MailboxResult<Overscan> {
+        self.send_message(OverscanGet::new().into())
+            .map(|message| message.response().overscan())
+    }
+
+    /// Set the overscan of the display. Returns the overscan the VideoCore has applied.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let overscan = mb.set_overscan(Overscan::default()).unwrap();
+    /// # }
+    /// ```
+    pub fn set_overscan(&mut self, overscan: Overscan) -> MailboxResult<Overscan> {
+        self.send_message(OverscanSet::from_overscan(overscan).into())
+            .map(|message| message.response().overscan())
+    }
+
+    /// Get the number of displays attached to the VideoCore, e.g. to enumerate both HDMI outputs of a
+    /// Raspberry Pi 4. Firmware that does not know the property tag supports a single display only, so 1 is
+    /// returned in this case.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let num_displays = mb.get_num_displays().unwrap();
+    /// # }
+    /// ```
+    pub fn get_num_displays(&mut self) -> MailboxResult<u32> {
+        match self.send_tag(NumDisplaysGet::new()) {
+            Ok(tag) => Ok(tag.response().num_displays()),
+            Err(MailboxError::ResponseError) => Ok(1),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Get the framebuffer depths supported by the firmware. Each depth is probed with the [DepthTest]
+    /// property tag, so the current framebuffer configuration is not changed. A depth is supported if the
+    /// firmware reports it back unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// if mb.supported_depths().unwrap().contains(&Depth::Bpp8) {
+    ///     println!("palettized framebuffer supported");
+    /// }
+    /// # }
+    /// ```
+    pub fn supported_depths(&mut self) -> MailboxResult<Vec<Depth>> {
+        let mut depths = Vec::new();
+        for &depth in [Depth::Bpp8, Depth::Bpp16, Depth::Bpp24, Depth::Bpp32].iter() {
+            let tag = self.send_tag(DepthTest::new(depth as u32))?;
+            if tag.response().depth() == depth as u32 {
+                depths.push(depth);
+            }
+        }
+
+        Ok(depths)
+    }
+
+    /// Get the format of the pixels of the current framebuffer as ``(pixel order, alpha mode, depth)``. This is
+    /// everything required to interpret the pixels in the framebuffer memory. A value not known to this crate is
+    /// reported as error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let (order, alpha, depth) = mb.get_color_format().unwrap();
+    /// # }
+    /// ```
+    pub fn get_color_format(&mut self) -> MailboxResult<(PixelOrder, AlphaMode, Depth)> {
+        let batch = MailboxBatch::empty()
+            .with_tag(PixelOrderGet::new())
+            .with_tag(AlphaModeGet::new())
+            .with_tag(DepthGet::new());
+        let batch = self.send_batch(batch)?;
+
+        Ok((
+            PixelOrder::try_from(batch.get_tag::<PixelOrderGet, _>().response().order())?,
+            AlphaMode::try_from(batch.get_tag::<AlphaModeGet, _>().response().mode())?,
+            Depth::try_from(batch.get_tag::<DepthGet, _>().response().depth())?,
+        ))
+    }
+
+    /// Get the current order of the color components of a pixel. A pixel order not known to this crate is
+    /// reported as [MailboxError::UnknownPixelOrder].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let order = mb.get_pixel_order().unwrap();
+    /// # }
+    /// ```
+    pub fn get_pixel_order(&mut self) -> MailboxResult<PixelOrder> {
+        let message = self.send_message(PixelOrderGet::new().into())?;
+        PixelOrder::try_from(message.response().order())
+    }
+
+    /// Set the order of the color components of a pixel. Returns the pixel order the firmware has applied, this
+    /// might differ from the requested one if it is not supported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let order = mb.set_pixel_order(PixelOrder::Rgb).unwrap();
+    /// # }
+    /// ```
+    pub fn set_pixel_order(&mut self, order: PixelOrder) -> MailboxResult<PixelOrder> {
+        let message = self.send_message(PixelOrderSet::new(order as u32).into())?;
+        PixelOrder::try_from(message.response().order())
+    }
+
+    /// Setup a framebuffer with 32 bits per pixel in RGBA order. The alpha channel is enabled where a value of
+    /// 0xFF means fully opaque and 0x0 fully transparent. The physical and virtual size are the same. This is
+    /// a shortcut for the [FramebufferBuilder] configured with [PixelOrder::Rgb] and [AlphaMode::Reversed] and
+    /// fails with [MailboxError::Unsupported] if the firmware does not accept the depth, the pixel order or the
+    /// alpha mode.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let framebuffer = mb.setup_rgba_framebuffer(1024, 768).unwrap();
+    /// # }
+    /// ```
+    pub fn setup_rgba_framebuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> MailboxResult<Framebuffer> {
+        self.framebuffer(width, height)
+            .depth(Depth::Bpp32)
+            .pixel_order(PixelOrder::Rgb)
+            .alpha_mode(AlphaMode::Reversed)
+            .allocate()
+    }
+
+    /// Setup a framebuffer with 8 bits per pixel where each pixel value is an index into the palette.
+    /// The framebuffer is allocated with the given physical and virtual size and the palette is set
+    /// afterwards. This fails if the firmware does not support the 8 bits per pixel mode.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let mut palette = [0u32; 256];
+    /// for (idx, color) in palette.iter_mut().enumerate() {
+    ///     // some grey scale palette
+    ///     let grey = idx as u32;
+    ///     *color = grey << 16 | grey << 8 | grey;
+    /// }
+    /// let framebuffer = mb.setup_palettized_framebuffer(640, 480, &palette).unwrap();
+    /// # }
+    /// ```
+    pub fn setup_palettized_framebuffer(
+        &mut self,
+        width: u32,
+        height: u32,
+        palette: &[u32; 256],
+    ) -> MailboxResult<Framebuffer> {
+        let framebuffer = self
+            .framebuffer(width, height)
+            .depth(Depth::Bpp8)
+            .allocate()?;
+
+        let status = self
+            .send_message(PaletteSet::new(0, 256, *palette).into())?
+            .response()
+            .status();
+        if status != 0 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Query the framebuffer that is currently active, e.g. the console framebuffer setup by the firmware,
+    /// without allocating a new one. Returns ``None`` if there is no active framebuffer, which is reported
+    /// by the VideoCore with a size, depth or pitch of 0.
+    ///
+    /// The property tag interface does not provide a way to query the base address of an active framebuffer
+    /// without requesting an allocation. Therefore the ``base_address`` of the returned [Framebuffer] is 0
+    /// and the ``size`` is derived from the pitch and the height. To access the framebuffer memory allocate
+    /// it with the same settings using [Mailbox::framebuffer] which will provide the existing buffer.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// if let Some(framebuffer) = mb.get_existing_framebuffer().unwrap() {
+    ///     println!("{}x{}", framebuffer.width, framebuffer.height);
+    /// }
+    /// # }
+    /// ```
+    pub fn get_existing_framebuffer(&mut self) -> MailboxResult<Option<Framebuffer>> {
+        let batch = MailboxBatch::empty()
+            .with_tag(PhysicalSizeGet::new())
+            .with_tag(DepthGet::new())
+            .with_tag(PitchGet::new());
+        let batch = self.send_batch(batch)?;
+        let physical_size = batch.get_tag::<PhysicalSizeGet, _>().response();
+        let depth = batch.get_tag::<DepthGet, _>().response().depth();
+        let pitch = batch.get_tag::<PitchGet, _>().response().pitch();
+        let (width, height) = (physical_size.width(), physical_size.height());
+        if width == 0 || height == 0 || depth == 0 || pitch == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Framebuffer {
+            base_address: 0,
+            size: pitch * height,
+            width,
+            height,
+            depth,
+            pitch,
+            row_bytes: width * framebuffer::bytes_per_pixel(depth),
+        }))
+    }
+
+    /// Copy the entries of the current palette into the given buffer, starting with the first entry. Returns the
+    /// number of entries copied, this is the length of the buffer but at most 256. The VideoCore always responds
+    /// with the whole palette, but the entries are copied straight from the message into the buffer, so only the
+    /// entries of interest are copied.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let mut colors = [0; 16];
+    /// let count = mb.get_palette_into(&mut colors).unwrap();
+    /// # }
+    /// ```
+    pub fn get_palette_into(&mut self, buffer: &mut [u32]) -> MailboxResult<usize> {
+        let tag = self.send_tag(PaletteGet::new())?;
+        let count = buffer.len().min(256);
+        buffer[..count].copy_from_slice(&tag.response().palette()[..count]);
+        Ok(count)
+    }
+
+    /// Read the current palette, let the given function modify it in place and write it back. Both
+    /// messages are build in the same aligned buffer, only the palette entries are moved by two words
+    /// within this buffer to make room for the offset and length of the [PaletteSet] request. Compared
+    /// to separate [PaletteGet] and [PaletteSet] messages this saves the copies of the 1KB palette from
+    /// and into the messages. Each call still requires two mailbox round trips to the VideoCore, so this
+    /// is the per frame cost when animating the palette.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// // rotate the palette colors by one entry
+    /// mb.modify_palette(|palette| palette.rotate_left(1)).unwrap();
+    /// # }
+    /// ```
+    pub fn modify_palette(&mut self, f: impl FnOnce(&mut [u32; 256])) -> MailboxResult<()> {
+        // the PaletteGet message consists of the message header, the tag header, 256 palette entries
+        // and the end tag. The PaletteSet message requires 2 additional words for offset and length
+        const GET_WORDS: usize = 2 + 3 + 256 + 1;
+        const SET_WORDS: usize = GET_WORDS + 2;
+        let mut message = MailboxScratch::<SET_WORDS>::new();
+        let buffer = message.as_mut_slice();
+
+        buffer[..5].copy_from_slice(&[
+            (GET_WORDS << 2) as u32,
+            MessageState::Request as u32,
+            PropertyTagId::PaletteGet as u32,
+            256 << 2,
+            0x0,
+        ]);
+        self.send_raw(MailboxChannel::PropertyTagsVc, &mut buffer[..GET_WORDS])?;
+
+        // turn the response into the PaletteSet request
+        buffer.copy_within(5..5 + 256, 7);
+        buffer[..7].copy_from_slice(&[
+            (SET_WORDS << 2) as u32,
+            MessageState::Request as u32,
+            PropertyTagId::PaletteSet as u32,
+            (2 + 256) << 2,
+            0x0,
+            0,
+            256,
+        ]);
+        buffer[SET_WORDS - 1] = 0x0;
+        let palette = <&mut [u32; 256]>::try_from(&mut buffer[7..7 + 256]).unwrap();
+        f(palette);
+        self.send_raw(MailboxChannel::PropertyTagsVc, buffer)?;
+
+        // the first response word contains the status of the palette update
+        if buffer[5] != 0 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(())
+    }
+
+    /// Set the palette entries starting with the entry ``offset`` to the given colors. Only the given entries
+    /// are passed to the VideoCore, so changing a single color is much cheaper than setting the whole palette.
+    /// Entries beyond the 256 entries of the palette are rejected with [MailboxError::OffsetOutOfBounds].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// // change the color of the palette entries 16 and 17 to red and green
+    /// mb.set_palette_range(16, &[0x0000_00FF, 0x0000_FF00]).unwrap();
+    /// # }
+    /// ```
+    pub fn set_palette_range(&mut self, offset: u32, entries: &[u32]) -> MailboxResult<()> {
+        if offset as usize + entries.len() > 256 {
+            return Err(MailboxError::OffsetOutOfBounds);
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut palette = [0; 256];
+        palette[..entries.len()].copy_from_slice(entries);
+        // only the offset, the length and the given entries of the palette are passed to the VideoCore
+        let message = MailboxTagArray::from_truncated_tag(
+            PaletteSet::new(offset, entries.len() as u32, palette),
+            (2 + entries.len()) << 2,
+        );
+        let message = self.send_tag_array(message)?;
+        if message.tags().any(|tag| tag.response().status() != 0) {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(())
+    }
+
+    /// Allocate ``size`` bytes of contiguous memory with the given byte ``alignment`` to be used with DMA.
+    /// The memory is locked in place, so the returned [DmaBuffer] provides it's bus address to be passed to
+    /// the DMA controller and the ARM address to access it from the ARM side. The memory is neither cached by
+    /// the ARM nor the VideoCore. It need to be given back with [DmaBuffer::free] once it is no longer used.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let buffer = mb.allocate_dma_buffer(4096, 4096).unwrap();
+    /// // pass buffer.bus_address to the DMA controller
+    /// buffer.free(&mut mb).map_err(|(_, error)| error).unwrap();
+    /// # }
+    /// ```
+    pub fn allocate_dma_buffer(&mut self, size: u32, alignment: u32) -> MailboxResult<DmaBuffer> {
+        let handle = self
+            .send_tag(MemoryAllocate::new(size, alignment, DMA_MEMORY_FLAGS))?
+            .response()
+            .handle();
+        if handle == 0 {
+            return Err(MailboxError::ResponseError);
+        }
+        let bus_address = match self.send_tag(MemoryLock::new(handle)) {
+            Ok(tag) if tag.response().bus_address() != 0 => tag.response().bus_address(),
+            result => {
+                // do not leak the memory that could not be locked
+                let _ = self.send_tag(MemoryRelease::new(handle));
+                return Err(result.err().unwrap_or(MailboxError::ResponseError));
+            }
+        };
+
+        Ok(DmaBuffer {
+            handle,
+            bus_address,
+            // the bus address refers to the uncached alias of the memory
+            arm_address: bus_address & 0x3FFF_FFFF,
+            size,
+            locked: true,
+        })
+    }
+
+    /// Get the memory handle of the given dispmanx resource. This allows to access the memory of a
+    /// resource created with the dispmanx API. An invalid resource handle is reported as an error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc(resource_handle: u32) {
+    /// let mut mb = Mailbox::new();
+    /// let mem_handle = mb.get_dispmanx_mem_handle(resource_handle).unwrap();
+    /// # }
+    /// ```
+    pub fn get_dispmanx_mem_handle(&mut self, resource_handle: u32) -> MailboxResult<u32> {
+        let message =
+            self.send_message(DispmanxResourceMemHandleGet::new(resource_handle).into())?;
+        let response = message.response();
+        if response.status() != 0 {
+            return Err(MailboxError::ResponseError);
+        }
+
+        Ok(response.mem_handle())
+    }
+
+    /// Send the VCHIQ slots base address to the VideoCore. Returns the raw status, use [VchiqStatus::from] or
+    /// [Mailbox::init_vchiq] to decode it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let _ = mb.set_vchiq_slot_base(0xBEEF_0000).unwrap();
+    /// # }
+    /// ```
+    pub fn set_vchiq_slot_base(&mut self, slot_base: u32) -> MailboxResult<u32> {
+        self.send_message(VchiqInit::new(slot_base).into())
+            .map(|message| message.response().status())
+    }
+
+    /// Send the VCHIQ slots base address to the VideoCore and decode the status of the initialization. The
+    /// firmware does not provide a property tag to query the VCHIQ state later on. The status returned here is
+    /// the only signal whether VCHIQ is ready to be used. Once [VchiqStatus::Initialized] is returned the
+    /// communication continues through the slots and the doorbell of the VCHIQ channel.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// if mb.init_vchiq(0xBEEF_0000).unwrap() == VchiqStatus::Initialized {
+    ///     // VCHIQ is ready
+    /// }
+    /// # }
+    /// ```
+    pub fn init_vchiq(&mut self, slot_base: u32) -> MailboxResult<VchiqStatus> {
+        self.set_vchiq_slot_base(slot_base).map(VchiqStatus::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_names() {
+        assert_eq!(ClockId::Core.name(), "Core clock");
+        assert_eq!(ClockId::Emmc2.name(), "EMMC2 clock");
+        assert_eq!(DeviceId::Uart0.name(), "UART0");
+        assert_eq!(DeviceId::UsbHcd.name(), "USB HCD");
+        assert_eq!(VoltageId::SdRamC.name(), "SDRAM Controller");
+        assert_eq!(VoltageId::SdRamI.name(), "SDRAM I/O");
+    }
+
+    #[test]
+    fn device_id_conversion() {
+        for &device_id in [DeviceId::Spi, DeviceId::Ccp2Tx, DeviceId::V3d].iter() {
+            assert_eq!(
+                DeviceId::try_from(device_id as u32).map(|id| id as u32),
+                Ok(device_id as u32)
+            );
+        }
+        assert_eq!(DeviceId::V3d as u32, 0xA);
+        assert_eq!(DeviceId::V3d.name(), "V3D");
+        // the id 0x9 is not assigned to any device
+        assert_eq!(DeviceId::try_from(0x9).err(), Some(0x9));
+        assert_eq!(DeviceId::try_from(0xB).err(), Some(0xB));
+    }
+
+    #[test]
+    fn set_several_clockrates() {
+        let exchanges = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = exchanges.clone();
+        let mut firmware = mock::Firmware::new().on(PropertyTagId::ClockrateSet, |values| {
+            // grant the next supported rate
+            values[1] = values[1] / 100_000_000 * 100_000_000;
+            Some(8)
+        });
+        mock::install_raw(move |_, buffer| {
+            counter.set(counter.get() + 1);
+            mock::process(buffer, &mut |tag, values| firmware.handle(tag, values));
+            Ok(())
+        });
+        let mut mb = Mailbox::new();
+        let granted = mb
+            .set_clockrates(&[
+                (ClockId::Arm, 1_234_000_000),
+                (ClockId::Core, 450_000_000),
+                (ClockId::V3D, 500_000_000),
+            ])
+            .unwrap();
+        assert_eq!(
+            granted
+                .iter()
+                .map(|&(clock_id, rate)| (clock_id as u32, rate))
+                .collect::<Vec<_>>(),
+            [
+                (ClockId::Arm as u32, 1_200_000_000),
+                (ClockId::Core as u32, 400_000_000),
+                (ClockId::V3D as u32, 500_000_000)
+            ]
+        );
+        // all clocks are set with a single message
+        assert_eq!(exchanges.get(), 1);
+
+        assert_eq!(
+            mb.set_clockrates(&[(ClockId::Arm, 0); 12]),
+            Err(MailboxError::BatchTooLarge)
+        );
+    }
+
+    #[test]
+    fn clockrate_requested_and_granted() {
+        mock::Firmware::new()
+            .on(PropertyTagId::ClockrateSet, |values| {
+                // grant the next supported rate
+                values[1] = values[1] / 100_000_000 * 100_000_000;
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.set_clockrate_verbose(ClockId::Arm, 1_234_000_000),
+            Ok((1_234_000_000, 1_200_000_000))
+        );
+    }
+
+    #[test]
+    fn read_otp_row() {
+        let otp = [0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xB827_EB12, 0x3456_0000];
+        mock::Firmware::new()
+            .on(PropertyTagId::CustomerOtpGet, move |values| {
+                let (start, count) = (values[0] as usize, values[1] as usize);
+                values[2..2 + count].copy_from_slice(&otp[start..start + count]);
+                Some(8 + 4 * values[1])
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.read_otp_row(6), Ok(0xB827_EB12));
+        assert_eq!(mb.read_otp_row(7), Ok(0x3456_0000));
+    }
+
+    #[test]
+    fn clockrate_of_missing_clock() {
+        mock::Firmware::new()
+            .on(PropertyTagId::ClockrateGet, |values| {
+                // the EMMC2 clock does not exist on this board
+                values[1] = if values[0] == ClockId::Core as u32 {
+                    250_000_000
+                } else {
+                    0
+                };
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.get_clockrate_opt(ClockId::Core), Ok(Some(250_000_000)));
+        assert_eq!(mb.get_clockrate_opt(ClockId::Emmc2), Ok(None));
+        assert_eq!(
+            mb.get_clockrate_or(ClockId::Core, 100_000_000),
+            Ok(250_000_000)
+        );
+        assert_eq!(
+            mb.get_clockrate_or(ClockId::Emmc2, 100_000_000),
+            Ok(100_000_000)
+        );
+    }
+
+    #[test]
+    fn heap_region_excludes_reservations() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::ArmMemoryGet, &[0x1000, 948 << 20])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.suggest_heap_region(0x10_0000, 0x8_0000),
+            Ok((0x10_1000, (948 << 20) - 0x18_0000))
+        );
+        assert_eq!(mb.suggest_heap_region(0, 0), Ok((0x1000, 948 << 20)));
+        assert_eq!(
+            mb.suggest_heap_region(948 << 20, 0),
+            Err(MailboxError::ReservationTooLarge)
+        );
+        assert_eq!(
+            mb.suggest_heap_region(1 << 20, u32::MAX),
+            Err(MailboxError::ReservationTooLarge)
+        );
+    }
+
+    #[test]
+    fn edid_into_buffer() {
+        mock::Firmware::new()
+            .on(PropertyTagId::EdidBlockGet, |values| {
+                let block = values[0] as u8;
+                values[1] = if block < 2 { 0 } else { 1 };
+                let edid = unsafe {
+                    core::slice::from_raw_parts_mut(values[2..].as_mut_ptr() as *mut u8, 128)
+                };
+                edid.iter_mut().for_each(|byte| *byte = block);
+                // the base block announces one extension block
+                edid[126] = 1;
+                Some(136)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        let mut buffer = [0xFF; 256];
+        assert_eq!(mb.read_edid_into(&mut buffer), Ok(256));
+        assert!(buffer[..126].iter().all(|&byte| byte == 0));
+        assert!(buffer[128..254].iter().all(|&byte| byte == 1));
+
+        let mut buffer = [0xFF; 255];
+        assert_eq!(
+            mb.read_edid_into(&mut buffer),
+            Err(MailboxError::BufferTooSmall { required: 256 })
+        );
+        assert!(buffer.iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn board_serial_64_combines_words() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::BoardSerialGet, &[0x2a5e_f2c8, 0x1000_0000])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.get_board_serial_64().unwrap(), 0x1000_0000_2a5e_f2c8);
+    }
+
+    /// firmware emulation of the V3D bring-up that records the order of the requests. Powering the
+    /// QPUs and starting the clock could be set to fail.
+    fn v3d_firmware(
+        qpu_ok: bool,
+        clock_ok: bool,
+        requests: std::rc::Rc<std::cell::RefCell<Vec<(u32, u32)>>>,
+    ) -> mock::Firmware {
+        let clock_requests = requests.clone();
+        mock::Firmware::new()
+            .on(PropertyTagId::QpuEnable, move |values| {
+                requests
+                    .borrow_mut()
+                    .push((PropertyTagId::QpuEnable as u32, values[0]));
+                values[0] = if qpu_ok { 0 } else { 0x8000_0000 };
+                Some(4)
+            })
+            .on(PropertyTagId::ClockStateSet, move |values| {
+                if values[0] != ClockId::V3D as u32 {
+                    return None;
+                }
+                clock_requests
+                    .borrow_mut()
+                    .push((PropertyTagId::ClockStateSet as u32, values[1]));
+                if !clock_ok {
+                    values[1] = 0;
+                }
+                Some(8)
+            })
+    }
+
+    #[test]
+    fn enable_v3d_powers_before_clock() {
+        let requests = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        v3d_firmware(true, true, requests.clone()).install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.enable_v3d(), Ok(()));
+        assert_eq!(mb.disable_v3d(), Ok(()));
+        assert_eq!(
+            requests.borrow().as_slice(),
+            &[
+                (PropertyTagId::QpuEnable as u32, 1),
+                (PropertyTagId::ClockStateSet as u32, 1),
+                (PropertyTagId::ClockStateSet as u32, 0),
+                (PropertyTagId::QpuEnable as u32, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn enable_v3d_fails() {
+        let requests = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        v3d_firmware(false, true, requests.clone()).install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.enable_v3d(), Err(MailboxError::ResponseError));
+        // the clock is not enabled if the power up fails
+        assert_eq!(requests.borrow().len(), 1);
+
+        let requests = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        v3d_firmware(true, false, requests.clone()).install();
+        assert_eq!(mb.enable_v3d(), Err(MailboxError::ResponseError));
+        assert_eq!(requests.borrow().len(), 2);
+    }
+
+    #[test]
+    fn repeated_queries_return_fresh_responses() {
+        let mut temperature = 40_000;
+        mock::Firmware::new()
+            .on(PropertyTagId::TemperatureGet, move |values| {
+                temperature += 500;
+                values[1] = temperature;
+                Some(8)
+            })
+            .on(PropertyTagId::ClockrateGet, |values| {
+                values[1] = values[0] * 1_000_000;
+                Some(8)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        for expected in (1..=3).map(|step| 40_000 + step * 500) {
+            assert_eq!(mb.get_temperature(), Ok(expected));
+            // a different tag in between does not leave stale data in the buffer
+            assert_eq!(
+                mb.get_clockrate(ClockId::Arm),
+                Ok(ClockId::Arm as u32 * 1_000_000)
+            );
+        }
+    }
+
+    #[test]
+    fn clear_throttled_flags() {
+        // under-voltage has occurred and the ARM frequency is currently capped
+        let mut state = 0x1_0002;
+        mock::Firmware::new()
+            .on(PropertyTagId::ThrottledGet, move |values| {
+                // the lower half word selects the sticky flags to clear
+                let clear_mask = values[0] << 16;
+                values[0] = state;
+                state &= !clear_mask;
+                Some(4)
+            })
+            .install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.get_throttled(), Ok(0x1_0002));
+        assert_eq!(mb.clear_throttled_flags(), Ok(0x1_0002));
+        // only the sticky flags are cleared
+        assert_eq!(mb.get_throttled(), Ok(0x0_0002));
+    }
+
+    /// firmware emulation of the throttling state. Each request reports the next state of the given sequence
+    /// with the conditions of the state setting the sticky flags. The sticky flags are cleared if requested and
+    /// ``clearing`` is supported.
+    fn throttle_firmware(sequence: &'static [u32], clearing: bool) -> mock::Firmware {
+        let mut sequence = sequence.iter();
+        let mut sticky = 0;
+        mock::Firmware::new().on(PropertyTagId::ThrottledGet, move |values| {
+            let current = *sequence.next().unwrap();
+            sticky |= current << 16;
+            // the lower half word selects the sticky flags to clear
+            let clear_mask = values[0] << 16;
+            values[0] = sticky | current;
+            if clearing {
+                sticky &= !clear_mask;
+            }
+            Some(4)
+        })
+    }
+
+    #[test]
+    fn poll_throttle_events() {
+        // under-voltage, then ARM capped, then both
+        const SEQUENCE: &[u32] = &[0b01, 0b00, 0b10, 0b00, 0b01, 0b11];
+        throttle_firmware(SEQUENCE, true).install();
+        let mut mb = Mailbox::new();
+        let events: Vec<_> = (0..SEQUENCE.len())
+            .map(|_| mb.poll_throttle_events().unwrap())
+            .map(|status| (status.current, status.events))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (0b01, 0b01),
+                (0b00, 0b00),
+                (0b10, 0b10),
+                (0b00, 0b00),
+                (0b01, 0b01),
+                (0b11, 0b10),
+            ]
+        );
+
+        // without support to clear the flags each condition is reported once
+        throttle_firmware(SEQUENCE, false).install();
+        let mut mb = Mailbox::new();
+        let events: Vec<_> = (0..SEQUENCE.len())
+            .map(|_| mb.poll_throttle_events().unwrap().events)
+            .collect();
+        assert_eq!(events, [0b01, 0b00, 0b10, 0b00, 0b00, 0b00]);
+    }
+
+    #[test]
+    fn probe_supported_tags() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::TemperatureGet, &[0x0, 45_000])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.tag_supported(PropertyTagId::TemperatureGet), Ok(true));
+        assert_eq!(mb.tag_supported(PropertyTagId::ThrottledGet), Ok(false));
+
+        // firmware that responds successfully but leaves unknown tags unprocessed
+        mock::install_raw(|_, buffer| {
+            buffer[1] = MessageState::ResponseOk as u32;
+            Ok(())
+        });
+        assert_eq!(mb.tag_supported(PropertyTagId::ThrottledGet), Ok(false));
+    }
+
+    /// firmware emulation of a device that reports the power state change after the given number of
+    /// power state requests
+    fn power_firmware(switch_after: u32) -> mock::Firmware {
+        let requests = std::rc::Rc::new(std::cell::Cell::new(0));
+        let state = std::rc::Rc::new(std::cell::Cell::new(0));
+        let reported = state.clone();
+        let report = move |values: &mut [u32]| {
+            requests.set(requests.get() + 1);
+            values[1] = if requests.get() > switch_after {
+                reported.get()
+            } else {
+                0
+            };
+            Some(8)
+        };
+        let report_set = report.clone();
+        mock::Firmware::new()
+            .on(PropertyTagId::PowerStateSet, move |values| {
+                state.set(values[1] & 0b1);
+                report_set(values)
+            })
+            .on(PropertyTagId::PowerStateGet, report)
+    }
+
+    #[test]
+    fn set_power_state_wait_polls_until_on() {
+        power_firmware(3).install();
+        let mut delays = 0;
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.set_power_state_wait(DeviceId::Uart0, true, 10, || delays += 1),
+            Ok(())
+        );
+        assert_eq!(delays, 3);
+    }
+
+    #[test]
+    fn set_power_state_wait_times_out() {
+        power_firmware(u32::MAX).install();
+        let mut delays = 0;
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.set_power_state_wait(DeviceId::Uart0, true, 5, || delays += 1),
+            Err(MailboxError::Timeout)
+        );
+        assert_eq!(delays, 5);
+    }
+
+    /// firmware emulation reporting the ARM clock running at the given rate and the corresponding
+    /// throttling state
+    fn arm_clock_firmware(measured: u32, throttled: u32) -> mock::Firmware {
+        mock::Firmware::new()
+            .respond(PropertyTagId::ThrottledGet, &[throttled])
+            .respond(
+                PropertyTagId::MeasuredClockrateGet,
+                &[ClockId::Arm as u32, measured],
+            )
+            .respond(
+                PropertyTagId::MaxClockrateGet,
+                &[ClockId::Arm as u32, 1_500_000_000],
+            )
+    }
+
+    #[test]
+    fn arm_clock_status() {
+        arm_clock_firmware(600_000_000, 0x5_0005).install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.arm_clock_status(),
+            Ok(ArmClockStatus {
+                capped: false,
+                measured_hz: Hertz::from_mhz(600),
+                max_hz: Hertz::from_mhz(1_500),
+            })
+        );
+
+        arm_clock_firmware(1_000_000_000, 0x2_0002).install();
+        let status = mb.arm_clock_status().unwrap();
+        assert!(status.capped);
+        assert_eq!(status.measured_hz, Hertz::from_mhz(1_000));
+    }
+
+    #[test]
+    fn clock_running_below_set_rate() {
+        arm_clock_firmware(600_000_000, 0x5_0005)
+            .respond(
+                PropertyTagId::ClockrateGet,
+                &[ClockId::Arm as u32, 1_500_000_000],
+            )
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.clock_discrepancy(ClockId::Arm),
+            Ok((1_500_000_000, 600_000_000, -900_000_000))
+        );
+    }
+
+    /// firmware emulation reporting the given firmware revision and board serial number
+    fn identity_firmware(revision: u32, serial: u32) -> mock::Firmware {
+        mock::Firmware::new()
+            .respond(PropertyTagId::FirmwareRevisionGet, &[revision])
+            .respond(PropertyTagId::BoardSerialGet, &[serial, 0])
+    }
+
+    #[test]
+    fn detect_emulated_mailbox() {
+        let mut mb = Mailbox::new();
+        // QEMU reports a fixed firmware revision and no serial number
+        identity_firmware(346_337, 0).install();
+        assert_eq!(mb.is_emulated(), Ok(true));
+        identity_firmware(0, 0x2a5e_f2c8).install();
+        assert_eq!(mb.is_emulated(), Ok(true));
+
+        identity_firmware(0x5F3A_1B2C, 0x2a5e_f2c8).install();
+        assert_eq!(mb.is_emulated(), Ok(false));
+    }
+
+    #[test]
+    fn decode_vchiq_status() {
+        mock::Firmware::new()
+            .on(PropertyTagId::VchiqInit, |values| {
+                // only slots within the first 1GB are accepted
+                values[0] = if values[0] < 0x4000_0000 { 0 } else { 1 };
+                Some(4)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.init_vchiq(0x0800_0000), Ok(VchiqStatus::Initialized));
+        assert_eq!(mb.init_vchiq(0xBEEF_0000), Ok(VchiqStatus::Failed(1)));
+    }
+
+    #[test]
+    fn ping_responsive_mailbox() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::FirmwareRevisionGet, &[0x5F3A_1B2C])
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.ping(), Ok(()));
+    }
+
+    #[test]
+    fn ping_without_response() {
+        // there is no firmware emulation installed, so the mailbox does not accept the message
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.ping(), Err(MailboxError::Timeout));
+    }
+
+    #[test]
+    fn clock_tree() {
+        // core and ARM without parent, UART derived from core, EMMC from an unknown clock and a clock
+        // unknown to the crate
+        mock::Firmware::new()
+            .respond(
+                PropertyTagId::ClocksGet,
+                &[0x0, 0x4, 0x0, 0x3, 0x4, 0x2, 0x42, 0x1, 0x4, 0x43],
+            )
+            .install();
+
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.get_clock_parents().unwrap(),
+            vec![
+                (ClockId::Core, CLOCK_PARENT_NONE),
+                (ClockId::Arm, CLOCK_PARENT_NONE),
+                (ClockId::Uart, ClockId::Core as u32),
+                (ClockId::Emmc, CLOCK_PARENT_UNKNOWN),
+            ]
+        );
+    }
+
+    #[test]
+    fn receive_vc_initiated_message() {
+        #[repr(C, align(16))]
+        struct Message([u32; 8]);
+
+        let mut message = Message([
+            12 + core::mem::size_of::<BlankScreen>() as u32,
+            0x0,
+            PropertyTagId::BlankScreen as u32,
+            4,
+            0x8000_0004,
+            0x1,
+            0x0,
+            0x0,
+        ]);
+        mock::push_incoming(MailboxChannel::PropertyTagsArm, &mut message.0);
+        let mut mb = Mailbox::new();
+        let tag = mb.receive_from_vc::<BlankScreen>().unwrap();
+        assert_eq!(tag.response().state(), 0x1);
+        assert_eq!(mock::acknowledged().len(), 1);
+        assert_eq!(mock::acknowledged()[0].1, message.0.as_mut_ptr());
+    }
+
+    #[test]
+    fn receive_vc_initiated_message_of_unexpected_type() {
+        #[repr(C, align(16))]
+        struct Message([u32; 8]);
+
+        let mut message = Message([
+            12 + core::mem::size_of::<DepthGet>() as u32,
+            0x0,
+            PropertyTagId::DepthGet as u32,
+            4,
+            0x8000_0004,
+            32,
+            0x0,
+            0x0,
+        ]);
+        mock::push_incoming(MailboxChannel::PropertyTagsArm, &mut message.0);
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.receive_from_vc::<BlankScreen>().err(),
+            Some(MailboxError::UnexpectedMessage)
+        );
+        // the message is acknowledged anyway
+        assert_eq!(mock::acknowledged().len(), 1);
+    }
+
+    #[test]
+    fn all_voltages_in_one_batch() {
+        mock::Firmware::new()
+            .on(PropertyTagId::VoltageGet, |values| {
+                // distinct offset per voltage id
+                values[1] = values[0] * 10;
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        let voltages = mb.get_all_voltages().unwrap();
+        for (idx, (voltage_id, value)) in voltages.iter().enumerate() {
+            assert_eq!(*voltage_id as u32, VoltageId::all()[idx] as u32);
+            assert_eq!(*value, *voltage_id as u32 * 10);
+        }
+    }
+
+    #[test]
+    fn all_domain_states() {
+        mock::Firmware::new()
+            .on(PropertyTagId::DomainStateGet, |values| {
+                // the firmware numbers the power domains starting with 1
+                assert!((1..=23).contains(&values[0]));
+                // every other power domain is powered on
+                values[1] = (values[0] + 1) & 0x1;
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        let states = mb.get_all_domain_states().unwrap();
+        assert_eq!(states.len(), DomainId::all().len());
+        assert_eq!(states[0], (DomainId::I2C0, 0));
+        assert_eq!(states[1], (DomainId::I2C1, 1));
+        assert_eq!(states[5], (DomainId::Hdmi, 1));
+        assert_eq!(states[22], (DomainId::Arm, 0));
+    }
+
+    #[test]
+    fn property_tag_channels() {
+        assert!(MailboxChannel::PropertyTagsVc.is_valid_for_property_tags());
+        assert!(MailboxChannel::PropertyTagsArm.is_valid_for_property_tags());
+        assert!(!MailboxChannel::PowerMgmt.is_valid_for_property_tags());
+        assert!(!MailboxChannel::FrameBuffer.is_valid_for_property_tags());
+        assert!(!MailboxChannel::VirtualUart.is_valid_for_property_tags());
+        assert!(MailboxChannel::PropertyTagsVc.is_valid_for_requests());
+        assert!(!MailboxChannel::PropertyTagsArm.is_valid_for_requests());
     }
 }
@@ -0,0 +1,153 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Mailbox Builder
+//!
+//! The [Mailbox] created with [Mailbox::new] uses the peripheral base address of the platform the crate is built
+//! for and waits the default number of iterations for the mailbox to respond. A [MailboxBuilder] allows to
+//! configure these settings. The property tag messages are always passed on the
+//! [MailboxChannel::PropertyTagsVc](crate::MailboxChannel::PropertyTagsVc) channel, as this is the only channel
+//! accepting requests.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::builder()
+//!         .peripheral_base(0xFE00_0000)
+//!         .timeout_iterations(0x1000)
+//!         .build();
+//!     let temperature = mb.get_temperature().unwrap();
+//! }
+//! ```
+//!
+
+use crate::Mailbox;
+
+/// The points of a message exchange a timing hook is notified about
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TimingEvent {
+    /// The message is about to be written to the mailbox
+    BeforeWrite,
+    /// The response of the VideoCore has been read from the mailbox
+    AfterRead,
+}
+
+/// The configuration of a [Mailbox] that is used for each exchange with the mailbox peripheral
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct MailboxConfig {
+    /// The MMIO base address of the peripherals the mailbox is part of
+    pub peripheral_base: usize,
+    /// The number of times the mailbox status is polled before giving up with a timeout
+    pub timeout_iterations: u32,
+    /// Notified before the message is written to and after the response is read from the mailbox
+    pub timing_hook: Option<fn(TimingEvent)>,
+    /// Called with the channel and the data read from the mailbox while waiting for a different channel
+    pub foreign_channel_hook: Option<fn(u32, u32)>,
+}
+
+impl MailboxConfig {
+    /// The configuration used by [Mailbox::new]
+    pub const fn new() -> Self {
+        Self {
+            peripheral_base: crate::interface::PERIPHERAL_BASE,
+            timeout_iterations: crate::interface::MAILBOX_TIMEOUT_ITERATIONS,
+            timing_hook: None,
+            foreign_channel_hook: None,
+        }
+    }
+}
+
+/// Builder to create a configured [Mailbox]. The builder is created with [Mailbox::builder]. All settings that
+/// are not configured keep the values [Mailbox::new] is using.
+pub struct MailboxBuilder {
+    config: MailboxConfig,
+}
+
+impl MailboxBuilder {
+    pub(crate) const fn new() -> Self {
+        Self {
+            config: MailboxConfig::new(),
+        }
+    }
+
+    /// Set the MMIO base address of the peripherals, e.g. 0xFE00_0000 for a Raspberry Pi 4 in low peripheral
+    /// mode
+    pub fn peripheral_base(mut self, base: usize) -> Self {
+        self.config.peripheral_base = base;
+        self
+    }
+
+    /// Set the number of times the mailbox status is polled while waiting for the mailbox to accept a message
+    /// or for a message initiated by the VideoCore before giving up with
+    /// [MailboxError::Timeout](crate::MailboxError::Timeout). Once a message has been accepted the response is
+    /// awaited without a limit, as the VideoCore owns the message buffer until it has responded.
+    pub fn timeout_iterations(mut self, iterations: u32) -> Self {
+        self.config.timeout_iterations = iterations;
+        self
+    }
+
+    /// Set a hook that is called with [TimingEvent::BeforeWrite] right before a message is written to the
+    /// mailbox and with [TimingEvent::AfterRead] once the response has been read. Taking a timestamp with a
+    /// timer of choice in the hook allows to measure how long the VideoCore takes to process a message. If the
+    /// mailbox times out the [TimingEvent::AfterRead] is not notified.
+    pub fn timing_hook(mut self, hook: fn(TimingEvent)) -> Self {
+        self.config.timing_hook = Some(hook);
+        self
+    }
+
+    /// Set a hook that is called with the channel and the data of each value read from the mailbox while
+    /// waiting for the response on a different channel. Without the hook these values are discarded. The hook
+    /// allows to pass them on to the part of the system that is waiting for them, e.g. the VCHIQ driver sharing
+    /// the mailbox with the property tag interface. While waiting for a message initiated by the VideoCore each
+    /// value read counts against the [timeout iterations](MailboxBuilder::timeout_iterations).
+    pub fn foreign_channel_hook(mut self, hook: fn(u32, u32)) -> Self {
+        self.config.foreign_channel_hook = Some(hook);
+        self
+    }
+
+    /// Create the [Mailbox] with the configured settings
+    pub fn build(self) -> Mailbox {
+        Mailbox::with_config(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings of the configuration that could be compared
+    fn settings(config: MailboxConfig) -> (usize, u32, bool, bool) {
+        (
+            config.peripheral_base,
+            config.timeout_iterations,
+            config.timing_hook.is_some(),
+            config.foreign_channel_hook.is_some(),
+        )
+    }
+
+    #[test]
+    fn defaults_match_new() {
+        assert_eq!(
+            settings(Mailbox::builder().build().config),
+            settings(Mailbox::new().config)
+        );
+    }
+
+    #[test]
+    fn build_configured_mailbox() {
+        let mailbox = Mailbox::builder()
+            .peripheral_base(0xFE00_0000)
+            .timeout_iterations(10)
+            .timing_hook(|_| ())
+            .foreign_channel_hook(|_, _| ())
+            .build();
+        assert_eq!(settings(mailbox.config), (0xFE00_0000, 10, true, true));
+    }
+}
@@ -38,8 +38,14 @@
 //! }
 //! ```
 
+use super::MESSAGE_FRAME_SIZE;
 use crate::propertytags::*;
-use crate::MessageState;
+use crate::{MailboxError, MailboxResult, MessageState};
+use core::convert::TryFrom;
+
+/// The message size marking a batch that has grown beyond the range of an u32. Such a batch will never be passed
+/// to the mailbox.
+const MSG_SIZE_OVERFLOW: u32 = u32::MAX;
 
 /// The wrapper storing all property tags that comprises a batch message
 #[derive(Debug)]
@@ -53,7 +59,10 @@ pub struct MailboxBatch<Tags> {
 
 /// Define a trait that allows to constrain the ``Tags`` generics used with the [MailboxBatch]
 #[doc(hidden)]
-pub trait PropertyTagList {}
+pub trait PropertyTagList {
+    /// Check whether the VideoCore has processed all tags of the list
+    fn all_processed(&self) -> bool;
+}
 
 /// Define linked list of tags contained in the [MailboxBatch]. This is a compiletime only list. On
 /// memory the different concecutive tags exists as packed array (hopefully)
@@ -70,16 +79,25 @@ pub struct Cons<Prev, Tag> {
 pub struct Empty;
 
 /// Implement the [PropertyTagList] trait for the Cons structure.
-impl<Prev: PropertyTagList, Tag: PropertyTag> PropertyTagList for Cons<Prev, Tag> {}
+impl<Prev: PropertyTagList, Tag: PropertyTag> PropertyTagList for Cons<Prev, Tag> {
+    fn all_processed(&self) -> bool {
+        // bit 31 of the tag state is set once the VideoCore has processed the tag
+        self.previous.all_processed() && self.tag.state() & 0x8000_0000 != 0
+    }
+}
 
 /// Implement the [PropertyTagList] trait for the Empty batch variant
-impl PropertyTagList for Empty {}
+impl PropertyTagList for Empty {
+    fn all_processed(&self) -> bool {
+        true
+    }
+}
 
 /// provide the function to create an empty [MailboxBatch] for the [Empty] type only
 impl MailboxBatch<Empty> {
     pub fn empty() -> Self {
         Self {
-            msg_size: 12,
+            msg_size: MESSAGE_FRAME_SIZE,
             msg_type: MessageState::Request,
             msg_tags: Empty,
             msg_end: 0,
@@ -96,9 +114,17 @@ impl<Tags: PropertyTagList> MailboxBatch<Tags> {
     /// a builder pattern ?
     /// As the tags are concecutive in the linked list they are also layed out concecutive in the memory
     /// as we require it to happen, the batch header part and the final u32 are kept in place...
+    /// The tags are layed out in the order they have been added and the VideoCore processes them in
+    /// this order. So setting a value and reading it back within the same batch is possible.
+    /// If the accumulated size of the batch would exceed the range of an u32 the batch is marked as
+    /// oversized and sending it will fail.
     pub fn with_tag<Tag>(self, tag: Tag) -> MailboxBatch<Cons<Tags, Tag>> {
+        let msg_size = u32::try_from(core::mem::size_of::<Tag>())
+            .ok()
+            .and_then(|tag_size| self.msg_size.checked_add(tag_size))
+            .unwrap_or(MSG_SIZE_OVERFLOW);
         MailboxBatch {
-            msg_size: self.msg_size + core::mem::size_of::<Tag>() as u32,
+            msg_size,
             msg_type: self.msg_type,
             msg_tags: Cons {
                 previous: self.msg_tags,
@@ -108,6 +134,72 @@ impl<Tags: PropertyTagList> MailboxBatch<Tags> {
         }
     }
 
+    /// Add several tags given as a tuple to the batch. The tags are added in the order of the tuple
+    /// as if they were added with [with_tag](MailboxBatch::with_tag) one after another. Tuples with up
+    /// to 8 tags are supported.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let batch = MailboxBatch::empty().with_tags((
+    ///     PhysicalSizeSet::new(1024, 768),
+    ///     VirtualSizeSet::new(1024, 768),
+    ///     DepthSet::new(32),
+    ///     FramebufferAllocate::new(16),
+    /// ));
+    /// # }
+    /// ```
+    pub fn with_tags<T>(self, tags: T) -> MailboxBatch<T::Output>
+    where
+        T: PropertyTagTuple<Tags>,
+    {
+        tags.append_to(self)
+    }
+
+    /// Append all tags of the other batch to this batch. The tags of the other batch are placed behind the
+    /// tags of this batch in the order they have been added to the other batch. The tags of both batches could
+    /// be retrieved from the combined batch with [get_tag](MailboxBatch::get_tag). If both batches contain
+    /// the same tag type the position of the tag need to be given explicitly, like for any batch containing a
+    /// tag type more than once.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let display = MailboxBatch::empty()
+    ///     .with_tag(PhysicalSizeGet::new())
+    ///     .with_tag(DepthGet::new());
+    /// let clocks = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
+    /// let mut mb = Mailbox::new();
+    /// let batch = mb.send_batch(display.concat(clocks)).unwrap();
+    /// let depth = batch.get_tag::<DepthGet, _>().response().depth();
+    /// let core_rate = batch.get_tag::<ClockrateGet, _>().response().clock_rate();
+    /// # }
+    /// ```
+    pub fn concat<Other>(self, other: MailboxBatch<Other>) -> MailboxBatch<Tags::Output>
+    where
+        Other: PropertyTagList,
+        Tags: AppendTags<Other>,
+    {
+        // both batches contain the message header and the end tag, the combined batch only once
+        let msg_size = if self.is_oversized() || other.is_oversized() {
+            MSG_SIZE_OVERFLOW
+        } else {
+            self.msg_size
+                .checked_add(other.msg_size - MESSAGE_FRAME_SIZE)
+                .unwrap_or(MSG_SIZE_OVERFLOW)
+        };
+        MailboxBatch {
+            msg_size,
+            msg_type: self.msg_type,
+            msg_tags: self.msg_tags.append(other.msg_tags),
+            msg_end: self.msg_end,
+        }
+    }
+
     /// The tricky part to find a tag after it has been added based on it's type. So there is some
     /// recursive type inference and stuff going on that keeps on going to find the right type that
     /// implements the ``find`` method for the requested tag type and returns a reference to it.
@@ -117,6 +209,30 @@ impl<Tags: PropertyTagList> MailboxBatch<Tags> {
     {
         self.msg_tags.find()
     }
+
+    /// Check whether the VideoCore has processed each tag of this batch. The VideoCore might report the
+    /// whole batch as processed with [MessageState::ResponseOk] even if it has skipped single tags, e.g. tags
+    /// not known to the firmware. This is the quick check before inspecting the state of each tag. A batch
+    /// without any tag is treated as processed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let mut mb = Mailbox::new();
+    /// let batch = MailboxBatch::empty()
+    ///     .with_tag(ClockrateGet::new(ClockId::Core))
+    ///     .with_tag(TemperatureGet::new());
+    /// let batch = mb.send_batch(batch).unwrap();
+    /// if batch.all_succeeded() {
+    ///     let temperature = batch.get_tag::<TemperatureGet, _>().response().value();
+    /// }
+    /// # }
+    /// ```
+    pub fn all_succeeded(&self) -> bool {
+        self.msg_tags.all_processed()
+    }
 }
 
 impl<T> MailboxBatch<T> {
@@ -124,6 +240,163 @@ impl<T> MailboxBatch<T> {
     pub fn get_state(&self) -> MessageState {
         self.msg_type
     }
+
+    /// The size in bytes of this batch as stated in the message header
+    pub(crate) fn msg_size(&self) -> u32 {
+        self.msg_size
+    }
+
+    /// Check whether this batch consists of the message header only and does not contain any property tag
+    pub(crate) fn is_empty(&self) -> bool {
+        self.msg_size == MESSAGE_FRAME_SIZE
+    }
+
+    /// Check whether the accumulated size of this batch has exceeded the range of an u32
+    pub(crate) fn is_oversized(&self) -> bool {
+        self.msg_size == MSG_SIZE_OVERFLOW
+    }
+
+    /// Check the framebuffer property tags of this batch before it is send. The batch is rejected with
+    /// [MailboxError::InvalidFramebufferBatch] if
+    /// - the physical size, the virtual size or the depth is set after the framebuffer has been allocated
+    /// - the same value is set several times to different values
+    /// - the value buffer of a framebuffer property tag is too small to hold the value
+    ///
+    /// A batch without framebuffer property tags is always valid. A virtual size exceeding the physical size is
+    /// not a conflict, as this is how a framebuffer is setup for double buffering.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// # fn doc() {
+    /// let batch = MailboxBatch::empty()
+    ///     .with_tag(PhysicalSizeSet::new(640, 480))
+    ///     .with_tag(FramebufferAllocate::new(16))
+    ///     .with_tag(DepthSet::new(32));
+    /// assert!(batch.validate_framebuffer().is_err());
+    /// # }
+    /// ```
+    pub fn validate_framebuffer(&self) -> MailboxResult<()> {
+        if self.is_oversized() {
+            return Err(MailboxError::BatchTooLarge);
+        }
+        let words = unsafe {
+            core::slice::from_raw_parts(
+                self as *const Self as *const u32,
+                (self.msg_size as usize).min(core::mem::size_of::<Self>()) >> 2,
+            )
+        };
+        let mut allocated = false;
+        let mut physical_size: Option<&[u32]> = None;
+        let mut virtual_size: Option<&[u32]> = None;
+        let mut depth: Option<&[u32]> = None;
+        // walk the serialized tags the same way the VideoCore does, the end tag is not part of the tags
+        let mut idx = 2;
+        while idx + 3 < words.len() {
+            let tag_id = words[idx];
+            let value_words = (words[idx + 1] as usize + 3) >> 2;
+            let values = &words[idx + 3..(idx + 3 + value_words).min(words.len())];
+            let value = if tag_id == PropertyTagId::PhysicalSizeSet as u32 {
+                Some((&mut physical_size, values.get(..2)))
+            } else if tag_id == PropertyTagId::VirtualSizeSet as u32 {
+                Some((&mut virtual_size, values.get(..2)))
+            } else if tag_id == PropertyTagId::DepthSet as u32 {
+                Some((&mut depth, values.get(..1)))
+            } else {
+                allocated |= tag_id == PropertyTagId::FramebufferAllocate as u32;
+                None
+            };
+            if let Some((current, requested)) = value {
+                // a hand written property tag might use the id with a smaller value buffer
+                let requested = requested.ok_or(MailboxError::InvalidFramebufferBatch)?;
+                if allocated || matches!(current, Some(current) if *current != requested) {
+                    return Err(MailboxError::InvalidFramebufferBatch);
+                }
+                *current = Some(requested);
+            }
+            idx += 3 + value_words;
+        }
+
+        Ok(())
+    }
+}
+
+/// A tuple of [PropertyTag]s that could be added to a [MailboxBatch] at once
+#[doc(hidden)]
+pub trait PropertyTagTuple<Tags> {
+    /// The type of the tag list once all tags of the tuple are added
+    type Output;
+
+    fn append_to(self, batch: MailboxBatch<Tags>) -> MailboxBatch<Self::Output>;
+}
+
+/// Build the type of the tag list resulting from adding each of the given tags to the list
+macro_rules! tag_list {
+    ($list:ty;) => {
+        $list
+    };
+    ($list:ty; $tag:ident $(, $tail:ident)*) => {
+        tag_list!(Cons<$list, $tag>; $($tail),*)
+    };
+}
+
+/// Implement the [PropertyTagTuple] for a tuple of the given tag types
+macro_rules! property_tag_tuple {
+    ($($tag:ident: $value:ident),+) => {
+        impl<Tags: PropertyTagList, $($tag: PropertyTag),+> PropertyTagTuple<Tags> for ($($tag,)+) {
+            type Output = tag_list!(Tags; $($tag),+);
+
+            fn append_to(self, batch: MailboxBatch<Tags>) -> MailboxBatch<Self::Output> {
+                let ($($value,)+) = self;
+                batch$(.with_tag($value))+
+            }
+        }
+    };
+}
+
+property_tag_tuple!(A: a);
+property_tag_tuple!(A: a, B: b);
+property_tag_tuple!(A: a, B: b, C: c);
+property_tag_tuple!(A: a, B: b, C: c, D: d);
+property_tag_tuple!(A: a, B: b, C: c, D: d, E: e);
+property_tag_tuple!(A: a, B: b, C: c, D: d, E: e, F: f);
+property_tag_tuple!(A: a, B: b, C: c, D: d, E: e, F: f, G: g);
+property_tag_tuple!(A: a, B: b, C: c, D: d, E: e, F: f, G: g, H: h);
+
+/// A trait that allows to append the tags of another tag list at the end of a tag list
+#[doc(hidden)]
+pub trait AppendTags<Other> {
+    /// The type of the tag list once the other tag list is appended
+    type Output: PropertyTagList;
+
+    fn append(self, other: Other) -> Self::Output;
+}
+
+/// Appending the empty list keeps the list as is
+impl<Tags: PropertyTagList> AppendTags<Empty> for Tags {
+    type Output = Tags;
+
+    fn append(self, _: Empty) -> Self::Output {
+        self
+    }
+}
+
+/// Appending a list means to append all previous entries of the other list first and add the last entry of
+/// the other list to the result
+impl<Tags, Prev, Tag> AppendTags<Cons<Prev, Tag>> for Tags
+where
+    Tags: AppendTags<Prev>,
+    Tag: PropertyTag,
+{
+    type Output = Cons<Tags::Output, Tag>;
+
+    fn append(self, other: Cons<Prev, Tag>) -> Self::Output {
+        Cons {
+            previous: self.append(other.previous),
+            tag: other.tag,
+        }
+    }
 }
 
 /// A trait that defines that it can find a tag of a specified type in the linked list Cons
@@ -159,8 +432,22 @@ impl<Prev: FindTag<Tag, Pos>, Tag, Pos, T> FindTag<Tag, Next<Pos>> for Cons<Prev
 mod tests {
     use super::*;
     use crate::ClockId;
+    use crate::{mock, Mailbox};
     use core::mem::size_of;
 
+    #[test]
+    fn message_frame_matches_field_offsets() {
+        assert_eq!(MailboxBatch::empty().msg_size(), MESSAGE_FRAME_SIZE);
+
+        let batch = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
+        let base = &batch as *const _ as usize;
+        let tags = core::ptr::addr_of!(batch.msg_tags) as usize;
+        let end = core::ptr::addr_of!(batch.msg_end) as usize;
+        assert_eq!(tags - base, 8);
+        assert_eq!(end - tags, size_of::<ClockrateGet>());
+        assert_eq!(end + 4 - base, batch.msg_size() as usize);
+    }
+
     #[test]
     fn create_single_item_batch() {
         let batch = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
@@ -202,4 +489,363 @@ mod tests {
         assert!(slice[7] == PropertyTagId::MaxClockrateGet as u32);
         assert!(slice[12] == PropertyTagId::BoardMACAddressGet as u32);
     }
+
+    #[test]
+    fn create_batch_from_tuple() {
+        let batch = MailboxBatch::empty().with_tags((
+            PhysicalSizeSet::new(640, 480),
+            VirtualSizeSet::new(640, 480),
+            DepthSet::new(8),
+            PitchGet::new(),
+        ));
+
+        let slice = unsafe {
+            core::slice::from_raw_parts(
+                &batch as *const MailboxBatch<_> as *const u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+
+        let physical_size = size_of::<PhysicalSizeSet>() >> 2;
+        let virtual_size = size_of::<VirtualSizeSet>() >> 2;
+        let depth = size_of::<DepthSet>() >> 2;
+        assert_eq!(slice[2], PropertyTagId::PhysicalSizeSet as u32);
+        assert_eq!(
+            slice[2 + physical_size],
+            PropertyTagId::VirtualSizeSet as u32
+        );
+        assert_eq!(
+            slice[2 + physical_size + virtual_size],
+            PropertyTagId::DepthSet as u32
+        );
+        assert_eq!(
+            slice[2 + physical_size + virtual_size + depth],
+            PropertyTagId::PitchGet as u32
+        );
+        assert_eq!(batch.get_tag::<DepthSet, _>().response().depth(), 8);
+    }
+
+    #[test]
+    fn tags_keep_insertion_order() {
+        let batch = MailboxBatch::empty()
+            .with_tag(PhysicalSizeSet::new(1024, 768))
+            .with_tag(VirtualSizeSet::new(1024, 768))
+            .with_tag(DepthSet::new(32))
+            .with_tag(PitchGet::new())
+            .with_tag(VirtualOffsetSet::new(0, 0))
+            .with_tag(VirtualSizeGet::new())
+            .with_tag(FramebufferAllocate::new(16));
+        let expected = [
+            PropertyTagId::PhysicalSizeSet,
+            PropertyTagId::VirtualSizeSet,
+            PropertyTagId::DepthSet,
+            PropertyTagId::PitchGet,
+            PropertyTagId::VirtualOffsetSet,
+            PropertyTagId::VirtualSizeGet,
+            PropertyTagId::FramebufferAllocate,
+        ];
+
+        let slice = unsafe {
+            core::slice::from_raw_parts(
+                &batch as *const MailboxBatch<_> as *const u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+
+        // walk the serialized tags the same way the VideoCore does
+        let mut tag_ids = Vec::new();
+        let mut idx = 2;
+        while slice[idx] != 0x0 {
+            tag_ids.push(slice[idx]);
+            idx += 3 + ((slice[idx + 1] as usize + 3) >> 2);
+        }
+        assert_eq!(idx, slice.len() - 1);
+        assert_eq!(
+            tag_ids,
+            expected.iter().map(|&id| id as u32).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn validate_framebuffer_batch() {
+        let batch = MailboxBatch::empty().with_tags((
+            PhysicalSizeSet::new(1024, 768),
+            VirtualSizeSet::new(1024, 768),
+            DepthSet::new(32),
+            FramebufferAllocate::new(16),
+            PitchGet::new(),
+        ));
+        assert_eq!(batch.validate_framebuffer(), Ok(()));
+        // a batch without framebuffer property tags is valid
+        let batch = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
+        assert_eq!(batch.validate_framebuffer(), Ok(()));
+    }
+
+    #[test]
+    fn accept_virtual_size_for_double_buffering() {
+        let batch = MailboxBatch::empty().with_tags((
+            PhysicalSizeSet::new(640, 480),
+            VirtualSizeSet::new(640, 960),
+            FramebufferAllocate::new(16),
+        ));
+        assert_eq!(batch.validate_framebuffer(), Ok(()));
+        // but the virtual size must not be set to different values
+        let batch = MailboxBatch::empty()
+            .with_tags((VirtualSizeSet::new(640, 960), VirtualSizeSet::new(640, 480)));
+        assert_eq!(
+            batch.validate_framebuffer(),
+            Err(MailboxError::InvalidFramebufferBatch)
+        );
+    }
+
+    #[test]
+    fn reject_conflicting_framebuffer_tags() {
+        // the depth is set after the allocation
+        let batch = MailboxBatch::empty().with_tags((
+            PhysicalSizeSet::new(640, 480),
+            FramebufferAllocate::new(16),
+            DepthSet::new(32),
+        ));
+        assert_eq!(
+            batch.validate_framebuffer(),
+            Err(MailboxError::InvalidFramebufferBatch)
+        );
+        // the depth is set twice to different values
+        let batch = MailboxBatch::empty().with_tags((DepthSet::new(16), DepthSet::new(32)));
+        assert_eq!(
+            batch.validate_framebuffer(),
+            Err(MailboxError::InvalidFramebufferBatch)
+        );
+        // setting the same value twice is fine
+        let batch = MailboxBatch::empty().with_tags((DepthSet::new(32), DepthSet::new(32)));
+        assert_eq!(batch.validate_framebuffer(), Ok(()));
+    }
+
+    #[test]
+    fn reject_framebuffer_tag_with_small_value_buffer() {
+        // a tag using the id of the physical size with the single word value buffer of the depth
+        let mut batch = MailboxBatch::empty().with_tag(DepthSet::new(32));
+        unsafe {
+            (&mut batch as *mut _ as *mut u32)
+                .add(2)
+                .write(PropertyTagId::PhysicalSizeSet as u32);
+        }
+        assert_eq!(
+            batch.validate_framebuffer(),
+            Err(MailboxError::InvalidFramebufferBatch)
+        );
+    }
+
+    #[test]
+    fn concat_batches() {
+        let clocks = MailboxBatch::empty()
+            .with_tag(ClockrateGet::new(ClockId::Core))
+            .with_tag(MaxClockrateGet::new(ClockId::Arm));
+        let framebuffer = MailboxBatch::empty().with_tags((
+            PhysicalSizeSet::new(640, 480),
+            VirtualSizeSet::new(640, 480),
+            DepthSet::new(16),
+        ));
+        let clocks_size = clocks.msg_size;
+        let framebuffer_size = framebuffer.msg_size;
+        let batch = clocks.concat(framebuffer);
+        assert_eq!(batch.msg_size, clocks_size + framebuffer_size - 12);
+
+        // the tags of both batches are retrieved by their type
+        assert_eq!(
+            batch.get_tag::<MaxClockrateGet, _>().response().clock_id(),
+            Ok(ClockId::Arm)
+        );
+        assert_eq!(
+            batch.get_tag::<VirtualSizeSet, _>().response().height(),
+            480
+        );
+        assert_eq!(batch.get_tag::<DepthSet, _>().response().depth(), 16);
+
+        let slice = unsafe {
+            core::slice::from_raw_parts(
+                &batch as *const MailboxBatch<_> as *const u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+        // walk the serialized tags the same way the VideoCore does
+        let mut tag_ids = Vec::new();
+        let mut idx = 2;
+        while slice[idx] != 0x0 {
+            tag_ids.push(slice[idx]);
+            idx += 3 + ((slice[idx + 1] as usize + 3) >> 2);
+        }
+        assert_eq!(idx, slice.len() - 1);
+        assert_eq!(
+            tag_ids,
+            [
+                PropertyTagId::ClockrateGet as u32,
+                PropertyTagId::MaxClockrateGet as u32,
+                PropertyTagId::PhysicalSizeSet as u32,
+                PropertyTagId::VirtualSizeSet as u32,
+                PropertyTagId::DepthSet as u32,
+            ]
+        );
+    }
+
+    #[test]
+    fn all_tags_succeeded() {
+        let mut batch = MailboxBatch::empty()
+            .with_tag(ClockrateGet::new(ClockId::Core))
+            .with_tag(BoardSerialGet::new())
+            .with_tag(MaxClockrateGet::new(ClockId::Arm));
+        assert!(!batch.all_succeeded());
+
+        let words = unsafe {
+            core::slice::from_raw_parts_mut(
+                &mut batch as *mut MailboxBatch<_> as *mut u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+        // simulate the response state of each tag written by the VideoCore
+        let states = [2 + 2, 2 + 5 + 2, 2 + 5 + 5 + 2];
+        for &idx in states.iter() {
+            words[idx] = 0x8000_0008;
+        }
+        assert!(batch.all_succeeded());
+
+        // the VideoCore skipped the tag in the middle
+        let words = unsafe {
+            core::slice::from_raw_parts_mut(
+                &mut batch as *mut MailboxBatch<_> as *mut u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+        words[states[1]] = 0x0;
+        assert!(!batch.all_succeeded());
+        assert!(batch.get_tag::<MaxClockrateGet, _>().state() & 0x8000_0000 != 0);
+
+        assert!(MailboxBatch::empty().all_succeeded());
+    }
+
+    #[test]
+    fn board_serial_response_has_two_words() {
+        let batch = MailboxBatch::empty().with_tag(BoardSerialGet::new());
+
+        let slice = unsafe {
+            core::slice::from_raw_parts(
+                &batch as *const MailboxBatch<_> as *const u32,
+                (batch.msg_size >> 2) as usize,
+            )
+        };
+
+        assert_eq!(slice[0], 12 + 12 + 8);
+        assert_eq!(slice[2], PropertyTagId::BoardSerialGet as u32);
+        // the value buffer provides room for the lower and upper 32 bits of the serial
+        assert_eq!(slice[3], 8);
+    }
+
+    #[test]
+    fn accumulate_batch_size() {
+        let batch = MailboxBatch::empty().with_tag(ClockrateGet::new(ClockId::Core));
+        assert_eq!(batch.msg_size, 12 + size_of::<ClockrateGet>() as u32);
+        let batch = batch.with_tag(MaxClockrateGet::new(ClockId::Arm));
+        assert_eq!(
+            batch.msg_size,
+            12 + (size_of::<ClockrateGet>() + size_of::<MaxClockrateGet>()) as u32
+        );
+        let batch = batch.with_tag(BoardMACAddressGet::new());
+        assert_eq!(
+            batch.msg_size,
+            12 + (size_of::<ClockrateGet>()
+                + size_of::<MaxClockrateGet>()
+                + size_of::<BoardMACAddressGet>()) as u32
+        );
+        assert!(!batch.is_oversized());
+    }
+
+    #[test]
+    fn overflowing_batch_size_is_marked_oversized() {
+        let batch = MailboxBatch {
+            msg_size: u32::MAX - 8,
+            msg_type: MessageState::Request,
+            msg_tags: Empty,
+            msg_end: 0,
+        }
+        .with_tag(ClockrateGet::new(ClockId::Core));
+        assert!(batch.is_oversized());
+        // once oversized the batch stays oversized
+        let batch = batch.with_tag(MaxClockrateGet::new(ClockId::Arm));
+        assert!(batch.is_oversized());
+        // and will never be passed to the mailbox
+        mock::install(|_, _| Some(0));
+        assert!(Mailbox::new().send_batch(batch).is_err());
+    }
+
+    #[test]
+    fn send_batch_processes_all_tags() {
+        mock::Firmware::new()
+            .respond(
+                PropertyTagId::ClockrateGet,
+                &[ClockId::Core as u32, 250_000_000],
+            )
+            .respond(
+                PropertyTagId::MaxClockrateGet,
+                &[ClockId::Arm as u32, 1_200_000_000],
+            )
+            .install();
+        let mut mb = Mailbox::new();
+        let batch = MailboxBatch::empty()
+            .with_tag(ClockrateGet::new(ClockId::Core))
+            .with_tag(MaxClockrateGet::new(ClockId::Arm));
+        let batch = mb.send_batch(batch).unwrap();
+        assert_eq!(
+            batch.get_tag::<ClockrateGet, _>().response().clock_rate(),
+            250_000_000
+        );
+        assert_eq!(
+            batch
+                .get_tag::<MaxClockrateGet, _>()
+                .response()
+                .clock_rate(),
+            1_200_000_000
+        );
+    }
+
+    #[test]
+    fn send_batch_rejects_empty_batch() {
+        let exchanged = std::rc::Rc::new(std::cell::Cell::new(false));
+        let flag = exchanged.clone();
+        mock::install_raw(move |_, _| {
+            flag.set(true);
+            Ok(())
+        });
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.send_batch(MailboxBatch::empty()).err(),
+            Some(MailboxError::EmptyBatch)
+        );
+        // the empty batch has never been passed to the mailbox
+        assert!(!exchanged.get());
+    }
+
+    #[test]
+    fn send_batch_detects_shorter_response() {
+        mock::install_raw(|_, buffer| {
+            mock::process(buffer, &mut |_, values: &mut [u32]| {
+                values[1] = 250_000_000;
+                Some(8)
+            });
+            // the VideoCore has dropped the last tag from the response
+            buffer[0] -= core::mem::size_of::<MaxClockrateGet>() as u32;
+            Ok(())
+        });
+        let expected = 12 + 2 * core::mem::size_of::<ClockrateGet>() as u32;
+        let mut mb = Mailbox::new();
+        let batch = MailboxBatch::empty()
+            .with_tag(ClockrateGet::new(ClockId::Core))
+            .with_tag(MaxClockrateGet::new(ClockId::Arm));
+        assert_eq!(
+            mb.send_batch(batch).err(),
+            Some(MailboxError::SizeMismatch {
+                expected,
+                actual: expected - core::mem::size_of::<MaxClockrateGet>() as u32,
+            })
+        );
+    }
 }
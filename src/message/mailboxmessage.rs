@@ -7,6 +7,7 @@
 
 //! # Mailbox Message
 //!
+use super::MESSAGE_FRAME_SIZE;
 use crate::{propertytags::*, MessageState};
 
 #[repr(C, align(16))]
@@ -31,6 +32,11 @@ where
     pub fn state(&self) -> MessageState {
         self.msg_type
     }
+
+    /// Get the property tag contained in this mailbox message
+    pub fn into_tag(self) -> T {
+        self.msg_tag
+    }
 }
 
 impl<T> From<T> for MailboxMessage<T>
@@ -41,10 +47,26 @@ where
     /// can be constructed
     fn from(item: T) -> Self {
         MailboxMessage {
-            msg_size: 12 + core::mem::size_of::<T>() as u32, //+ 8 + 4 + core::mem::size_of::<T>() as u32,
+            msg_size: MESSAGE_FRAME_SIZE + core::mem::size_of::<T>() as u32,
             msg_type: MessageState::Request,
             msg_tag: item,
             msg_end: 0x0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClockId;
+
+    #[test]
+    fn message_frame_matches_field_offsets() {
+        let message = MailboxMessage::from(ClockrateGet::new(ClockId::Core));
+        let base = &message as *const _ as usize;
+        let tag = core::ptr::addr_of!(message.msg_tag) as usize;
+        let end = core::ptr::addr_of!(message.msg_end) as usize;
+        assert_eq!(tag - base, 8);
+        assert_eq!(end + 4 - base, message.msg_size as usize);
+    }
+}
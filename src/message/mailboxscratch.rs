@@ -0,0 +1,77 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # MailboxScratch buffer
+//!
+//! A properly aligned buffer for hand crafted mailbox messages that are send with [Mailbox::send_raw](crate::Mailbox::send_raw).
+//! As the buffer can be created in a const context it could also be placed in a ``static`` that is guarded by a
+//! lock, e.g. a ``ruspiro_lock::Mutex``.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let mut scratch = MailboxScratch::<8>::new();
+//!     let buffer = scratch.as_mut_slice();
+//!     // get the core clock rate
+//!     buffer.copy_from_slice(&[32, 0x0, 0x3_0002, 8, 0x0, 0x4, 0x0, 0x0]);
+//!     mb.send_raw(MailboxChannel::PropertyTagsVc, buffer).unwrap();
+//!     let clock_rate = buffer[6];
+//! }
+//! ```
+
+/// Buffer of ``N`` u32 words that is aligned as required by the mailbox
+#[derive(Copy, Clone, Debug)]
+#[repr(C, align(16))]
+pub struct MailboxScratch<const N: usize>([u32; N]);
+
+impl<const N: usize> MailboxScratch<N> {
+    /// Create a new buffer with all words set to 0
+    pub const fn new() -> Self {
+        Self([0; N])
+    }
+
+    /// Create a new buffer containing the given words
+    pub const fn with_words(words: [u32; N]) -> Self {
+        Self(words)
+    }
+
+    /// Get the words of this buffer
+    pub fn as_slice(&self) -> &[u32] {
+        &self.0
+    }
+
+    /// Get the words of this buffer for modification
+    pub fn as_mut_slice(&mut self) -> &mut [u32] {
+        &mut self.0
+    }
+}
+
+impl<const N: usize> Default for MailboxScratch<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_buffer_is_aligned() {
+        assert_eq!(core::mem::align_of::<MailboxScratch<1>>(), 16);
+        assert_eq!(core::mem::align_of::<MailboxScratch<64>>(), 16);
+
+        let mut scratch = MailboxScratch::<5>::new();
+        assert_eq!(scratch.as_slice().len(), 5);
+        assert_eq!(scratch.as_mut_slice().len(), 5);
+        assert_eq!(scratch.as_slice().as_ptr() as usize & 0xF, 0);
+    }
+}
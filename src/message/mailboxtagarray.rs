@@ -0,0 +1,166 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Mailbox Tag Array
+//!
+//! A message containing up to ``N`` property tags of the same type. This is used to query or set a value for
+//! several ids with one message, e.g. the rates of all clocks. Other than with a [MailboxBatch](crate::MailboxBatch)
+//! the number of property tags need not to be known at compile time. A message with a single property tag may
+//! also pass only the leading part of the tag's value buffer, e.g. the palette entries to be updated.
+
+use super::MESSAGE_FRAME_SIZE;
+use crate::{propertytags::*, MailboxError, MailboxResult, MessageState};
+use core::mem::MaybeUninit;
+
+#[repr(C, align(16))]
+pub(crate) struct MailboxTagArray<T, const N: usize> {
+    msg_size: u32,
+    msg_type: MessageState,
+    /// The property tags pushed are followed by the end tag, so the end tag is placed into the first unused slot
+    msg_tags: [MaybeUninit<T>; N],
+    msg_end: u32,
+    /// The number of property tags pushed, this is not passed to the VideoCore
+    count: usize,
+}
+
+impl<T, const N: usize> MailboxTagArray<T, N>
+where
+    T: PropertyTag + Copy,
+{
+    /// Create a new message without any property tag
+    pub fn new() -> Self {
+        Self {
+            msg_size: MESSAGE_FRAME_SIZE,
+            msg_type: MessageState::Request,
+            msg_tags: [MaybeUninit::uninit(); N],
+            msg_end: 0x0,
+            count: 0,
+        }
+    }
+
+    /// Create a new message containing all the given property tags
+    pub fn from_tags(tags: [T; N]) -> Self {
+        let mut message = Self::new();
+        for tag in tags.iter() {
+            // the message is sized to hold all tags
+            let _ = message.push(*tag);
+        }
+        message
+    }
+
+    /// Add the property tag to the message. If the message is already full the tag is rejected with
+    /// [MailboxError::BatchTooLarge].
+    pub fn push(&mut self, tag: T) -> MailboxResult<()> {
+        if self.count == N {
+            return Err(MailboxError::BatchTooLarge);
+        }
+        self.msg_tags[self.count] = MaybeUninit::new(tag);
+        self.count += 1;
+        self.msg_size = MESSAGE_FRAME_SIZE + (self.count * core::mem::size_of::<T>()) as u32;
+        Ok(())
+    }
+
+    /// Place the end tag directly behind the last property tag and return the message words to be passed to
+    /// the mailbox
+    pub fn as_mut_words(&mut self) -> &mut [u32] {
+        let words = self.msg_size as usize >> 2;
+        let message = self as *mut Self as *mut u32;
+        unsafe {
+            // the message header, the property tags and the end tag are all made of u32 words and the end tag
+            // either lands in the first unused slot or in the end tag of the structure
+            message.add(words - 1).write(0x0);
+            core::slice::from_raw_parts_mut(message, words)
+        }
+    }
+
+    /// Get the state of the processed message
+    pub fn state(&self) -> MessageState {
+        self.msg_type
+    }
+
+    /// Get the property tags contained in this message
+    pub fn tags(&self) -> impl Iterator<Item = T> + '_ {
+        self.msg_tags[..self.count]
+            .iter()
+            .map(|tag| unsafe { tag.assume_init() })
+    }
+}
+
+impl<T> MailboxTagArray<T, 1>
+where
+    T: PropertyTag + Copy,
+{
+    /// Create a new message containing the given property tag, where only the leading ``value_size`` bytes of
+    /// it's value buffer are passed to the VideoCore. This allows to send property tags with a variable length
+    /// payload. The value size is limited to the size of the value buffer of the property tag.
+    pub fn from_truncated_tag(tag: T, value_size: usize) -> Self {
+        // the property tag header consists of the tag id, the value buffer size and the tag state
+        let value_size = value_size.min(T::WIRE_SIZE - 12) & !0x3;
+        let mut message = Self::from_tags([tag]);
+        unsafe {
+            (message.msg_tags[0].as_mut_ptr() as *mut u32)
+                .add(1)
+                .write(value_size as u32);
+        }
+        message.msg_size = MESSAGE_FRAME_SIZE + 12 + value_size as u32;
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClockId;
+
+    #[test]
+    fn end_tag_follows_last_tag() {
+        let mut message = MailboxTagArray::<ClockrateGet, 3>::new();
+        message.push(ClockrateGet::new(ClockId::Core)).unwrap();
+        message.push(ClockrateGet::new(ClockId::Arm)).unwrap();
+        let words = message.as_mut_words();
+        assert_eq!(words.len(), 2 + 2 * 5 + 1);
+        assert_eq!(words[0], 52);
+        assert_eq!(&words[2..4], &[ClockrateGet::TAG_ID as u32, 8]);
+        assert_eq!(words[10], ClockId::Arm as u32);
+        assert_eq!(words[12], 0x0);
+    }
+
+    #[test]
+    fn full_message_rejects_tags() {
+        let mut message = MailboxTagArray::from_tags([ClockrateGet::new(ClockId::Core)]);
+        assert_eq!(message.as_mut_words().len(), 8);
+        assert_eq!(
+            message.push(ClockrateGet::new(ClockId::Arm)),
+            Err(MailboxError::BatchTooLarge)
+        );
+        assert_eq!(message.tags().count(), 1);
+    }
+
+    #[test]
+    fn truncated_tag_passes_leading_values() {
+        let mut palette = [0; 256];
+        palette[..2].copy_from_slice(&[0xFF, 0xFF00]);
+        let mut message =
+            MailboxTagArray::from_truncated_tag(PaletteSet::new(16, 2, palette), 8 + 2 * 4);
+        let words = message.as_mut_words();
+        assert_eq!(
+            words,
+            &[
+                40,
+                0x0,
+                PaletteSet::TAG_ID as u32,
+                16,
+                0x0,
+                16,
+                2,
+                0xFF,
+                0xFF00,
+                0x0
+            ]
+        );
+    }
+}
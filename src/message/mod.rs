@@ -5,8 +5,27 @@
  * License: Apache License 2.0 / MIT
  **********************************************************************************************************************/
 
+use crate::MessageState;
+
+/// The size in bytes of a message without any property tag: the message size and the message state in front of the
+/// property tags and the end tag after them.
+pub(crate) const MESSAGE_FRAME_SIZE: u32 = 12;
+
+// the size of the message frame need to match the size of it's fields
+const _MESSAGE_FRAME_SIZE: [(); MESSAGE_FRAME_SIZE as usize] =
+    [(); 2 * core::mem::size_of::<u32>() + core::mem::size_of::<MessageState>()];
+
 mod mailboxbatch;
 pub use mailboxbatch::*;
 
+mod mailboxscratch;
+pub use mailboxscratch::*;
+
 mod mailboxmessage;
 pub(crate) use mailboxmessage::*;
+
+mod mailboxtagarray;
+pub(crate) use mailboxtagarray::*;
+
+mod tagged;
+pub use tagged::*;
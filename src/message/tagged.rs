@@ -0,0 +1,127 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Tagged property tags
+//!
+//! The tags of a [MailboxBatch](crate::MailboxBatch) are retrieved by their type. If a batch contains several
+//! property tags of the same type, e.g. to set the rate of several clocks, each of them could be wrapped into a
+//! [Tagged] property tag with a distinct index. The index becomes part of the type, so each of them could be
+//! retrieved from the batch by it's index.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let batch = MailboxBatch::empty()
+//!         .with_tag(Tagged::<_, 0>::new(ClockrateSet::new(ClockId::Arm, 1_200_000_000, 0)))
+//!         .with_tag(Tagged::<_, 1>::new(ClockrateSet::new(ClockId::Core, 400_000_000, 0)));
+//!     let batch = mb.send_batch(batch).unwrap();
+//!     let arm_rate = batch.get_tag::<Tagged<ClockrateSet, 0>, _>().response().clock_rate();
+//!     let core_rate = batch.get_tag::<Tagged<ClockrateSet, 1>, _>().response().clock_rate();
+//! }
+//! ```
+
+use crate::{PropertyTag, PropertyTagId};
+
+/// A [PropertyTag] marked with the index ``N``. It is passed to the VideoCore exactly like the wrapped
+/// property tag.
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+pub struct Tagged<T, const N: usize>(T);
+
+impl<T: PropertyTag, const N: usize> Tagged<T, N> {
+    /// Mark the given property tag with the index ``N``
+    pub const fn new(tag: T) -> Self {
+        Self(tag)
+    }
+
+    /// The wrapped property tag
+    pub fn tag(&self) -> &T {
+        &self.0
+    }
+
+    /// Unwrap the property tag
+    pub fn into_tag(self) -> T {
+        self.0
+    }
+}
+
+impl<T: PropertyTag, const N: usize> PropertyTag for Tagged<T, N> {
+    type Request = T::Request;
+    type Response = T::Response;
+    const TAG_ID: PropertyTagId = T::TAG_ID;
+
+    fn tagid(&self) -> PropertyTagId {
+        self.0.tagid()
+    }
+
+    fn state(&self) -> u32 {
+        self.0.state()
+    }
+
+    fn response(&self) -> &Self::Response {
+        self.0.response()
+    }
+
+    fn size(&self) -> u32 {
+        self.0.size()
+    }
+
+    fn response_words(&self) -> &[u32] {
+        self.0.response_words()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, ClockId, ClockrateSet, Mailbox, MailboxBatch};
+
+    #[test]
+    fn same_tag_type_several_times() {
+        mock::Firmware::new()
+            .on(PropertyTagId::ClockrateSet, |values| {
+                // grant the next supported rate
+                values[1] = values[1] / 100_000_000 * 100_000_000;
+                Some(8)
+            })
+            .install();
+        let batch = MailboxBatch::empty()
+            .with_tag(Tagged::<_, 0>::new(ClockrateSet::new(
+                ClockId::Arm,
+                1_234_000_000,
+                0,
+            )))
+            .with_tag(Tagged::<_, 1>::new(ClockrateSet::new(
+                ClockId::Core,
+                450_000_000,
+                0,
+            )));
+        let batch = Mailbox::new().send_batch(batch).unwrap();
+
+        let arm = batch.get_tag::<Tagged<ClockrateSet, 0>, _>().response();
+        assert_eq!(arm.clock_id(), Ok(ClockId::Arm));
+        assert_eq!(arm.clock_rate(), 1_200_000_000);
+        let core = batch.get_tag::<Tagged<ClockrateSet, 1>, _>().response();
+        assert_eq!(core.clock_id(), Ok(ClockId::Core));
+        assert_eq!(core.clock_rate(), 400_000_000);
+    }
+
+    #[test]
+    fn same_wire_layout_as_wrapped_tag() {
+        assert_eq!(
+            Tagged::<ClockrateSet, 7>::WIRE_SIZE,
+            ClockrateSet::WIRE_SIZE
+        );
+        let tag = Tagged::<_, 7>::new(ClockrateSet::new(ClockId::Arm, 1, 0));
+        assert_eq!(tag.tagid() as u32, PropertyTagId::ClockrateSet as u32);
+        assert_eq!(tag.size(), tag.tag().size());
+    }
+}
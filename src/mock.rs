@@ -0,0 +1,264 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Mailbox mock
+//!
+//! When running the tests there is no VideoCore available that could process the mailbox messages. Therefore the
+//! message buffer is handed over to a firmware emulation that is installed by the individual test. The emulation is
+//! kept per thread as the tests are running in parallel.
+//!
+
+use crate::{MailboxChannel, MailboxError, MailboxResult, PropertyTagId};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+
+type RawFirmware = Box<dyn FnMut(MailboxChannel, &mut [u32]) -> MailboxResult<()>>;
+type TagHandler = Box<dyn FnMut(&mut [u32]) -> Option<u32>>;
+
+thread_local! {
+    static FIRMWARE: RefCell<Option<RawFirmware>> = RefCell::new(None);
+    static INCOMING: RefCell<VecDeque<(MailboxChannel, *mut u32)>> = RefCell::new(VecDeque::new());
+    static ACKNOWLEDGED: RefCell<Vec<(MailboxChannel, *mut u32)>> = RefCell::new(Vec::new());
+    static REGISTERS: RefCell<HashMap<usize, (u32, usize)>> = RefCell::new(HashMap::new());
+    static QUEUED: RefCell<HashMap<usize, VecDeque<u32>>> = RefCell::new(HashMap::new());
+    static CACHE_MAINTENANCE: RefCell<Vec<CacheMaintenance>> = RefCell::new(Vec::new());
+    static MEMORY: RefCell<Option<(u32, *mut u8, usize)>> = RefCell::new(None);
+}
+
+/// The cache maintenance operations on a memory range given by it's start address and length
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum CacheMaintenance {
+    Flush(usize, usize),
+    Invalidate(usize, usize),
+}
+
+/// Install a firmware emulation that get's access to the whole message buffer passed to the mailbox
+pub(crate) fn install_raw<F>(firmware: F)
+where
+    F: FnMut(MailboxChannel, &mut [u32]) -> MailboxResult<()> + 'static,
+{
+    FIRMWARE.with(|fw| fw.replace(Some(Box::new(firmware))));
+}
+
+/// Install a firmware emulation that is called for each property tag contained in the message buffer. The handler
+/// get's the tag id and the value buffer of the tag and returns the size in bytes of the response it has written
+/// into the value buffer. If the handler returns ``None`` the tag is treated as not processed and the whole message
+/// will be responded with an error state.
+pub(crate) fn install<F>(mut handler: F)
+where
+    F: FnMut(u32, &mut [u32]) -> Option<u32> + 'static,
+{
+    install_raw(move |_, buffer| {
+        process(buffer, &mut handler);
+        Ok(())
+    });
+}
+
+/// Firmware emulation that processes the property tags it has a handler for. Any other property tag is not
+/// processed, so the message is responded with an error state.
+#[derive(Default)]
+pub(crate) struct Firmware {
+    handlers: Vec<(u32, TagHandler)>,
+}
+
+impl Firmware {
+    /// Create a firmware emulation that does not process any property tag
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let the handler process the given property tag. The handler get's the value buffer of the tag and returns
+    /// the size in bytes of the response it has written into it or ``None`` to leave the tag unprocessed. A handler
+    /// added later for the same property tag replaces the earlier one.
+    pub(crate) fn on<F>(mut self, tag: PropertyTagId, handler: F) -> Self
+    where
+        F: FnMut(&mut [u32]) -> Option<u32> + 'static,
+    {
+        self.handlers.push((tag as u32, Box::new(handler)));
+        self
+    }
+
+    /// Respond to the given property tag with the fixed response values
+    pub(crate) fn respond(self, tag: PropertyTagId, response: &[u32]) -> Self {
+        let response = response.to_vec();
+        self.on(tag, move |values| {
+            values[..response.len()].copy_from_slice(&response);
+            Some(response.len() as u32 * 4)
+        })
+    }
+
+    /// Process a single property tag with the handler for it's tag id
+    pub(crate) fn handle(&mut self, tag: u32, values: &mut [u32]) -> Option<u32> {
+        self.handlers
+            .iter_mut()
+            .rev()
+            .find(|(id, _)| *id == tag)
+            .and_then(|(_, handler)| handler(values))
+    }
+
+    /// Install this firmware emulation for the current thread
+    pub(crate) fn install(mut self) {
+        install(move |tag, values| self.handle(tag, values));
+    }
+}
+
+/// Walk through the property tags of the message buffer and let the handler process each of them. The response
+/// state of each tag and the whole message is updated like the VideoCore would do.
+pub(crate) fn process<F>(buffer: &mut [u32], handler: &mut F)
+where
+    F: FnMut(u32, &mut [u32]) -> Option<u32>,
+{
+    let mut success = true;
+    let mut idx = 2;
+    // the message is terminated by the end tag 0x0
+    while idx < buffer.len() && buffer[idx] != 0x0 {
+        let tag_id = buffer[idx];
+        // the value buffer is always padded to a multiple of 32 bits
+        let value_words = ((buffer[idx + 1] + 3) >> 2) as usize;
+        let values = &mut buffer[idx + 3..idx + 3 + value_words];
+        match handler(tag_id, values) {
+            Some(size) => buffer[idx + 2] = 0x8000_0000 | size,
+            None => success = false,
+        }
+        idx += 3 + value_words;
+    }
+    buffer[1] = if success { 0x8000_0000 } else { 0x8000_0001 };
+}
+
+/// Hand the message buffer to the installed firmware emulation. If there is no emulation installed the mailbox
+/// does not respond at all and the exchange times out.
+pub(crate) fn exchange(channel: MailboxChannel, buffer: &mut [u32]) -> MailboxResult<()> {
+    FIRMWARE.with(|fw| match fw.borrow_mut().as_mut() {
+        Some(firmware) => firmware(channel, buffer),
+        None => Err(MailboxError::Timeout),
+    })
+}
+
+/// Queue a message buffer the VideoCore passes to the ARM on the given channel. The buffer need to outlive it's
+/// reception.
+pub(crate) fn push_incoming(channel: MailboxChannel, buffer: &mut [u32]) {
+    INCOMING.with(|incoming| {
+        incoming
+            .borrow_mut()
+            .push_back((channel, buffer.as_mut_ptr()))
+    });
+}
+
+/// Receive the next message buffer queued for the given channel. If there is none the mailbox does not respond
+/// and the reception times out.
+pub(crate) fn receive(channel: MailboxChannel) -> MailboxResult<*mut u32> {
+    INCOMING.with(|incoming| {
+        let mut incoming = incoming.borrow_mut();
+        let position = incoming
+            .iter()
+            .position(|&(incoming_channel, _)| incoming_channel as u8 == channel as u8);
+        match position.and_then(|position| incoming.remove(position)) {
+            Some((_, buffer)) => Ok(buffer),
+            None => Err(MailboxError::Timeout),
+        }
+    })
+}
+
+/// Record the acknowledgement of a received message buffer
+pub(crate) fn acknowledge(channel: MailboxChannel, buffer: *mut u32) {
+    ACKNOWLEDGED.with(|acknowledged| acknowledged.borrow_mut().push((channel, buffer)));
+}
+
+/// The message buffers that has been acknowledged so far
+pub(crate) fn acknowledged() -> Vec<(MailboxChannel, *mut u32)> {
+    ACKNOWLEDGED.with(|acknowledged| acknowledged.borrow().clone())
+}
+
+/// Set the value of the emulated mailbox register at the given address
+pub(crate) fn set_register(address: usize, value: u32) {
+    REGISTERS.with(|registers| registers.borrow_mut().entry(address).or_default().0 = value);
+}
+
+/// Queue values the emulated mailbox register at the given address returns on the next reads. Once the queued
+/// values are read the register returns the value that has been set.
+pub(crate) fn queue_register(address: usize, values: &[u32]) {
+    QUEUED.with(|queued| {
+        queued
+            .borrow_mut()
+            .entry(address)
+            .or_default()
+            .extend(values.iter().copied())
+    });
+}
+
+/// Read the emulated mailbox register at the given address. Registers that has not been set read as 0
+pub(crate) fn read_register(address: usize) -> u32 {
+    let queued = QUEUED.with(|queued| {
+        queued
+            .borrow_mut()
+            .get_mut(&address)
+            .and_then(|values| values.pop_front())
+    });
+    REGISTERS.with(|registers| {
+        let mut registers = registers.borrow_mut();
+        let register = registers.entry(address).or_default();
+        register.1 += 1;
+        queued.unwrap_or(register.0)
+    })
+}
+
+/// Write the emulated mailbox register at the given address
+pub(crate) fn write_register(address: usize, value: u32) {
+    set_register(address, value);
+}
+
+/// The number of times the emulated mailbox register at the given address has been read
+pub(crate) fn register_reads(address: usize) -> usize {
+    REGISTERS.with(|registers| {
+        registers
+            .borrow()
+            .get(&address)
+            .map_or(0, |register| register.1)
+    })
+}
+
+/// Record the flush of the data cache for the given memory range
+pub(crate) fn flush_dcache_range(start: usize, len: usize) {
+    CACHE_MAINTENANCE.with(|operations| {
+        operations
+            .borrow_mut()
+            .push(CacheMaintenance::Flush(start, len))
+    });
+}
+
+/// Record the invalidation of the data cache for the given memory range
+pub(crate) fn invalidate_dcache_range(start: usize, len: usize) {
+    CACHE_MAINTENANCE.with(|operations| {
+        operations
+            .borrow_mut()
+            .push(CacheMaintenance::Invalidate(start, len))
+    });
+}
+
+/// The cache maintenance operations that has been recorded so far
+pub(crate) fn cache_maintenance() -> Vec<CacheMaintenance> {
+    CACHE_MAINTENANCE.with(|operations| operations.borrow().clone())
+}
+
+/// Provide the memory at the given ARM physical address with the given buffer. The buffer need to outlive it's
+/// accesses.
+pub(crate) fn map_memory(address: u32, memory: &mut [u8]) {
+    MEMORY.with(|mapped| mapped.replace(Some((address, memory.as_mut_ptr(), memory.len()))));
+}
+
+/// The address of the buffer providing the memory at the given ARM physical address. Accessing memory that has not
+/// been provided with [map_memory] fails the test.
+pub(crate) fn memory_address(address: u32, len: usize) -> *mut u8 {
+    MEMORY.with(|mapped| match *mapped.borrow() {
+        Some((start, memory, size))
+            if address >= start && (address - start) as usize + len <= size =>
+        {
+            memory.wrapping_add((address - start) as usize)
+        }
+        _ => panic!("memory at {:#x} not mapped", address),
+    })
+}
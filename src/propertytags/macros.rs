@@ -10,7 +10,7 @@
 
 /// This macro defines the request part of the property tag data
 /// The macro expands to:
-/// ```
+/// ```ignore
 /// #[repr(C)]
 /// #[derive(Copy, Clone)]
 /// pub struct <PropertyTagName>DataRequest {
@@ -18,7 +18,7 @@
 /// }
 /// ```
 macro_rules! property_tag_request {
-    ($name:ident, {$($field:ident:$type:ty), *}) => {
+    ($name:ident, {$($field:ident:$type:ty $(= $default:expr)?), *}) => {
         #[doc(hidden)]
         #[repr(C)]
         #[derive(Copy, Clone)]
@@ -40,7 +40,7 @@ macro_rules! property_tag_request {
 /// }
 /// ```
 macro_rules! property_tag_response {
-    ($name:ident, {$($field:ident:$type:ty), *}) => {
+    ($name:ident, {$($field:ident:$type:tt $(as $id:ident)?), *}) => {
         #[doc(hidden)]
         #[repr(C)]
         #[derive(Copy, Clone)]
@@ -53,14 +53,59 @@ macro_rules! property_tag_response {
         #[doc(hidden)]
         impl $name {
             $(
-                pub fn $field(&self) -> $type {
-                    self.$field
-                }
+                property_tag_response_getter!($field, $type $(, $id)?);
+
+                property_tag_response_ref!($field, $type);
             )*
         }
     }
 }
 
+/// This macro defines the accessor of a response field. A field that is declared as ``<field>: u32 as <Id>`` keeps
+/// the raw value the VideoCore has written and the accessor converts it with ``TryFrom<u32>`` into the id type. So
+/// an unexpected value is reported as ``Err(<raw value>)`` instead of being read as an invalid enum value.
+/// The macro expands to:
+/// ```ignore
+/// pub fn <field>(&self) -> <type> {
+///     self.<field>
+/// }
+/// // or for an id field
+/// pub fn <field>(&self) -> Result<<Id>, u32> {
+///     <Id>::try_from(self.<field>)
+/// }
+/// ```
+macro_rules! property_tag_response_getter {
+    ($field:ident, $type:tt, $id:ident) => {
+        pub fn $field(&self) -> Result<$id, u32> {
+            <$id as core::convert::TryFrom<u32>>::try_from(self.$field)
+        }
+    };
+    ($field:ident, $type:tt) => {
+        pub fn $field(&self) -> $type {
+            self.$field
+        }
+    };
+}
+
+/// This macro defines an additional accessor returning a reference to an array field of the response. This
+/// allows to access the array without copying it out of the response.
+/// The macro expands to:
+/// ```ignore
+/// pub fn <field>_ref(&self) -> &[<type>; <size>] {
+///     &self.<field>
+/// }
+/// ```
+macro_rules! property_tag_response_ref {
+    ($field:ident, [$type:ty; $size:expr]) => {
+        paste::item! {
+            pub fn [<$field _ref>](&self) -> &[$type; $size] {
+                &self.$field
+            }
+        }
+    };
+    ($field:ident, $type:tt) => {};
+}
+
 /// This macros defines the message part of the property tag and will contain the request and response
 /// The macro expansd to:
 /// ```ignore
@@ -109,11 +154,12 @@ macro_rules! init_padding {
 
 /// This macros defines the required implementation for the property tag message structure defined so far
 macro_rules! property_tag_impl {
-    ($name:ident, {$($field:ident:$type:ty),*} $(,$padding:ident:$padtype:ty)?) => {
+    ($name:ident, {$($req_fields:tt)*} $(,$padding:ident:$padtype:ty)?) => {
         paste::item! {
             impl PropertyTag for $name {
                 type Request = [<$name Data Request>];
                 type Response = [<$name Data Response>];
+                const TAG_ID: PropertyTagId = PropertyTagId::$name;
 
                 /// Get the [PropertyTagId] of this property tag
                 fn tagid(&self) -> PropertyTagId {
@@ -140,29 +186,74 @@ macro_rules! property_tag_impl {
                 fn size(&self) -> u32 {
                     self.tagsize
                 }
+
+                fn response_words(&self) -> &[u32] {
+                    let mut size = core::cmp::min(
+                        self.tagsize as usize,
+                        core::mem::size_of::<[<$name Data>]>(),
+                    );
+                    // once processed the state contains the size of the response
+                    if self.tagstate & 0x8000_0000 != 0 {
+                        size = core::cmp::min(size, (self.tagstate & 0x7FFF_FFFF) as usize);
+                    }
+                    let words = core::ptr::addr_of!(self.tagdata) as *const u32;
+                    debug_assert!(words as usize & 0x3 == 0, "property tag need to be 32 bit aligned");
+                    unsafe { core::slice::from_raw_parts(words, (size + 3) >> 2) }
+                }
             }
+        }
+
+        property_tag_new!(@munch $name [$($padding: init_padding!($padtype),)?] [] []; $($req_fields)*);
+    };
+}
 
+/// This macro defines the constructor of the property tag. It walks through the request fields and collects the
+/// parameters of the constructor and the initialization of the request fields. A request field with a default
+/// value is initialized with this value and does not become a parameter of the constructor.
+/// The macro expands to:
+/// ```ignore
+/// impl <PropertyTagName> {
+///     pub fn new(<request field without default>_val: <type>, ...) -> Self {
+///         ...
+///     }
+/// }
+/// ```
+macro_rules! property_tag_new {
+    (@munch $name:ident $padding:tt [$($params:tt)*] [$($inits:tt)*]; $field:ident:$type:ty = $default:expr $(, $($rest:tt)*)?) => {
+        property_tag_new!(@munch $name $padding [$($params)*] [$($inits)* $field: $default,]; $($($rest)*)?);
+    };
+    (@munch $name:ident $padding:tt [$($params:tt)*] [$($inits:tt)*]; $field:ident:$type:ty $(, $($rest:tt)*)?) => {
+        paste::item! {
+            property_tag_new!(
+                @munch $name $padding
+                [$($params)* [<$field _val>]: $type,]
+                [$($inits)* $field: [<$field _val>],];
+                $($($rest)*)?
+            );
+        }
+    };
+    (@munch $name:ident [$($padding:tt)*] [$($params:tt)*] [$($inits:tt)*];) => {
+        paste::item! {
             #[allow(clippy::new_without_default)]
             impl $name {
                 /// Create a new property tag. All data the property tag defines in it's Request
-                /// structure are expected to be provided when constructing a [PropertyTag]
-                pub fn new(
-                    $(
-                        [<$field _val>]: $type,
-                    )*
-                ) -> Self {
+                /// structure without a default value are expected to be provided when constructing
+                /// a [PropertyTag]
+                pub fn new($($params)*) -> Self {
                     Self {
                         tagid: PropertyTagId::$name,
                         tagsize: core::mem::size_of::<[<$name Data>]>() as u32,
                         tagstate: 0x0,
-                        tagdata: [<$name Data>] {
-                            request: [<$name Data Request>] {
-                                $(
-                                    $field: [<$field _val>],
-                                )*
-                            }
+                        tagdata: {
+                            // the response might be larger than the request, so ensure all
+                            // bytes of the data are initialized
+                            let mut data: [<$name Data>] = unsafe { core::mem::zeroed() };
+                            data.request = [<$name Data Request>] {
+                                $($inits)*
+                            };
+                            data
                         },
-                        $($padding: init_padding!($padtype),)?
+                        $($padding)*
                     }
                 }
             }
@@ -170,10 +261,66 @@ macro_rules! property_tag_impl {
     };
 }
 
+/// This macro defines an additional constructor for property tags with an id as the first request field. The
+/// raw id is validated with the ``TryFrom<u32>`` implementation of the id type before the property tag is
+/// created, the invalid raw id is returned as error. The remaining request fields are passed as with ``new``.
+/// The macro expands to:
+/// ```ignore
+/// impl <PropertyTagName> {
+///     pub fn try_new_raw(raw_id: u32, <remaining request field>: <type>, ...) -> Result<Self, u32> {
+///         <Id>::try_from(raw_id).map(|id| Self::new(id, ...))
+///     }
+/// }
+/// ```
+macro_rules! property_tag_try_new_raw {
+    ($id:ident: $($name:ident $(($($param:ident: $type:ty),*))?),* $(,)?) => {
+        $(
+            impl $name {
+                /// Create a new property tag from the raw id. Returns ``Err(<raw id>)`` if the raw id is
+                /// not a valid id.
+                pub fn try_new_raw(raw_id: u32 $($(, $param: $type)*)?) -> Result<Self, u32> {
+                    <$id as core::convert::TryFrom<u32>>::try_from(raw_id)
+                        .map(|id| Self::new(id $($(, $param)*)?))
+                }
+            }
+        )*
+    };
+}
+
+/// This macro defines the accessor of the trailing words of a property tag declared with ``TRAILING: u32``. The
+/// trailing words follow the fixed response fields. Their number is not part of the type but taken from the
+/// response size the VideoCore reports in the state of the property tag, bound to the size of the property tag
+/// data. Only ``u32`` is supported as the words are taken from [PropertyTag::response_words].
+/// The macro expands to:
+/// ```ignore
+/// impl <PropertyTagName> {
+///     pub fn trailing(&self) -> &[u32] {
+///         ...
+///     }
+/// }
+/// ```
+// no property tag of this crate has trailing words yet
+#[allow(unused_macros)]
+macro_rules! property_tag_trailing {
+    ($name:ident, u32) => {
+        paste::item! {
+            impl $name {
+                /// The words following the fixed response fields as reported by the VideoCore. This is empty
+                /// if the property tag has not been processed or the response does not contain any trailing
+                /// word.
+                pub fn trailing(&self) -> &[u32] {
+                    let fixed = core::mem::size_of::<[<$name Data Response>]>() >> 2;
+                    self.response_words().get(fixed..).unwrap_or(&[])
+                }
+            }
+        }
+    };
+}
+
 /// Helper macro to conviniently define a [PropertyTag] structure
 ///
 /// # Examples
-/// ```no_run
+/// ```ignore
 /// # use ruspiro_mailbox::*;
 /// property_tag! {
 ///     ClockrateGet: {
@@ -196,7 +343,7 @@ macro_rules! property_tag_impl {
 /// ```
 /// If MAX(size_of(REQUEST), size_of(RESPONSE)) is not a multiple of size_of(u32) than padding need
 /// to be added to the property tag like so:
-/// ```
+/// ```ignore
 /// # use ruspiro_mailbox::property_tag;
 /// property_tag!(
 ///     BoardMacAddressGet: {
@@ -212,15 +359,29 @@ macro_rules! property_tag_impl {
 /// Padding is available with the following variants: ``PADDING: u8``, ``PADDING: [u8; x]`` or ``PADDING: u16``.
 ///
 /// The constructor ``new`` of the property tag contains all parameters of the tag request structure
-/// that need to be passed to create a valid property tag.
+/// that need to be passed to create a valid property tag. A request field could be given a default value
+/// like ``temperature_id: u32 = 0``. Such a field is not a parameter of the constructor but always
+/// initialized with the default value.
+///
+/// A response field that echoes an id could be declared like ``clock_id: u32 as ClockId``. The raw value is kept
+/// and the accessor of the field returns ``Result<ClockId, u32>`` using the ``TryFrom<u32>`` implementation of the
+/// id type.
+/// A response with a variable number of words following the fixed response fields could be declared with
+/// ``TRAILING: u32`` after the response. The accessor ``trailing`` returns these words sized by the response
+/// size the VideoCore reports in the state of the property tag. The words need to fit into the property tag data,
+/// so the request has to reserve the space, e.g. with a field like ``capacity: [u32; 8] = [0; 8]``.
+///
+/// Each property tag provides a ``snapshot`` function returning a copy of the response.
 /// The resulting [PropertyTag] can be used to be converted into a [MailboxMessage] and send to the
 /// mailbox or it can be added to a [MailboxBatch] to be send with additional [PropertyTag]s.
 macro_rules! property_tag {
-    ($(#[doc = $doc:expr])* $name:ident : { REQUEST: $req_fields:tt , RESPONSE: $rsp_fields:tt $(, PADDING:$type:ty)?}) => {
+    ($(#[doc = $doc:expr])* $name:ident : { REQUEST: $req_fields:tt , RESPONSE: $rsp_fields:tt $(, TRAILING: $trailing:ident)? $(, PADDING:$type:ty)?}) => {
         paste::item! {
             $(#[doc = $doc])*
+            // packing to 32 bits avoids any padding between the fields while the tag data stays aligned, so
+            // the references to the request and response are aligned as well
             #[allow(dead_code)]
-            #[repr(C, packed)]
+            #[repr(C, packed(4))]
             #[derive(Copy, Clone)]
             pub struct $name {
                 /// Property Tag Id. See [PropertyTagId]
@@ -240,6 +401,37 @@ macro_rules! property_tag {
 
             property_tag_data!([<$name Data>], $req_fields, $rsp_fields);
             property_tag_impl!($name, $req_fields $(,tagpadding:$type)?);
+            $(property_tag_trailing!($name, $trailing);)?
+
+            impl $name {
+                /// Copy the response out of the property tag. The copy does not borrow the property tag
+                /// or the message it has been send with, so it could be kept across further mailbox calls.
+                /// The same contract as for [PropertyTag::response] applies: the response is only valid
+                /// once the property tag has been processed by the VideoCore.
+                pub fn snapshot(&self) -> [<$name Data Response>] {
+                    unsafe { self.tagdata.response }
+                }
+            }
+
+            impl core::fmt::Debug for $name {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    // the fields of the packed structure are copied before they are formatted by reference
+                    let (tagid, tagsize, tagstate) = (self.tagid, self.tagsize, self.tagstate);
+                    f.debug_struct(stringify!($name))
+                        .field("tagid", &tagid)
+                        .field("tagsize", &tagsize)
+                        .field("tagstate", &tagstate)
+                        .field("data", &self.response_words())
+                        .finish()
+                }
+            }
+
+            /// Each property tag need to keep the wire layout expected by the VideoCore
+            #[cfg(test)]
+            #[test]
+            fn [<$name:snake _wire_layout>]() {
+                tests::assert_wire_layout::<$name>();
+            }
         }
     };
 }
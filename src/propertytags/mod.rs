@@ -33,14 +33,17 @@
 //! # }
 //! ```
 
-use crate::{ClockId, DeviceId, VoltageId};
+use crate::{ClockId, DeviceId, DomainId, Overscan, Size, VoltageId};
 
 #[macro_use]
 mod macros;
 
 /// Proprty tag ID's used for the different mailbox messages
 /// The same id's have to be used to define the property tag structures
-/// as the mailbox request message tag id will be automatically set
+/// as the mailbox request message tag id will be automatically set.
+///
+/// The mailbox property interface does not document a tag reporting memory or bus throughput counters,
+/// so there is no such property tag available.
 #[repr(u32)]
 #[allow(dead_code)]
 #[derive(Copy, Clone, Debug)]
@@ -59,6 +62,8 @@ pub enum PropertyTagId {
     ArmMemoryGet = 0x1_0005,
     /// Retrieve the MAC address
     BoardMACAddressGet = 0x1_0003,
+    /// Retrieve all clocks and their parents
+    ClocksGet = 0x1_0007,
     /// Retrieve VC/GPU memory base address and size
     VcMemoryGet = 0x1_0006,
     /// Retrieve all usable DMA channels
@@ -77,6 +82,8 @@ pub enum PropertyTagId {
     ClockrateSet = 0x3_8002,
     /// Get the max possible rate for a given clock ID
     MaxClockrateGet = 0x3_0004,
+    /// Get the actual measured rate for a given clock ID
+    MeasuredClockrateGet = 0x3_0047,
     /// Get the minimal possible rate for a given clock ID
     MinClockrateGet = 0x3_0007,
     /// Get the current voltage value for the given [VoltageId]
@@ -91,6 +98,28 @@ pub enum PropertyTagId {
     TemperatureGet = 0x3_0006,
     /// Retrieve the maximum safe temperature in thousandths of a degree Celsius
     MaxTemperatureGet = 0x3_000A,
+    /// Allocate contiguous memory on the VideoCore
+    MemoryAllocate = 0x3_000C,
+    /// Lock the allocated memory in place and retrieve it's bus address
+    MemoryLock = 0x3_000D,
+    /// Unlock the allocated memory, it might be moved by the VideoCore afterwards
+    MemoryUnlock = 0x3_000E,
+    /// Release the allocated memory
+    MemoryRelease = 0x3_000F,
+    /// Retrieve the throttling state and clear the sticky throttling flags
+    ThrottledGet = 0x3_0046,
+    /// Enable or disable the QPUs, this powers the V3D block
+    QpuEnable = 0x3_0012,
+    /// Retrieve the memory handle of a dispmanx resource
+    DispmanxResourceMemHandleGet = 0x3_0014,
+    /// Read a 128 byte block of the EDID of the attached display
+    EdidBlockGet = 0x3_0020,
+    /// Read rows of the customer OTP
+    CustomerOtpGet = 0x3_0021,
+    /// Retrieve the state of a power domain
+    DomainStateGet = 0x3_0030,
+    /// Set the state of a power domain
+    DomainStateSet = 0x3_8030,
     /// Allocate a frame buffer based on the size and pixel config given in a batch mailbox message
     FramebufferAllocate = 0x4_0001,
     /// Release and disable the frame buffer
@@ -102,6 +131,9 @@ pub enum PropertyTagId {
     /// Set the physical (display) size of the frame buffer (allocation will be made based on this size, even
     /// though there might be pitching applied to the requested width)
     PhysicalSizeSet = 0x4_8003,
+    /// Test the physical (display) size of the frame buffer. The response is the same as for [PhysicalSizeSet]
+    /// but nothing is actually applied
+    PhysicalSizeTest = 0x4_4003,
     /// Retrieve the virtual display size
     VirtualSizeGet = 0x4_0004,
     /// Set the virtual display size that has to be less than or equal to the physical size and is the
@@ -111,6 +143,8 @@ pub enum PropertyTagId {
     DepthGet = 0x4_0005,
     /// Set the actual pixel bit depth
     DepthSet = 0x4_8005,
+    /// Test the pixel bit depth. The response is the same as for [DepthSet] but the depth is not applied
+    DepthTest = 0x4_4005,
     /// Retrieve the current pixel color ordering
     PixelOrderGet = 0x4_0006,
     /// Set the pixel color ordering (RGB or BGR)
@@ -133,19 +167,20 @@ pub enum PropertyTagId {
     PaletteGet = 0x4_000B,
     /// Set/Update the palette color values
     PaletteSet = 0x4_800B,
+    /// Wait for the next vertical sync of the display
+    WaitForVsync = 0x4_800E,
     /// VideoCore Host Interface initialization
     VchiqInit = 0x4_8010,
+    /// Retrieve the number of attached displays
+    NumDisplaysGet = 0x4_0013,
+    /// Retrieve the command line passed to the kernel
+    CommandLineGet = 0x5_0001,
     /* not yet implemented property tags
-    ClocksGet = 0x1_0007,
     TimingGet = 0x2_0002,
 
     TurboGet = 0x3_0009,
     TurboSet = 0x3_8009,
 
-    MemoryAllocate = 0x3_000C,
-    MemoryLock = 0x3_000D,
-    MemoryUnlock = 0x3_000E,
-    MemoryRelease = 0x3_000F,
     ExecuteCode = 0x3_0010,
     */
 }
@@ -153,11 +188,23 @@ pub enum PropertyTagId {
 /// The trait that each PropertyTag need to implement. The most convinient way to define mailbox
 /// property tags is to use the corresponding macro. This will create the required
 /// structure and implement this trait accoridingly
-pub trait PropertyTag: Send {
+pub trait PropertyTag: Send + Sized {
     /// Type of the property tag request
     type Request;
     /// Type of the property tag response
     type Response;
+    /// The [PropertyTagId] all property tags of this type are using
+    const TAG_ID: PropertyTagId;
+    /// The size in bytes the property tag occupies in a message, including the tag id, size and state. This
+    /// allows to size a message buffer at compile time. A message consists of the 8 byte message header, the
+    /// property tags and the 4 byte end tag:
+    /// ```
+    /// # use ruspiro_mailbox::*;
+    /// const WORDS: usize = (12 + ClockrateGet::WIRE_SIZE + VoltageGet::WIRE_SIZE) >> 2;
+    /// let message = MailboxScratch::<WORDS>::new();
+    /// assert_eq!(message.as_slice().len(), 13);
+    /// ```
+    const WIRE_SIZE: usize = core::mem::size_of::<Self>();
     /// Return the [PropertyTagId] of this property tag
     fn tagid(&self) -> PropertyTagId;
     /// Return the current state of the property tag. This value is 0x0 for Requests and for a
@@ -169,6 +216,10 @@ pub trait PropertyTag: Send {
     fn response(&self) -> &Self::Response;
     /// Returns the size of the property tag as defined by it's structure definition
     fn size(&self) -> u32;
+    /// Return the raw words of the response. The words are bounded by the size of the value buffer of this
+    /// property tag and, once processed, by the size of the response. This allows to inspect the response
+    /// independent of it's decoding.
+    fn response_words(&self) -> &[u32];
 }
 
 property_tag!(
@@ -216,11 +267,25 @@ property_tag!(
 );
 
 property_tag!(
-    /// Retrieve the board serial number
+    /// Retrieve the board serial number. The firmware responds with a 64 bit serial in two words, the
+    /// lower 32 bits first. Both words are always returned, but the upper word may be 0.
     BoardSerialGet: {
         REQUEST: {},
         RESPONSE: {
-            board_serial: u32
+            board_serial: u32,
+            board_serial_high: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the clocks that exist. The response contains pairs of the parent clock id followed by the
+    /// clock id. A parent clock id of 0 indicates a clock without parent. Only the pairs covered by the
+    /// response size are valid.
+    ClocksGet: {
+        REQUEST: {},
+        RESPONSE: {
+            clocks: [u32; 32]
         }
     }
 );
@@ -271,7 +336,7 @@ property_tag!(
             device_id: DeviceId
         },
         RESPONSE: {
-            device_id: DeviceId,
+            device_id: u32 as DeviceId,
             state: u32
         }
     }
@@ -285,7 +350,34 @@ property_tag!(
             state: u32
         },
         RESPONSE: {
-            device_id: DeviceId,
+            device_id: u32 as DeviceId,
+            state: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the state of a power domain. Bit 0 of the state is set if the domain is powered on
+    DomainStateGet: {
+        REQUEST: {
+            domain_id: DomainId
+        },
+        RESPONSE: {
+            domain_id: u32 as DomainId,
+            state: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Set the state of a power domain. Bit 0 of the state powers the domain on
+    DomainStateSet: {
+        REQUEST: {
+            domain_id: DomainId,
+            state: u32
+        },
+        RESPONSE: {
+            domain_id: u32 as DomainId,
             state: u32
         }
     }
@@ -298,7 +390,7 @@ property_tag!(
             clock_id: ClockId
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
             state: u32
         }
     }
@@ -312,7 +404,7 @@ property_tag!(
             state: u32
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
             state: u32
         }
     }
@@ -326,7 +418,7 @@ property_tag! (
             clock_id: ClockId
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
             clock_rate: u32
         }
     }
@@ -342,7 +434,7 @@ property_tag! (
             skip_turbo: u32
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
             clock_rate: u32
         }
     }
@@ -355,7 +447,21 @@ property_tag!(
             clock_id: ClockId
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
+            clock_rate: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the rate in Hz a clock is actually running at as measured by the firmware. This might
+    /// differ from the rate set, e.g. if the clock is throttled.
+    MeasuredClockrateGet: {
+        REQUEST: {
+            clock_id: ClockId
+        },
+        RESPONSE: {
+            clock_id: u32 as ClockId,
             clock_rate: u32
         }
     }
@@ -368,7 +474,7 @@ property_tag!(
             clock_id: ClockId
         },
         RESPONSE: {
-            clock_id: ClockId,
+            clock_id: u32 as ClockId,
             clock_rate: u32
         }
     }
@@ -382,7 +488,7 @@ property_tag!(
             voltage_id: VoltageId
         },
         RESPONSE: {
-            voltage_id: VoltageId,
+            voltage_id: u32 as VoltageId,
             value: u32
         }
     }
@@ -397,7 +503,7 @@ property_tag!(
             value: u32
         },
         RESPONSE: {
-            voltage_id: VoltageId,
+            voltage_id: u32 as VoltageId,
             value: u32
         }
     }
@@ -411,7 +517,7 @@ property_tag!(
             voltage_id: VoltageId
         },
         RESPONSE: {
-            voltage_id: VoltageId,
+            voltage_id: u32 as VoltageId,
             value: u32
         }
     }
@@ -425,7 +531,7 @@ property_tag!(
             voltage_id: VoltageId
         },
         RESPONSE: {
-            voltage_id: VoltageId,
+            voltage_id: u32 as VoltageId,
             value: u32
         }
     }
@@ -433,10 +539,10 @@ property_tag!(
 
 property_tag!(
     /// Retrieve the current temperature in thousandths of a degree Celsius. The temperature id
-    /// should always be passed as 0
+    /// is always 0
     TemperatureGet: {
         REQUEST: {
-            temperature_id: u32
+            temperature_id: u32 = 0
         },
         RESPONSE: {
             temperature_id: u32,
@@ -447,10 +553,10 @@ property_tag!(
 
 property_tag!(
     /// Retrieve the maximum safe temperature in thousandths of a degree Celsius. The temperature id
-    /// should always be passed as 0. Above this temperature the CPU might turn off overclock.
+    /// is always 0. Above this temperature the CPU might turn off overclock.
     MaxTemperatureGet: {
         REQUEST: {
-            temperature_id: u32
+            temperature_id: u32 = 0
         },
         RESPONSE: {
             temperature_id: u32,
@@ -459,6 +565,102 @@ property_tag!(
     }
 );
 
+property_tag!(
+    /// Retrieve the throttling state. Bits 0..3 report the current state (under-voltage, ARM frequency
+    /// capped, currently throttled, soft temperature limit active) and bits 16..19 report whether the
+    /// corresponding state has occurred since the last reset. The sticky bits corresponding to the bits
+    /// 0..15 set in the ``clear_mask`` are cleared after the state has been read, so 0xFFFF clears all of
+    /// them.
+    ThrottledGet: {
+        REQUEST: {
+            clear_mask: u32
+        },
+        RESPONSE: {
+            state: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Enable (1) or disable (0) the QPUs. Enabling the QPUs powers up the V3D block. The status in the
+    /// response is 0 on success.
+    QpuEnable: {
+        REQUEST: {
+            enable: u32
+        },
+        RESPONSE: {
+            status: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Allocate ``size`` bytes of contiguous memory with the given byte ``alignment``. The ``flags`` select the
+    /// bus address alias and the caching of the memory. The response contains the handle of the memory, this
+    /// is 0 if the allocation failed.
+    MemoryAllocate: {
+        REQUEST: {
+            size: u32,
+            alignment: u32,
+            flags: u32
+        },
+        RESPONSE: {
+            handle: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Lock the allocated memory of the given handle in place. The response contains the bus address of the
+    /// memory, this is 0 if the handle is not valid.
+    MemoryLock: {
+        REQUEST: {
+            handle: u32
+        },
+        RESPONSE: {
+            bus_address: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Unlock the allocated memory of the given handle. The status in the response is 0 on success.
+    MemoryUnlock: {
+        REQUEST: {
+            handle: u32
+        },
+        RESPONSE: {
+            status: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Release the allocated memory of the given handle. The status in the response is 0 on success.
+    MemoryRelease: {
+        REQUEST: {
+            handle: u32
+        },
+        RESPONSE: {
+            status: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the memory handle of the given dispmanx resource. The status in the response is 0 if
+    /// the resource handle is valid.
+    DispmanxResourceMemHandleGet: {
+        REQUEST: {
+            resource_handle: u32
+        },
+        RESPONSE: {
+            status: u32,
+            mem_handle: u32
+        }
+    }
+);
+
 property_tag!(
     /// Allocate a frame buffer with the given byte alignment
     FramebufferAllocate: {
@@ -481,6 +683,8 @@ property_tag!(
 );
 
 property_tag!(
+    /// Blank the screen. The state is 1 to blank the screen and 0 to unblank it. The content of the
+    /// framebuffer is not changed.
     BlankScreen: {
         REQUEST: {
             state: u32
@@ -520,6 +724,22 @@ property_tag!(
     }
 );
 
+property_tag!(
+    /// Test the physical display/framebuffer size. The response is the same as it would be for
+    /// [PhysicalSizeSet] but the size is not actually applied. This allows to probe for supported
+    /// sizes without changing the current framebuffer configuration.
+    PhysicalSizeTest: {
+        REQUEST: {
+            width: u32,
+            height: u32
+        },
+        RESPONSE: {
+            width: u32,
+            height: u32
+        }
+    }
+);
+
 property_tag!(
     /// Retreive the virtual display/framebuffer size. This is actually the size of the buffer passed to
     /// the monitor and might be only a part of the allocated frame buffer
@@ -549,6 +769,44 @@ property_tag!(
     }
 );
 
+/// Implement the typed [Size] accessor for the responses of the size property tags
+macro_rules! size_response {
+    ($($name:ident),*) => {
+        $(
+            impl $name {
+                /// The width and height as typed [Size]
+                pub fn size(&self) -> Size {
+                    Size::new(self.width(), self.height())
+                }
+            }
+        )*
+    };
+}
+
+size_response!(
+    PhysicalSizeGetDataResponse,
+    PhysicalSizeSetDataResponse,
+    PhysicalSizeTestDataResponse,
+    VirtualSizeGetDataResponse,
+    VirtualSizeSetDataResponse
+);
+
+/// Implement the constructor from a typed [Size] for the size property tags
+macro_rules! size_request {
+    ($($name:ident),*) => {
+        $(
+            impl $name {
+                /// Create the property tag from the typed [Size]
+                pub fn from_size(size: Size) -> Self {
+                    Self::new(size.width, size.height)
+                }
+            }
+        )*
+    };
+}
+
+size_request!(PhysicalSizeSet, PhysicalSizeTest, VirtualSizeSet);
+
 property_tag!(
     /// Retrieve the bits per pixel used for depth information
     DepthGet: {
@@ -572,6 +830,19 @@ property_tag!(
     }
 );
 
+property_tag!(
+    /// Test the bits per pixel used for depth information. The response is the same as it would be for
+    /// [DepthSet], but the depth is not applied.
+    DepthTest: {
+        REQUEST: {
+            depth: u32
+        },
+        RESPONSE: {
+            depth: u32
+        }
+    }
+);
+
 property_tag!(
     /// Retrieve the current pixel order. The returned value is:<br>
     /// 0x0 - BGR<br>
@@ -693,6 +964,82 @@ property_tag!(
     }
 );
 
+impl BoardSerialGetDataResponse {
+    /// The full 64 bit serial number combined from both words of the response
+    pub fn serial(&self) -> u64 {
+        (self.board_serial_high as u64) << 32 | self.board_serial as u64
+    }
+
+    /// The bytes of the serial number with the most significant byte first. This is the order the serial
+    /// is printed in, e.g. in ``/proc/cpuinfo`` of Linux.
+    pub fn serial_bytes(&self) -> [u8; 8] {
+        self.serial().to_be_bytes()
+    }
+}
+
+impl core::fmt::Display for BoardSerialGetDataResponse {
+    /// Format the serial number as 16 lower case hex digits
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:016x}", self.serial())
+    }
+}
+
+impl OverscanGetDataResponse {
+    /// The overscan values as typed [Overscan]
+    pub fn overscan(&self) -> Overscan {
+        Overscan {
+            top: self.top(),
+            bottom: self.bottom(),
+            left: self.left(),
+            right: self.right(),
+        }
+    }
+}
+
+impl OverscanSet {
+    /// Create the property tag from the typed [Overscan] values
+    pub fn from_overscan(overscan: Overscan) -> Self {
+        Self::new(overscan.top, overscan.bottom, overscan.left, overscan.right)
+    }
+}
+
+impl OverscanSetDataResponse {
+    /// The overscan values applied as typed [Overscan]
+    pub fn overscan(&self) -> Overscan {
+        Overscan {
+            top: self.top(),
+            bottom: self.bottom(),
+            left: self.left(),
+            right: self.right(),
+        }
+    }
+}
+
+property_tag!(
+    /// Wait for the next vertical sync of the display. The VideoCore responds once the vertical sync has
+    /// happened. This tag is not part of the documented mailbox property interface but used by the Linux
+    /// framebuffer driver, firmware that does not know it rejects it.
+    WaitForVsync: {
+        REQUEST: {
+            reserved: u32 = 0
+        },
+        RESPONSE: {
+            reserved: u32
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the number of displays attached to the VideoCore, e.g. two on a Raspberry Pi 4 with both HDMI
+    /// ports connected. Older firmware does not know this tag.
+    NumDisplaysGet: {
+        REQUEST: {},
+        RESPONSE: {
+            num_displays: u32
+        }
+    }
+);
+
 property_tag!(
     /// Retrieve the current palette color entries
     PaletteGet: {
@@ -705,7 +1052,8 @@ property_tag!(
 
 property_tag!(
     /// Set/update the palette entries. As the palette buffer given is always a fixed sized array the
-    /// offset need to be 0 and the length 256 and all palette colors need to be passed.
+    /// offset need to be 0 and the length 256 and all palette colors need to be passed. To update only
+    /// some of the entries use [Mailbox::set_palette_range](crate::Mailbox::set_palette_range).
     PaletteSet: {
         REQUEST: {
             offset: u32,
@@ -729,3 +1077,472 @@ property_tag!(
         }
     }
 );
+
+property_tag!(
+    /// Read the 128 byte block with the given number of the EDID of the attached display. A status other than 0
+    /// indicates the block could not be read.
+    EdidBlockGet: {
+        REQUEST: {
+            block_number: u32
+        },
+        RESPONSE: {
+            block_number: u32,
+            status: u32,
+            edid: [u8; 128]
+        }
+    }
+);
+
+property_tag!(
+    /// Read ``count`` rows of the customer OTP starting with the row ``start``. The customer OTP consists of 8
+    /// rows, the values of the rows read are returned in the first ``count`` entries of ``values``.
+    CustomerOtpGet: {
+        REQUEST: {
+            start: u32,
+            count: u32
+        },
+        RESPONSE: {
+            start: u32,
+            count: u32,
+            values: [u32; 8]
+        }
+    }
+);
+
+property_tag!(
+    /// Retrieve the command line passed to the kernel as ASCII characters. The size of the response state
+    /// contains the length of the command line. A command line longer than the response buffer is truncated.
+    CommandLineGet: {
+        REQUEST: {},
+        RESPONSE: {
+            command_line: [u8; 1024]
+        }
+    }
+);
+
+// the property tags with an id as request field could also be created from a raw id, e.g. read from a
+// configuration, that is validated first
+property_tag_try_new_raw!(DeviceId: PowerStateGet, PowerStateSet(state: u32));
+property_tag_try_new_raw!(DomainId: DomainStateGet, DomainStateSet(state: u32));
+property_tag_try_new_raw!(
+    ClockId: ClockStateGet,
+    ClockStateSet(state: u32),
+    ClockrateGet,
+    ClockrateSet(clock_rate: u32, skip_turbo: u32),
+    MaxClockrateGet,
+    MeasuredClockrateGet,
+    MinClockrateGet,
+);
+property_tag_try_new_raw!(
+    VoltageId: VoltageGet,
+    VoltageSet(value: u32),
+    MaxVoltageGet,
+    MinVoltageGet,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, Mailbox, MailboxBatch, MailboxError};
+
+    /// Verify the wire layout of the property tag. The whole tag need to be a multiple of 32 bits in size,
+    /// otherwise padding is missing, and the request and the response need to fit into the value buffer
+    /// following the tag id, size and state.
+    pub(super) fn assert_wire_layout<T: PropertyTag>() {
+        let size = core::mem::size_of::<T>();
+        assert_eq!(size % 4, 0, "property tag size not a multiple of 32 bits");
+        assert!(core::mem::size_of::<T::Request>() <= size - 12);
+        assert!(core::mem::size_of::<T::Response>() <= size - 12);
+    }
+
+    #[test]
+    fn dispmanx_mem_handle_layout() {
+        let tag = DispmanxResourceMemHandleGet::new(0x1234);
+        assert_eq!(core::mem::size_of::<DispmanxResourceMemHandleGet>(), 12 + 8);
+
+        let words = unsafe {
+            core::slice::from_raw_parts(
+                &tag as *const DispmanxResourceMemHandleGet as *const u32,
+                5,
+            )
+        };
+        assert_eq!(words, &[0x3_0014, 8, 0x0, 0x1234, 0x0]);
+
+        // the response overlays the request starting with the status
+        let response = tag.response();
+        assert_eq!(response.status(), 0x1234);
+        assert_eq!(response.mem_handle(), 0x0);
+    }
+
+    #[test]
+    fn size_round_trip() {
+        let size = Size::new(1024, 768);
+        // the response overlays the request, so the values are read back unchanged
+        assert_eq!(PhysicalSizeSet::from_size(size).response().size(), size);
+        assert_eq!(PhysicalSizeTest::from_size(size).response().size(), size);
+        let tag = VirtualSizeSet::from_size(size);
+        assert_eq!(tag.response().size(), size);
+        assert_eq!(tag.response_words(), &[1024, 768]);
+    }
+
+    #[test]
+    fn overscan_round_trip() {
+        let overscan = Overscan {
+            top: 16,
+            bottom: 24,
+            left: 32,
+            right: 48,
+        };
+        let tag = OverscanSet::from_overscan(overscan);
+        // the response overlays the request, so the values are read back unchanged
+        assert_eq!(tag.response().overscan(), overscan);
+        assert_eq!(tag.response_words(), &[16, 24, 32, 48]);
+    }
+
+    #[test]
+    fn board_serial_byte_order() {
+        let response = BoardSerialGetDataResponse {
+            board_serial: 0x2a5e_f2c8,
+            board_serial_high: 0x1000_0000,
+        };
+        assert_eq!(response.serial(), 0x1000_0000_2a5e_f2c8);
+        assert_eq!(
+            response.serial_bytes(),
+            [0x10, 0x00, 0x00, 0x00, 0x2a, 0x5e, 0xf2, 0xc8]
+        );
+        assert_eq!(format!("{}", response), "100000002a5ef2c8");
+    }
+
+    // the wire size is available at compile time
+    const _CLOCKRATE_GET_WIRE_SIZE: [(); core::mem::size_of::<ClockrateGet>()] =
+        [(); ClockrateGet::WIRE_SIZE];
+    const _PALETTE_SET_WIRE_SIZE: [(); core::mem::size_of::<PaletteSet>()] =
+        [(); PaletteSet::WIRE_SIZE];
+
+    #[test]
+    fn wire_size_of_tags() {
+        assert_eq!(ClockrateGet::WIRE_SIZE, 12 + 8);
+        assert_eq!(BoardMACAddressGet::WIRE_SIZE, 12 + 8);
+        assert_eq!(PaletteSet::WIRE_SIZE, 12 + 8 + 1024);
+    }
+
+    #[test]
+    fn empty_request_and_response() {
+        let tag = FramebufferRelease::new();
+        assert_eq!(core::mem::size_of::<FramebufferReleaseDataRequest>(), 0);
+        assert_eq!(core::mem::size_of::<FramebufferReleaseDataResponse>(), 0);
+        // the tag only consists of the tag id, the size of the value buffer and the state
+        assert_eq!(FramebufferRelease::WIRE_SIZE, 12);
+        assert_eq!(tag.size(), 0);
+        let words = unsafe {
+            core::slice::from_raw_parts(&tag as *const FramebufferRelease as *const u32, 3)
+        };
+        assert_eq!(words, &[0x4_8001, 0, 0x0]);
+        assert!(tag.response_words().is_empty());
+    }
+
+    #[test]
+    fn num_displays_layout() {
+        let tag = NumDisplaysGet::new();
+        let words =
+            unsafe { core::slice::from_raw_parts(&tag as *const NumDisplaysGet as *const u32, 4) };
+        assert_eq!(words, &[0x4_0013, 4, 0x0, 0x0]);
+    }
+
+    #[test]
+    fn array_response_by_reference() {
+        let mut tag = PaletteGet::new();
+        // simulate the response written by the VideoCore
+        unsafe { tag.tagdata.response.palette[255] = 0x00FF_00FF };
+        let palette = tag.response().palette_ref();
+        // the reference points into the response buffer of the tag
+        assert_eq!(palette.as_ptr(), tag.response_words().as_ptr());
+        assert_eq!(palette[255], 0x00FF_00FF);
+    }
+
+    #[allow(dead_code)]
+    mod trailing {
+        use super::*;
+
+        // the values of the OTP rows declared as trailing words, the request reserves the space for 4 rows
+        property_tag!(
+            CustomerOtpGet: {
+                REQUEST: {
+                    start: u32,
+                    count: u32,
+                    capacity: [u32; 4] = [0; 4]
+                },
+                RESPONSE: {
+                    start: u32,
+                    count: u32
+                },
+                TRAILING: u32
+            }
+        );
+
+        #[test]
+        fn trailing_words_bound_by_state() {
+            let mut tag = CustomerOtpGet::new(0, 2);
+            // not processed yet, the trailing words span the whole data
+            assert_eq!(tag.trailing(), &[0; 4]);
+
+            tag.tagdata.request.capacity = [0x11, 0x22, 0x33, 0x44];
+            // the VideoCore reports the fixed fields and 2 rows
+            tag.tagstate = 0x8000_0000 | 16;
+            assert_eq!(tag.response().count(), 2);
+            assert_eq!(tag.trailing(), &[0x11, 0x22]);
+
+            // no trailing word reported
+            tag.tagstate = 0x8000_0000 | 8;
+            assert!(tag.trailing().is_empty());
+
+            // a larger response is truncated to the data of the property tag
+            tag.tagstate = 0x8000_0000 | 64;
+            assert_eq!(tag.trailing(), &[0x11, 0x22, 0x33, 0x44]);
+        }
+    }
+
+    #[test]
+    fn format_tag_fields() {
+        // the tags are packed but keep the alignment of the tag data
+        assert_eq!(core::mem::align_of::<ClockrateGet>(), 4);
+        let tag = ClockrateGet::new(ClockId::Arm);
+        assert_eq!(
+            format!("{:?}", tag),
+            "ClockrateGet { tagid: ClockrateGet, tagsize: 8, tagstate: 0, data: [3, 0] }"
+        );
+        let response = tag.response();
+        assert_eq!(
+            format!("{:?} {}", response.clock_id(), response.clock_rate()),
+            "Ok(Arm) 0"
+        );
+
+        // a tag with padding
+        let tag = BoardMACAddressGet::new();
+        assert_eq!(
+            format!("{:?}", tag),
+            "BoardMACAddressGet { tagid: BoardMACAddressGet, tagsize: 6, tagstate: 0, data: [0, 0] }"
+        );
+        assert_eq!(
+            format!("{:?}", tag.response().mac_address_ref()),
+            "[0, 0, 0, 0, 0, 0]"
+        );
+    }
+
+    #[test]
+    fn defaulted_request_field() {
+        // the temperature id has a default and is not a parameter of the constructor
+        let tag = TemperatureGet::new();
+        assert_eq!(tag.tagid() as u32, PropertyTagId::TemperatureGet as u32);
+        assert_eq!(tag.size(), 8);
+        assert_eq!(unsafe { tag.tagdata.request.temperature_id }, 0);
+        assert_eq!(tag.response_words(), &[0x0, 0x0]);
+    }
+
+    #[test]
+    fn try_new_from_raw_id() {
+        let tag = ClockrateSet::try_new_raw(0x3, 1_200_000_000, 0).unwrap();
+        assert_eq!(tag.response().clock_id(), Ok(ClockId::Arm));
+        assert_eq!(tag.response().clock_rate(), 1_200_000_000);
+        assert!(matches!(
+            VoltageGet::try_new_raw(0x1)
+                .unwrap()
+                .response()
+                .voltage_id(),
+            Ok(VoltageId::Core)
+        ));
+
+        assert_eq!(ClockrateGet::try_new_raw(0x42).err(), Some(0x42));
+        assert_eq!(PowerStateSet::try_new_raw(0x9, 1).err(), Some(0x9));
+        assert_eq!(DomainStateGet::try_new_raw(0).err(), Some(0));
+        assert_eq!(DomainStateGet::try_new_raw(24).err(), Some(24));
+    }
+
+    #[test]
+    fn response_id_is_checked() {
+        let mut tag = ClockrateGet::new(ClockId::Arm);
+        // the request is overlaid by the response and the echoed id is valid
+        assert_eq!(tag.response().clock_id(), Ok(ClockId::Arm));
+
+        // simulate the VideoCore echoing an id unknown to this crate
+        let mut response = *tag.response();
+        response.clock_id = 0x42;
+        tag.tagdata.response = response;
+        assert_eq!(tag.response().clock_id(), Err(0x42));
+    }
+
+    #[test]
+    fn raw_response_words() {
+        let tag = BoardSerialGet::new();
+        // the response is larger than the request but initialized
+        assert_eq!(tag.response_words(), &[0x0, 0x0]);
+
+        mock::Firmware::new()
+            .on(PropertyTagId::BoardMACAddressGet, |values| {
+                values[..2].copy_from_slice(&[0x4433_2211, 0x6655]);
+                Some(6)
+            })
+            .install();
+        let batch = MailboxBatch::empty().with_tag(BoardMACAddressGet::new());
+        let batch = Mailbox::new().send_batch(batch).unwrap();
+        assert_eq!(
+            batch.get_tag::<BoardMACAddressGet, _>().response_words(),
+            &[0x4433_2211, 0x6655]
+        );
+    }
+
+    /// a hand written property tag that misses the padding to a multiple of 32 bits. It is packed to 16 bits, so
+    /// the value is still aligned and could be referenced
+    #[repr(C, packed(2))]
+    struct MisSizedTag {
+        tagid: PropertyTagId,
+        tagsize: u32,
+        tagstate: u32,
+        value: u16,
+    }
+
+    impl PropertyTag for MisSizedTag {
+        type Request = u16;
+        type Response = u16;
+        const TAG_ID: PropertyTagId = PropertyTagId::ClockrateGet;
+
+        fn tagid(&self) -> PropertyTagId {
+            self.tagid
+        }
+
+        fn state(&self) -> u32 {
+            self.tagstate
+        }
+
+        fn response(&self) -> &Self::Response {
+            &self.value
+        }
+
+        fn size(&self) -> u32 {
+            self.tagsize
+        }
+
+        fn response_words(&self) -> &[u32] {
+            &[]
+        }
+    }
+
+    /// a hand written property tag reading the current and the maximum temperature
+    #[repr(C, packed(4))]
+    struct TemperatureRangeTag {
+        tagid: PropertyTagId,
+        tagsize: u32,
+        tagstate: u32,
+        values: [u32; 2],
+    }
+
+    impl PropertyTag for TemperatureRangeTag {
+        type Request = [u32; 2];
+        type Response = [u32; 2];
+        const TAG_ID: PropertyTagId = PropertyTagId::TemperatureGet;
+
+        fn tagid(&self) -> PropertyTagId {
+            self.tagid
+        }
+
+        fn state(&self) -> u32 {
+            self.tagstate
+        }
+
+        fn response(&self) -> &Self::Response {
+            &self.values
+        }
+
+        fn size(&self) -> u32 {
+            self.tagsize
+        }
+
+        fn response_words(&self) -> &[u32] {
+            self.response()
+        }
+    }
+
+    #[test]
+    fn query_custom_tag() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::TemperatureGet, &[45_000, 85_000])
+            .install();
+        let tag = TemperatureRangeTag {
+            tagid: PropertyTagId::TemperatureGet,
+            tagsize: 8,
+            tagstate: 0,
+            values: [0; 2],
+        };
+        let mut mb = Mailbox::new();
+        assert_eq!(mb.query(tag, |&[current, max]| max - current), Ok(40_000));
+    }
+
+    #[test]
+    fn reject_mis_sized_tag() {
+        let exchanged = std::rc::Rc::new(std::cell::Cell::new(false));
+        let flag = exchanged.clone();
+        mock::install_raw(move |_, _| {
+            flag.set(true);
+            Ok(())
+        });
+        let tag = MisSizedTag {
+            tagid: PropertyTagId::ClockrateGet,
+            tagsize: 2,
+            tagstate: 0,
+            value: 0,
+        };
+        let mb = Mailbox::new();
+        assert_eq!(mb.send_tag(tag).err(), Some(MailboxError::BadTagSize));
+        assert!(!exchanged.get());
+    }
+
+    #[test]
+    fn snapshot_outlives_message() {
+        mock::Firmware::new()
+            .on(PropertyTagId::ClockrateGet, |values| {
+                values[1] = 250_000_000 + values[0];
+                Some(8)
+            })
+            .install();
+        let mb = Mailbox::new();
+        let core = mb
+            .send_tag(ClockrateGet::new(ClockId::Core))
+            .unwrap()
+            .snapshot();
+        // the snapshot does not borrow the tag it has been taken from
+        let arm = mb.send_tag(ClockrateGet::new(ClockId::Arm)).unwrap();
+        assert_eq!(core.clock_id(), Ok(ClockId::Core));
+        assert_eq!(core.clock_rate(), 250_000_000 + ClockId::Core as u32);
+        assert_eq!(
+            arm.response().clock_rate(),
+            250_000_000 + ClockId::Arm as u32
+        );
+    }
+
+    #[test]
+    fn send_tag_without_values() {
+        mock::Firmware::new()
+            .on(PropertyTagId::FramebufferRelease, |values| {
+                if values.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                }
+            })
+            .install();
+        let mb = Mailbox::new();
+        let message = mb.send_message(FramebufferRelease::new().into()).unwrap();
+        let tag = message.into_tag();
+        assert_eq!(tag.size(), 0);
+        assert_eq!(tag.state(), 0x8000_0000);
+        assert!(tag.response_words().is_empty());
+
+        // a firmware that does not process the tag responds with an error
+        mock::Firmware::new().install();
+        assert_eq!(
+            mb.send_message(FramebufferRelease::new().into())
+                .map(|_| ()),
+            Err(MailboxError::ResponseError)
+        );
+    }
+}
@@ -0,0 +1,229 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Retry Policy
+//!
+//! A mailbox that does not respond in time typically indicates a busy or wedged VideoCore, while a message
+//! responded with an error has been rejected by the firmware. A [RetryPolicy] allows to retry both cases with
+//! a different number of attempts and to wait between the attempts.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! fn doc() {
+//!     let mut mb = Mailbox::new();
+//!     let mut backoff = |attempt: u32| {
+//!         for _ in 0..attempt * 1000 {
+//!             core::hint::spin_loop();
+//!         }
+//!     };
+//!     let policy = RetryPolicy::new(5, 2).with_backoff(&mut backoff);
+//!     let clock_rate = mb
+//!         .send_with_policy(ClockrateGet::new(ClockId::Core), policy)
+//!         .unwrap()
+//!         .response()
+//!         .clock_rate();
+//! }
+//! ```
+//!
+
+use crate::{MailboxError, MailboxResult};
+
+/// The policy to retry a property tag that failed with [MailboxError::Timeout] or
+/// [MailboxError::ResponseError]. Any other error is returned immediately.
+pub struct RetryPolicy<'a> {
+    /// The maximum number of attempts that may time out
+    timeout_attempts: u32,
+    /// The maximum number of attempts that may be responded with an error
+    error_attempts: u32,
+    /// Called between the attempts with the number of attempts made so far
+    backoff: Option<&'a mut dyn FnMut(u32)>,
+}
+
+impl<'a> RetryPolicy<'a> {
+    /// Create a policy that gives up once ``timeout_attempts`` attempts have timed out or ``error_attempts``
+    /// attempts have been responded with an error. An attempt count of 0 or 1 disables the retry of the
+    /// respective error.
+    pub const fn new(timeout_attempts: u32, error_attempts: u32) -> Self {
+        Self {
+            timeout_attempts,
+            error_attempts,
+            backoff: None,
+        }
+    }
+
+    /// Call the given closure between the attempts, e.g. to wait for the VideoCore to recover. The closure
+    /// get's the number of attempts made so far.
+    pub fn with_backoff(mut self, backoff: &'a mut dyn FnMut(u32)) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+
+    /// Run the given attempt until it succeeds or the policy gives up. Returns the result of the last attempt.
+    pub(crate) fn run<T, F>(mut self, mut attempt: F) -> MailboxResult<T>
+    where
+        F: FnMut() -> MailboxResult<T>,
+    {
+        let mut timeouts = 0;
+        let mut errors = 0;
+        loop {
+            match attempt() {
+                Err(MailboxError::Timeout) => {
+                    timeouts += 1;
+                    if timeouts >= self.timeout_attempts {
+                        return Err(MailboxError::Timeout);
+                    }
+                }
+                Err(MailboxError::ResponseError) => {
+                    errors += 1;
+                    if errors >= self.error_attempts {
+                        return Err(MailboxError::ResponseError);
+                    }
+                }
+                result => return result,
+            }
+            if let Some(backoff) = self.backoff.as_mut() {
+                backoff(timeouts + errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, ClockId, ClockrateGet, Mailbox, PropertyTag};
+
+    #[test]
+    fn separate_attempts_per_error() {
+        let mut outcomes = vec![
+            Err(MailboxError::Timeout),
+            Err(MailboxError::ResponseError),
+            Err(MailboxError::Timeout),
+            Err(MailboxError::Timeout),
+        ]
+        .into_iter();
+        assert_eq!(
+            RetryPolicy::new(3, 2).run(|| outcomes.next().unwrap_or(Ok(()))),
+            Err(MailboxError::Timeout)
+        );
+        assert_eq!(outcomes.len(), 0);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let mut attempts = 0;
+        assert_eq!(
+            RetryPolicy::new(5, 5).run(|| -> MailboxResult<()> {
+                attempts += 1;
+                Err(MailboxError::BatchTooLarge)
+            }),
+            Err(MailboxError::BatchTooLarge)
+        );
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn backoff_between_attempts() {
+        let mut waits = Vec::new();
+        let mut backoff = |attempt| waits.push(attempt);
+        let mut outcomes =
+            vec![Err(MailboxError::Timeout), Err(MailboxError::ResponseError)].into_iter();
+        assert_eq!(
+            RetryPolicy::new(2, 2)
+                .with_backoff(&mut backoff)
+                .run(|| outcomes.next().unwrap_or(Ok(7))),
+            Ok(7)
+        );
+        assert_eq!(waits, [1, 2]);
+    }
+
+    #[test]
+    fn retry_with_policy() {
+        // the mailbox is empty when drained after the timeout
+        mock::set_register(crate::interface::PERIPHERAL_BASE + 0xB898, 0x4000_0000);
+        // the first exchange times out, the second is rejected and the third succeeds
+        let mut exchanges = 0;
+        mock::install_raw(move |_, buffer| {
+            exchanges += 1;
+            match exchanges {
+                1 => Err(MailboxError::Timeout),
+                2 => {
+                    mock::process(buffer, &mut |_, _| None);
+                    Ok(())
+                }
+                _ => {
+                    mock::process(buffer, &mut |_, values: &mut [u32]| {
+                        values[1] = 250_000_000;
+                        Some(8)
+                    });
+                    Ok(())
+                }
+            }
+        });
+        let mut waits = Vec::new();
+        let mut backoff = |attempt| waits.push(attempt);
+        let mut mb = Mailbox::new();
+        let tag = mb
+            .send_with_policy(
+                ClockrateGet::new(ClockId::Core),
+                RetryPolicy::new(2, 2).with_backoff(&mut backoff),
+            )
+            .unwrap();
+        assert_eq!(tag.response().clock_rate(), 250_000_000);
+        assert_eq!(waits, [1, 2]);
+
+        // a single error response is not retried if the policy only retries timeouts
+        let mut exchanges = 0;
+        mock::install_raw(move |_, buffer| {
+            exchanges += 1;
+            mock::process(buffer, &mut |_, _| {
+                if exchanges > 1 {
+                    Some(8)
+                } else {
+                    None
+                }
+            });
+            Ok(())
+        });
+        assert_eq!(
+            mb.send_with_policy(ClockrateGet::new(ClockId::Core), RetryPolicy::new(5, 1))
+                .map(|_| ()),
+            Err(MailboxError::ResponseError)
+        );
+    }
+
+    #[test]
+    fn retry_after_timeout_drains_mailbox() {
+        let mut mb = Mailbox::builder()
+            .peripheral_base(0xFE00_0000)
+            .timeout_iterations(10)
+            .build();
+        // a late response is pending once the first attempt has timed out, the status is read by the drain
+        // and the poll
+        mock::queue_register(0xFE00_B898, &[0x0, 0x0]);
+        mock::set_register(0xFE00_B898, 0x4000_0000);
+        mock::set_register(0xFE00_B880, 0x0010_0008);
+        let mut exchanges = 0;
+        mock::install_raw(move |_, buffer| {
+            exchanges += 1;
+            if exchanges == 1 {
+                return Err(MailboxError::Timeout);
+            }
+            // the stale response has been read before the retry is passed to the VideoCore
+            assert_eq!(mock::register_reads(0xFE00_B880), 1);
+            mock::process(buffer, &mut |_, _| Some(8));
+            Ok(())
+        });
+        assert!(mb
+            .send_with_policy(ClockrateGet::new(ClockId::Core), RetryPolicy::new(2, 1))
+            .is_ok());
+        assert_eq!(mock::register_reads(0xFE00_B880), 1);
+    }
+}
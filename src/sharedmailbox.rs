@@ -0,0 +1,158 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Shared Mailbox
+//!
+//! The [Mailbox] requires exclusive access for each message passed to the VideoCore. To share one [Mailbox]
+//! between several cores it is wrapped into a [SharedMailbox] that guards the access with a mutex.
+//!
+//! # Usage
+//!
+//! ```no_run
+//! use ruspiro_mailbox::*;
+//!
+//! static MAILBOX: SharedMailbox = SharedMailbox::new();
+//!
+//! fn doc() {
+//!     // the mailbox is locked until the response has been received
+//!     let temperature = MAILBOX.lock().get_temperature().unwrap();
+//! }
+//! ```
+
+use crate::{Mailbox, MailboxError, MailboxResult};
+use ruspiro_lock::{Mutex, MutexGuard};
+
+/// A [Mailbox] that could be shared between several cores
+pub struct SharedMailbox {
+    inner: Mutex<Mailbox>,
+}
+
+impl SharedMailbox {
+    /// Create a new [SharedMailbox]. As there is only one mailbox peripheral this should only be created
+    /// once, for example as a ``static``.
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Mailbox::new()),
+        }
+    }
+
+    /// Lock the [Mailbox] for exclusive access. All [Mailbox] methods are available through the returned
+    /// guard. The lock is held until the guard is dropped, so a single method call on the guard keeps the
+    /// lock for the whole exchange of the request and the response with the VideoCore.
+    pub fn lock(&self) -> MutexGuard<'_, Mailbox> {
+        self.inner.lock()
+    }
+
+    /// Lock the [Mailbox] for exclusive access while running the given function. This keeps the lock across
+    /// several messages that need to be passed without any other message in between.
+    pub fn with_mailbox<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Mailbox) -> R,
+    {
+        f(&mut self.lock())
+    }
+
+    /// Run the given function with the [Mailbox] if it is not locked by someone else. Instead of waiting for
+    /// the lock this returns [MailboxError::Busy] immediately, so it could be used in an interrupt handler that
+    /// must not block. The interrupt handler need to cope with ``Busy``, e.g. by deferring the message, as the
+    /// code it has interrupted might hold the lock and could not continue until the handler has returned.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use ruspiro_mailbox::*;
+    /// static MAILBOX: SharedMailbox = SharedMailbox::new();
+    ///
+    /// fn irq_handler() {
+    ///     match MAILBOX.try_exchange(|mb| mb.get_temperature()) {
+    ///         Ok(temperature) => println!("temperature: {}", temperature),
+    ///         // the interrupted code is using the mailbox, try again with the next interrupt
+    ///         Err(MailboxError::Busy) => (),
+    ///         Err(error) => println!("error: {}", error),
+    ///     }
+    /// }
+    /// ```
+    pub fn try_exchange<F, R>(&self, f: F) -> MailboxResult<R>
+    where
+        F: FnOnce(&mut Mailbox) -> MailboxResult<R>,
+    {
+        match self.inner.try_lock() {
+            Some(mut mailbox) => f(&mut mailbox),
+            None => Err(MailboxError::Busy),
+        }
+    }
+}
+
+impl Default for SharedMailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, PropertyTagId};
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    static MAILBOX: SharedMailbox = SharedMailbox::new();
+
+    #[test]
+    fn try_exchange_while_locked() {
+        let shared = SharedMailbox::new();
+        mock::Firmware::new()
+            .respond(PropertyTagId::TemperatureGet, &[0x0, 42_000])
+            .install();
+        {
+            // simulate the interrupted code holding the lock
+            let _mailbox = shared.lock();
+            assert_eq!(
+                shared.try_exchange(|mb| mb.get_temperature()),
+                Err(MailboxError::Busy)
+            );
+        }
+        assert_eq!(shared.try_exchange(|mb| mb.get_temperature()), Ok(42_000));
+    }
+
+    #[test]
+    fn shared_mailbox_serializes_callers() {
+        let events = Arc::new(StdMutex::new(Vec::new()));
+        let callers: Vec<_> = (0..2)
+            .map(|caller| {
+                let events = events.clone();
+                std::thread::spawn(move || {
+                    // the firmware emulation is kept per thread
+                    mock::install(move |tag, values| {
+                        events.lock().unwrap().push((caller, true));
+                        std::thread::sleep(std::time::Duration::from_millis(1));
+                        events.lock().unwrap().push((caller, false));
+                        if tag == PropertyTagId::FirmwareRevisionGet as u32 {
+                            values[0] = caller as u32;
+                            Some(4)
+                        } else {
+                            None
+                        }
+                    });
+                    for _ in 0..10 {
+                        assert_eq!(MAILBOX.lock().get_firmware_revision(), Ok(caller as u32));
+                    }
+                })
+            })
+            .collect();
+        for caller in callers {
+            caller.join().unwrap();
+        }
+
+        // each exchange is completed before the next one starts
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 40);
+        for exchange in events.chunks(2) {
+            assert_eq!(exchange[0].0, exchange[1].0);
+            assert!(exchange[0].1 && !exchange[1].1);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2020 by the authors
+ *
+ * Author: André Borrmann <pspwizard@gmx.de>
+ * License: Apache License 2.0 / MIT
+ **********************************************************************************************************************/
+
+//! # Units
+//!
+//! Typed representations of the values reported by the mailbox property tag interface.
+//!
+
+use core::fmt;
+
+/// A frequency in Hz as used for the clock rates
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct Hertz(pub u32);
+
+impl Hertz {
+    /// Create a frequency from a value in kHz. Values above ``u32::MAX`` Hz saturate at ``u32::MAX`` Hz
+    pub const fn from_khz(khz: u32) -> Self {
+        Hertz(khz.saturating_mul(1_000))
+    }
+
+    /// Create a frequency from a value in MHz. Values above ``u32::MAX`` Hz saturate at ``u32::MAX`` Hz
+    pub const fn from_mhz(mhz: u32) -> Self {
+        Hertz(mhz.saturating_mul(1_000_000))
+    }
+
+    /// The frequency in kHz. Fractions of a kHz are truncated
+    pub const fn as_khz(&self) -> u32 {
+        self.0 / 1_000
+    }
+
+    /// The frequency in MHz. Fractions of a MHz are truncated
+    pub const fn as_mhz(&self) -> u32 {
+        self.0 / 1_000_000
+    }
+}
+
+impl From<u32> for Hertz {
+    fn from(hz: u32) -> Self {
+        Hertz(hz)
+    }
+}
+
+impl From<Hertz> for u32 {
+    fn from(hertz: Hertz) -> Self {
+        hertz.0
+    }
+}
+
+impl fmt::Display for Hertz {
+    /// Display the frequency in the largest unit that keeps the value >= 1, e.g. "250 MHz" or "1.5 kHz"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            hz if hz >= 1_000_000 => write_scaled(f, hz, 1_000_000, 6, "MHz"),
+            hz if hz >= 1_000 => write_scaled(f, hz, 1_000, 3, "kHz"),
+            hz => write!(f, "{} Hz", hz),
+        }
+    }
+}
+
+/// A region of the memory as reported by the memory property tags
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryRegion {
+    /// The base address of the memory region
+    pub base_address: u32,
+    /// The size of the memory region in bytes
+    pub size: u32,
+}
+
+impl MemoryRegion {
+    /// The size in MiB (1024 * 1024 bytes). Fractions of a MiB are truncated
+    pub const fn size_mib(&self) -> u32 {
+        self.size >> 20
+    }
+
+    /// The size in GiB (1024 * 1024 * 1024 bytes). Fractions of a GiB are truncated
+    pub const fn size_gib(&self) -> u32 {
+        self.size >> 30
+    }
+}
+
+impl fmt::Display for MemoryRegion {
+    /// Display the size in the largest binary unit that keeps the value >= 1 followed by the base address, e.g.
+    /// "948 MiB at 0x00000000". Fractions of the unit are given with up to 2 digits and truncated.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (shift, unit) = match self.size {
+            size if size >= 1 << 30 => (30, "GiB"),
+            size if size >= 1 << 20 => (20, "MiB"),
+            size if size >= 1 << 10 => (10, "KiB"),
+            _ => (0, "B"),
+        };
+        let whole = self.size >> shift;
+        let mut fraction = ((self.size as u64 - ((whole as u64) << shift)) * 100) >> shift;
+        let mut width = 2;
+        match fraction {
+            0 => write!(f, "{} {}", whole, unit)?,
+            _ => {
+                if fraction % 10 == 0 {
+                    fraction /= 10;
+                    width -= 1;
+                }
+                write!(f, "{}.{:0width$} {}", whole, fraction, unit, width = width)?
+            }
+        }
+        write!(f, " at {:#010x}", self.base_address)
+    }
+}
+
+/// Write the value scaled down by the given divisor. The fraction is only written if it is not 0 and without
+/// trailing zeros.
+fn write_scaled(
+    f: &mut fmt::Formatter<'_>,
+    value: u32,
+    divisor: u32,
+    digits: usize,
+    unit: &str,
+) -> fmt::Result {
+    let whole = value / divisor;
+    let mut fraction = value % divisor;
+    if fraction == 0 {
+        return write!(f, "{} {}", whole, unit);
+    }
+    let mut width = digits;
+    while fraction % 10 == 0 {
+        fraction /= 10;
+        width -= 1;
+    }
+    write!(f, "{}.{:0width$} {}", whole, fraction, unit, width = width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mock, ClockId, Mailbox, PropertyTagId};
+
+    #[test]
+    fn hertz_conversions() {
+        let rate = Hertz(250_000_000);
+        assert_eq!(rate.as_khz(), 250_000);
+        assert_eq!(rate.as_mhz(), 250);
+        assert_eq!(Hertz(1_999_999).as_mhz(), 1);
+        assert_eq!(Hertz::from_mhz(1_200), Hertz(1_200_000_000));
+        assert_eq!(Hertz::from_khz(48), Hertz(48_000));
+        assert_eq!(Hertz::from_mhz(4_294), Hertz(4_294_000_000));
+        assert_eq!(Hertz::from_mhz(4_295), Hertz(u32::MAX));
+        assert_eq!(Hertz::from_khz(u32::MAX), Hertz(u32::MAX));
+    }
+
+    #[test]
+    fn hertz_display() {
+        assert_eq!(format!("{}", Hertz(250_000_000)), "250 MHz");
+        assert_eq!(format!("{}", Hertz(1_500_000)), "1.5 MHz");
+        assert_eq!(format!("{}", Hertz(48_000_500)), "48.0005 MHz");
+        assert_eq!(format!("{}", Hertz(32_768)), "32.768 kHz");
+        assert_eq!(format!("{}", Hertz(999)), "999 Hz");
+        assert_eq!(format!("{}", Hertz(0)), "0 Hz");
+    }
+
+    #[test]
+    fn memory_region_units() {
+        let region = |size| MemoryRegion {
+            base_address: 0x0,
+            size,
+        };
+        assert_eq!(region((1 << 20) - 1).size_mib(), 0);
+        assert_eq!(region(1 << 20).size_mib(), 1);
+        assert_eq!(region(948 << 20).size_mib(), 948);
+        assert_eq!(region((1 << 30) - 1).size_gib(), 0);
+        assert_eq!(region(1 << 30).size_gib(), 1);
+        assert_eq!(region(u32::MAX).size_mib(), 4095);
+        assert_eq!(region(u32::MAX).size_gib(), 3);
+    }
+
+    #[test]
+    fn memory_region_display() {
+        let region = |size| MemoryRegion {
+            base_address: 0x3B40_0000,
+            size,
+        };
+        assert_eq!(format!("{}", region(0)), "0 B at 0x3b400000");
+        assert_eq!(format!("{}", region(1023)), "1023 B at 0x3b400000");
+        assert_eq!(format!("{}", region(1 << 10)), "1 KiB at 0x3b400000");
+        assert_eq!(
+            format!("{}", region((1 << 20) - 1)),
+            "1023.99 KiB at 0x3b400000"
+        );
+        assert_eq!(format!("{}", region(76 << 20)), "76 MiB at 0x3b400000");
+        assert_eq!(format!("{}", region(3 << 29)), "1.5 GiB at 0x3b400000");
+        assert_eq!(format!("{}", region(u32::MAX)), "3.99 GiB at 0x3b400000");
+    }
+
+    #[test]
+    fn clockrate_as_hertz() {
+        mock::Firmware::new()
+            .respond(PropertyTagId::ClockrateGet, &[0x4, 250_000_000])
+            .on(PropertyTagId::ClockrateSet, |values| {
+                // grant the next supported rate
+                values[1] = values[1] / 100_000_000 * 100_000_000;
+                Some(8)
+            })
+            .install();
+        let mut mb = Mailbox::new();
+        assert_eq!(
+            mb.get_clockrate_hz(ClockId::Core).unwrap(),
+            Hertz::from_mhz(250)
+        );
+        assert_eq!(
+            mb.set_clockrate_hz(ClockId::Arm, Hertz::from_mhz(1_234))
+                .unwrap(),
+            Hertz::from_mhz(1_200)
+        );
+    }
+}